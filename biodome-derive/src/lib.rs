@@ -0,0 +1,449 @@
+//! Derive macros for [biodome](https://docs.rs/biodome). Enable the
+//! `derive` feature of biodome to use them; they are re-exported from
+//! there.
+
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{parse_macro_input, Data, DataEnum, DeriveInput, Error, Expr, Fields, Lit, LitStr, Meta};
+
+/// Read a fieldless enum from an env var by variant name, ignoring
+/// case, so that `MODE=staging` gives `Mode::Staging`.
+///
+/// This implements `TryFromEnv`, so the enum works with `biodome()`,
+/// and `FromTomlValue`, so it also works inside collections. Values
+/// that don't name a variant fail with an error that suggests the
+/// closest names, like "did you mean 'staging'?", or lists the
+/// accepted names when none is close.
+#[proc_macro_derive(FromEnvEnum)]
+pub fn derive_from_env_enum(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    from_env_enum(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn from_env_enum(input: DeriveInput) -> Result<proc_macro2::TokenStream, Error> {
+    let Data::Enum(data) = &input.data else {
+        return Err(Error::new_spanned(
+            &input.ident,
+            "FromEnvEnum can only be derived for enums",
+        ));
+    };
+    let mut variants = Vec::new();
+    for variant in &data.variants {
+        if !matches!(variant.fields, Fields::Unit) {
+            return Err(Error::new_spanned(
+                variant,
+                "FromEnvEnum variants can't have fields",
+            ));
+        }
+        variants.push(&variant.ident);
+    }
+    let names: Vec<String> = variants
+        .iter()
+        .map(|v| v.to_string().to_lowercase())
+        .collect();
+    let expected = format!("expected one of: {}", names.join(", "));
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::biodome::TryFromEnv<Self> for #ident #ty_generics #where_clause {
+            type Error = ::std::string::String;
+
+            fn try_from_env(value: &str) -> ::std::result::Result<Self, Self::Error> {
+                let value = value.trim();
+                #(
+                    if value.eq_ignore_ascii_case(#names) {
+                        return ::std::result::Result::Ok(#ident::#variants);
+                    }
+                )*
+                let suggestions = ::biodome::suggest(value, &[#(#names),*]);
+                if suggestions.is_empty() {
+                    return ::std::result::Result::Err(#expected.to_string());
+                }
+                let quoted: ::std::vec::Vec<::std::string::String> =
+                    suggestions.iter().map(|s| format!("'{}'", s)).collect();
+                ::std::result::Result::Err(format!("did you mean {}?", quoted.join(" or ")))
+            }
+
+            fn to_env(value: &Self) -> ::std::option::Option<::std::string::String> {
+                let name = match value {
+                    #( #ident::#variants => #names, )*
+                };
+                ::std::option::Option::Some(name.to_string())
+            }
+        }
+
+        impl #impl_generics ::biodome::FromTomlValue for #ident #ty_generics #where_clause {
+            fn from_toml(
+                value: &::biodome::toml::Value,
+            ) -> ::std::result::Result<Self, &'static str> {
+                let value = value.as_str().ok_or("expected a string")?;
+                <Self as ::biodome::TryFromEnv<Self>>::try_from_env(value).map_err(|_err| #expected)
+            }
+
+            fn to_toml(&self) -> ::std::option::Option<::biodome::toml::Value> {
+                <Self as ::biodome::TryFromEnv<Self>>::to_env(self)
+                    .map(::biodome::toml::Value::String)
+            }
+        }
+    })
+}
+
+/// Implement `Settings` for a struct with named fields, reading each
+/// field from the env var with the field's name in upper case.
+///
+/// Doc comments on the fields become the descriptions of their env
+/// vars, for the documentation that biodome generates. Fields can be
+/// adjusted with `#[biodome(...)]`:
+///
+/// - `default = <expr>`: the value used when the env var isn't set.
+///   Without it, the field's `Default` is used.
+/// - `default_from = "<expr>"`: like `default`, but the expression
+///   can use the fields declared before this one, as they were read,
+///   e.g. `default_from = "base_timeout * 2"`. A default that follows
+///   another field stays consistent when that field is set.
+/// - `name = "KEY"`: read the env var "KEY" instead.
+/// - `flatten`: the field is a struct of settings of its own, such as
+///   a `TlsConfig` shared between services, read in the same scope.
+///   Its type must derive `Settings` too.
+/// - `flatten, prefix = "TLS_"`: as `flatten`, with the keys of the
+///   inner struct under "TLS_".
+///
+/// The struct also gets a `json_schema()` function, which describes
+/// its env vars as a JSON Schema for deployment tooling.
+///
+/// It can also be derived for an enum, where an env var named with
+/// `#[biodome(tag = "KEY")]` selects the variant, by its name in any
+/// case. Each variant is either a unit variant, or holds a struct that
+/// derives `Settings`, read under the variant's name in upper case,
+/// so that `STORAGE_BACKEND=s3` reads `STORAGE_S3_*`. Variants can be
+/// adjusted with `#[biodome(...)]`:
+///
+/// - `default`: the variant used when the env var isn't set. Without
+///   it, the first variant is used.
+/// - `prefix = "AWS_"`: read the struct under "AWS_" instead.
+///
+/// ```rust,ignore
+/// #[derive(biodome::Settings)]
+/// #[biodome(tag = "BACKEND")]
+/// enum Storage {
+///     S3(S3Config),
+///     #[biodome(default)]
+///     Local(LocalConfig),
+///     Memory,
+/// }
+///
+/// #[derive(biodome::Settings)]
+/// struct TlsConfig {
+///     cert: std::path::PathBuf,
+///     key: std::path::PathBuf,
+/// }
+///
+/// #[derive(biodome::Settings)]
+/// struct ServerSettings {
+///     /// Port the HTTP server listens on.
+///     #[biodome(default = 8080)]
+///     port: u16,
+///     #[biodome(name = "RUST_LOG", default = "info")]
+///     log_filter: String,
+///     #[biodome(flatten, prefix = "TLS_")]
+///     tls: TlsConfig,
+/// }
+/// ```
+#[proc_macro_derive(Settings, attributes(biodome))]
+pub fn derive_settings(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    settings(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+/// What the attributes on one field of a `Settings` struct say.
+struct FieldOptions {
+    name: String,
+    default: Option<Expr>,
+    default_from: Option<Expr>,
+    doc: Option<String>,
+    flatten: bool,
+    prefix: Option<String>,
+}
+
+impl FieldOptions {
+    fn parse(field: &syn::Field) -> Result<Self, Error> {
+        let ident = field.ident.as_ref().expect("named field");
+        let mut options = FieldOptions {
+            name: ident.to_string().trim_start_matches("r#").to_uppercase(),
+            default: None,
+            default_from: None,
+            doc: None,
+            flatten: false,
+            prefix: None,
+        };
+        let mut renamed = false;
+        let mut doc_lines = Vec::new();
+        for attr in &field.attrs {
+            if attr.path().is_ident("doc") {
+                if let Meta::NameValue(nv) = &attr.meta {
+                    if let Expr::Lit(lit) = &nv.value {
+                        if let Lit::Str(s) = &lit.lit {
+                            doc_lines.push(s.value().trim().to_string());
+                        }
+                    }
+                }
+            } else if attr.path().is_ident("biodome") {
+                attr.parse_nested_meta(|meta| {
+                    if meta.path.is_ident("default") {
+                        options.default = Some(meta.value()?.parse()?);
+                    } else if meta.path.is_ident("default_from") {
+                        let expr = meta.value()?.parse::<LitStr>()?;
+                        options.default_from = Some(expr.parse()?);
+                    } else if meta.path.is_ident("name") {
+                        options.name = meta.value()?.parse::<LitStr>()?.value();
+                        renamed = true;
+                    } else if meta.path.is_ident("flatten") {
+                        options.flatten = true;
+                    } else if meta.path.is_ident("prefix") {
+                        options.prefix = Some(meta.value()?.parse::<LitStr>()?.value());
+                    } else {
+                        return Err(meta.error("unknown biodome attribute"));
+                    }
+                    Ok(())
+                })?;
+            }
+        }
+        if options.default.is_some() && options.default_from.is_some() {
+            return Err(Error::new_spanned(
+                field,
+                "a field can't have both default and default_from",
+            ));
+        }
+        let defaulted = options.default.is_some() || options.default_from.is_some();
+        if options.flatten && (renamed || defaulted) {
+            return Err(Error::new_spanned(
+                field,
+                "a flattened field can't have a name or a default",
+            ));
+        }
+        if options.prefix.is_some() && !options.flatten {
+            return Err(Error::new_spanned(
+                field,
+                "prefix can only be used with flatten",
+            ));
+        }
+        let doc = doc_lines.join(" ").trim().to_string();
+        if !doc.is_empty() {
+            options.doc = Some(doc);
+        }
+        Ok(options)
+    }
+}
+
+fn settings(input: DeriveInput) -> Result<proc_macro2::TokenStream, Error> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(Error::new_spanned(
+                    &input.ident,
+                    "Settings can only be derived for structs with named fields",
+                ))
+            }
+        },
+        Data::Enum(data) => return tagged_settings(&input, data),
+        _ => {
+            return Err(Error::new_spanned(
+                &input.ident,
+                "Settings can only be derived for structs and enums",
+            ))
+        }
+    };
+    let mut describes = Vec::new();
+    let mut lets = Vec::new();
+    let mut idents = Vec::new();
+    let mut properties = Vec::new();
+    for field in fields {
+        let options = FieldOptions::parse(field)?;
+        let ident = &field.ident;
+        let ty = &field.ty;
+        idents.push(ident);
+        if options.flatten {
+            let prefix = options.prefix.as_deref().unwrap_or("");
+            lets.push(quote! {
+                let #ident = __biodome_scope.nested(#prefix).load::<#ty>();
+            });
+            properties.push(quote! {
+                ::biodome::schema::flatten(
+                    &mut __biodome_properties,
+                    #prefix,
+                    <#ty>::json_schema(),
+                );
+            });
+            continue;
+        }
+        let name = &options.name;
+        if let Some(doc) = &options.doc {
+            describes.push(quote! { __biodome_scope.describe(#name, #doc); });
+        }
+        // String literals are converted, so that `default = "info"`
+        // works for a `String` field.
+        let default = match (&options.default, &options.default_from) {
+            (Some(Expr::Lit(lit)), _) if matches!(lit.lit, Lit::Str(_)) => {
+                quote! { ::std::convert::From::from(#lit) }
+            }
+            (Some(expr), _) | (None, Some(expr)) => quote! { #expr },
+            (None, None) => quote! { ::std::default::Default::default() },
+        };
+        lets.push(quote! {
+            let #ident: #ty = {
+                let default: #ty = #default;
+                __biodome_scope.biodome::<#ty, #ty>(#name, default)
+            };
+        });
+        let description = match &options.doc {
+            Some(doc) => quote! { ::std::option::Option::Some(#doc) },
+            None => quote! { ::std::option::Option::None },
+        };
+        // The defaults are bound to the field names, so that a
+        // `default_from` sees the defaults of the fields it uses.
+        properties.push(quote! {
+            let #ident: #ty = #default;
+            __biodome_properties.insert(
+                #name.to_string(),
+                ::biodome::schema::property::<#ty>(#name, &#ident, #description),
+            );
+        });
+    }
+
+    let ident = &input.ident;
+    let title = ident.to_string();
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::biodome::Settings for #ident #ty_generics #where_clause {
+            fn load(__biodome_scope: &::biodome::Scope) -> Self {
+                #( #describes )*
+                #( #lets )*
+                #ident {
+                    #( #idents, )*
+                }
+            }
+        }
+
+        impl #impl_generics #ident #ty_generics #where_clause {
+            /// A JSON Schema describing the env vars of these
+            /// settings, without their prefix.
+            pub fn json_schema() -> ::biodome::serde_json::Value {
+                let mut __biodome_properties = ::biodome::serde_json::Map::new();
+                #( #properties )*
+                ::biodome::schema::object(#title, __biodome_properties)
+            }
+        }
+    })
+}
+
+/// The settings for an enum, where the env var named by the `tag`
+/// attribute selects the variant.
+fn tagged_settings(
+    input: &DeriveInput,
+    data: &DataEnum,
+) -> Result<proc_macro2::TokenStream, Error> {
+    let mut tag = None;
+    for attr in &input.attrs {
+        if attr.path().is_ident("biodome") {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("tag") {
+                    tag = Some(meta.value()?.parse::<LitStr>()?.value());
+                    Ok(())
+                } else {
+                    Err(meta.error("unknown biodome attribute"))
+                }
+            })?;
+        }
+    }
+    let Some(tag) = tag else {
+        return Err(Error::new_spanned(
+            &input.ident,
+            "Settings for an enum need #[biodome(tag = \"KEY\")]",
+        ));
+    };
+
+    let ident = &input.ident;
+    let mut names = Vec::new();
+    let mut default = None;
+    let mut arms = Vec::new();
+    let mut properties = Vec::new();
+    for variant in &data.variants {
+        let variant_ident = &variant.ident;
+        let name = variant_ident.to_string().to_lowercase();
+        let mut prefix = format!("{}_", variant_ident.to_string().to_uppercase());
+        for attr in &variant.attrs {
+            if attr.path().is_ident("biodome") {
+                attr.parse_nested_meta(|meta| {
+                    if meta.path.is_ident("default") {
+                        default = Some(name.clone());
+                    } else if meta.path.is_ident("prefix") {
+                        prefix = meta.value()?.parse::<LitStr>()?.value();
+                    } else {
+                        return Err(meta.error("unknown biodome attribute"));
+                    }
+                    Ok(())
+                })?;
+            }
+        }
+        let value = match &variant.fields {
+            Fields::Unit => quote! { #ident::#variant_ident },
+            Fields::Unnamed(fields) if fields.unnamed.len() == 1 => {
+                let ty = &fields.unnamed[0].ty;
+                properties.push(quote! {
+                    ::biodome::schema::flatten(
+                        &mut __biodome_properties,
+                        #prefix,
+                        <#ty>::json_schema(),
+                    );
+                });
+                quote! { #ident::#variant_ident(__biodome_scope.nested(#prefix).load::<#ty>()) }
+            }
+            _ => {
+                return Err(Error::new_spanned(
+                    variant,
+                    "Settings variants must have no fields, or one unnamed field",
+                ))
+            }
+        };
+        arms.push(quote! { #name => #value, });
+        names.push(name);
+    }
+    let Some(first) = names.first() else {
+        return Err(Error::new_spanned(ident, "Settings enums need variants"));
+    };
+    let default = default.unwrap_or_else(|| first.clone());
+
+    let title = ident.to_string();
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::biodome::Settings for #ident #ty_generics #where_clause {
+            fn load(__biodome_scope: &::biodome::Scope) -> Self {
+                let variant = __biodome_scope.one_of_ignore_case(#tag, #default, &[#( #names ),*]);
+                match variant.as_str() {
+                    #( #arms )*
+                    _ => unreachable!("one_of returned {}", variant),
+                }
+            }
+        }
+
+        impl #impl_generics #ident #ty_generics #where_clause {
+            /// A JSON Schema describing the env vars of these
+            /// settings, for every variant, without their prefix.
+            pub fn json_schema() -> ::biodome::serde_json::Value {
+                let mut __biodome_properties = ::biodome::serde_json::Map::new();
+                __biodome_properties.insert(
+                    #tag.to_string(),
+                    ::biodome::schema::tag(#tag, #default, &[#( #names ),*]),
+                );
+                #( #properties )*
+                ::biodome::schema::object(#title, __biodome_properties)
+            }
+        }
+    })
+}
@@ -0,0 +1,115 @@
+use crate::registry::{self, truncate, RegistryEntry};
+use crate::{profile, GitInfo};
+
+const MAX_VALUE_LEN: usize = 40;
+
+/// A few lines to log when a service starts: its name and version,
+/// the active profile, and every setting read so far, marked with
+/// where its value came from. Sensitive values are redacted, as in
+/// [`crash_context`](crate::crash_context).
+///
+/// The version is the commit the service was built from, as found by
+/// [`GitInfo::resolve`].
+///
+/// ```rust
+/// use biodome::{banner, biodome, Secret};
+///
+/// std::env::set_var("API_WORKERS", "16");
+/// std::env::set_var("API_TOKEN", "hunter2");
+/// let workers = biodome("API_WORKERS", 4);
+/// let port = biodome("API_PORT", 8080);
+/// let token = biodome("API_TOKEN", Secret::new(String::new()));
+/// let banner = banner("api");
+/// assert!(banner.contains("API_WORKERS = 16 (env)\n"));
+/// assert!(banner.contains("API_PORT    = 8080 (default)\n"));
+/// assert!(!banner.contains("hunter2"));
+/// eprint!("{}", banner);
+/// ```
+pub fn banner(service_name: &str) -> String {
+    let entries = registry::registry();
+    let git = GitInfo::resolve();
+    render(
+        service_name,
+        git.short_sha(),
+        profile::profile().as_deref(),
+        &entries,
+    )
+}
+
+fn render(
+    service_name: &str,
+    version: Option<&str>,
+    profile: Option<&str>,
+    entries: &[RegistryEntry],
+) -> String {
+    let mut out = format!(
+        "== {} {} (profile: {}) ==\n",
+        service_name,
+        version.unwrap_or("unknown"),
+        profile.unwrap_or("none")
+    );
+    let width = entries.iter().map(|e| e.key.len()).max().unwrap_or(0);
+    for entry in entries {
+        let origin = if entry.from_env() { "env" } else { "default" };
+        let value = match entry.value.as_ref().or(entry.default.as_ref()) {
+            _ if entry.redacted() => "[REDACTED]".to_string(),
+            Some(v) => truncate(v, MAX_VALUE_LEN),
+            None => "?".to_string(),
+        };
+        out += &format!(
+            "  {:width$} = {} ({})\n",
+            entry.key,
+            value,
+            origin,
+            width = width
+        );
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_banner() {
+        let entries = vec![
+            RegistryEntry {
+                key: "PORT".to_string(),
+                type_name: "u16",
+                value: None,
+                from_env: false,
+                hashed: false,
+                default: Some("8080".to_string()),
+                sensitive: false,
+                text: false,
+                required: false,
+                choices: vec![],
+            },
+            RegistryEntry {
+                key: "DB_PASSWORD".to_string(),
+                type_name: "alloc::string::String",
+                value: Some("hunter2".to_string()),
+                from_env: true,
+                hashed: false,
+                default: None,
+                sensitive: false,
+                text: false,
+                required: false,
+                choices: vec![],
+            },
+        ];
+        assert_eq!(
+            render("api", Some("8f2c1e0"), Some("prod"), &entries),
+            concat!(
+                "== api 8f2c1e0 (profile: prod) ==\n",
+                "  PORT        = 8080 (default)\n",
+                "  DB_PASSWORD = [REDACTED] (env)\n",
+            )
+        );
+        assert_eq!(
+            render("api", None, None, &[]),
+            "== api unknown (profile: none) ==\n"
+        );
+    }
+}
@@ -0,0 +1,59 @@
+use std::fmt;
+
+use crate::{rawconv, TryFromEnv, TryIntoEnv};
+
+/// A number of bytes, parsed from human-friendly sizes like
+/// `512`, `10MB` or `8MiB`.
+///
+/// Decimal units (`KB`, `MB`, `GB`, `TB`, `PB`) are powers of 1000,
+/// binary units (`KiB`, `MiB`, `GiB`, `TiB`, `PiB`) are powers of 1024.
+/// Units are case-insensitive and may be separated from the number
+/// by whitespace. A bare number is a count of bytes.
+///
+/// ```rust
+/// use biodome::{biodome, ByteSize};
+///
+/// std::env::set_var("MAX_BODY", "8MiB");
+/// let MAX_BODY = biodome("MAX_BODY", ByteSize(1024));
+/// assert_eq!(MAX_BODY.as_u64(), 8 * 1024 * 1024);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct ByteSize(pub u64);
+
+impl ByteSize {
+    pub fn as_u64(&self) -> u64 {
+        self.0
+    }
+}
+
+impl From<ByteSize> for u64 {
+    fn from(size: ByteSize) -> u64 {
+        size.0
+    }
+}
+
+impl fmt::Display for ByteSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}B", self.0)
+    }
+}
+
+impl TryFromEnv<Self> for ByteSize {
+    type Error = &'static str;
+
+    fn try_from_env(value: &str) -> Result<Self, Self::Error> {
+        rawconv::to_bytes(value).map(ByteSize)
+    }
+
+    fn to_env(value: &Self) -> Option<String> {
+        value.try_into_env().ok()
+    }
+}
+
+impl TryIntoEnv<String> for ByteSize {
+    type Error = &'static str;
+
+    fn try_into_env(&self) -> Result<String, Self::Error> {
+        Ok(self.0.to_string())
+    }
+}
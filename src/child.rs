@@ -0,0 +1,106 @@
+use std::process::Command;
+
+use crate::source;
+
+/// Builds the environment for a child process from this process's
+/// settings, renaming keys where the child expects different names.
+///
+/// ```rust
+/// use biodome::ChildEnv;
+/// use std::process::Command;
+///
+/// std::env::set_var("DB_URL", "postgres://db/app");
+/// let child_env = ChildEnv::new()
+///     .emit_as("DB_URL", "DATABASE_URL")
+///     .pass("LOG_LEVEL")
+///     .set("SIDECAR_MODE", "legacy");
+///
+/// let mut cmd = Command::new("sidecar");
+/// child_env.apply(&mut cmd);
+/// assert_eq!(child_env.vars()[0], ("DATABASE_URL".to_string(), "postgres://db/app".to_string()));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ChildEnv {
+    mappings: Vec<(Mapping, String)>,
+}
+
+#[derive(Debug, Clone)]
+enum Mapping {
+    Key(String),
+    Value(String),
+}
+
+impl ChildEnv {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Pass "key" to the child under the same name.
+    pub fn pass(self, key: &str) -> Self {
+        self.emit_as(key, key)
+    }
+
+    /// Pass the value of "key" to the child as "name". If "key" is
+    /// not set when the environment is built, "name" is left alone.
+    pub fn emit_as(mut self, key: &str, name: &str) -> Self {
+        self.mappings
+            .push((Mapping::Key(key.to_string()), name.to_string()));
+        self
+    }
+
+    /// Set "name" to "value" in the child.
+    pub fn set(mut self, name: &str, value: &str) -> Self {
+        self.mappings
+            .push((Mapping::Value(value.to_string()), name.to_string()));
+        self
+    }
+
+    /// The variables the child will get, in the order they were added.
+    pub fn vars(&self) -> Vec<(String, String)> {
+        self.mappings
+            .iter()
+            .filter_map(|(mapping, name)| {
+                let value = match mapping {
+                    Mapping::Key(key) => source::get(key)?,
+                    Mapping::Value(value) => value.clone(),
+                };
+                Some((name.clone(), value))
+            })
+            .collect()
+    }
+
+    /// Add the variables to the environment of "cmd".
+    pub fn apply<'a>(&self, cmd: &'a mut Command) -> &'a mut Command {
+        cmd.envs(self.vars())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn mapping() {
+        env::set_var("CHILD_DB_URL", "postgres://db/app");
+        env::remove_var("CHILD_MISSING");
+        let child_env = ChildEnv::new()
+            .emit_as("CHILD_DB_URL", "DATABASE_URL")
+            .pass("CHILD_DB_URL")
+            .pass("CHILD_MISSING")
+            .set("MODE", "legacy");
+        assert_eq!(
+            child_env.vars(),
+            vec![
+                ("DATABASE_URL".to_string(), "postgres://db/app".to_string()),
+                ("CHILD_DB_URL".to_string(), "postgres://db/app".to_string()),
+                ("MODE".to_string(), "legacy".to_string()),
+            ]
+        );
+        let mut cmd = Command::new("true");
+        child_env.apply(&mut cmd);
+        let envs: Vec<_> = cmd.get_envs().collect();
+        assert_eq!(envs.len(), 3);
+        env::remove_var("CHILD_DB_URL");
+    }
+}
@@ -0,0 +1,159 @@
+//! Find out which CI system a build is running on, and what it is
+//! building.
+//!
+//! Each CI system names its env vars differently; [`detect`] reads
+//! them into the same fields for all of them.
+
+use crate::source;
+
+/// A CI system.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CiSystem {
+    GitHubActions,
+    GitLab,
+    Jenkins,
+    Buildkite,
+}
+
+/// What the current CI build is building. Fields the CI system does
+/// not provide are `None`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ci {
+    pub system: CiSystem,
+    /// For pull requests, the branch being merged.
+    pub branch: Option<String>,
+    pub commit: Option<String>,
+    /// The pull request or merge request number, if the build is for
+    /// one.
+    pub pr_number: Option<u64>,
+    pub build_url: Option<String>,
+}
+
+/// The CI build this process is part of, or `None` outside of CI.
+///
+/// ```rust
+/// use biodome::ci::{self, CiSystem};
+///
+/// std::env::set_var("BUILDKITE", "true");
+/// std::env::set_var("BUILDKITE_BRANCH", "fix-timeouts");
+/// std::env::set_var("BUILDKITE_PULL_REQUEST", "false");
+/// let ci = ci::detect().unwrap();
+/// assert_eq!(ci.system, CiSystem::Buildkite);
+/// assert_eq!(ci.branch.as_deref(), Some("fix-timeouts"));
+/// assert_eq!(ci.pr_number, None);
+/// ```
+pub fn detect() -> Option<Ci> {
+    detect_with(&source::get)
+}
+
+type Lookup<'a> = &'a dyn Fn(&str) -> Option<String>;
+
+fn detect_with(lookup: Lookup) -> Option<Ci> {
+    // An empty value counts as unset.
+    let get = |key: &str| lookup(key).filter(|v| !v.is_empty());
+    if get("GITHUB_ACTIONS").is_some() {
+        Some(github(&get))
+    } else if get("GITLAB_CI").is_some() {
+        Some(Ci {
+            system: CiSystem::GitLab,
+            branch: get("CI_MERGE_REQUEST_SOURCE_BRANCH_NAME")
+                .or_else(|| get("CI_COMMIT_BRANCH"))
+                .or_else(|| get("CI_COMMIT_REF_NAME")),
+            commit: get("CI_COMMIT_SHA"),
+            pr_number: number(get("CI_MERGE_REQUEST_IID")),
+            build_url: get("CI_PIPELINE_URL"),
+        })
+    } else if get("JENKINS_URL").is_some() {
+        Some(Ci {
+            system: CiSystem::Jenkins,
+            branch: get("CHANGE_BRANCH")
+                .or_else(|| get("BRANCH_NAME"))
+                .or_else(|| get("GIT_BRANCH")),
+            commit: get("GIT_COMMIT"),
+            pr_number: number(get("CHANGE_ID")),
+            build_url: get("BUILD_URL"),
+        })
+    } else if get("BUILDKITE").is_some() {
+        Some(Ci {
+            system: CiSystem::Buildkite,
+            branch: get("BUILDKITE_BRANCH"),
+            commit: get("BUILDKITE_COMMIT"),
+            // This is "false" for builds that aren't for a pull request.
+            pr_number: number(get("BUILDKITE_PULL_REQUEST")),
+            build_url: get("BUILDKITE_BUILD_URL"),
+        })
+    } else {
+        None
+    }
+}
+
+fn github(get: Lookup) -> Ci {
+    // For pull requests, GITHUB_REF is like `refs/pull/42/merge`.
+    let pr_number = get("GITHUB_REF").and_then(|r| {
+        let rest = r.strip_prefix("refs/pull/")?;
+        rest.split('/').next()?.parse().ok()
+    });
+    let build_url = match (
+        get("GITHUB_SERVER_URL"),
+        get("GITHUB_REPOSITORY"),
+        get("GITHUB_RUN_ID"),
+    ) {
+        (Some(server), Some(repo), Some(run)) => {
+            Some(format!("{}/{}/actions/runs/{}", server, repo, run))
+        }
+        _ => None,
+    };
+    Ci {
+        system: CiSystem::GitHubActions,
+        branch: get("GITHUB_HEAD_REF").or_else(|| get("GITHUB_REF_NAME")),
+        commit: get("GITHUB_SHA"),
+        pr_number,
+        build_url,
+    }
+}
+
+fn number(value: Option<String>) -> Option<u64> {
+    value?.trim().parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn systems() {
+        let vars = HashMap::from([
+            ("GITHUB_ACTIONS", "true"),
+            ("GITHUB_REF", "refs/pull/42/merge"),
+            ("GITHUB_REF_NAME", "42/merge"),
+            ("GITHUB_HEAD_REF", "fix-timeouts"),
+            ("GITHUB_SHA", "8f2c1e0"),
+            ("GITHUB_SERVER_URL", "https://github.com"),
+            ("GITHUB_REPOSITORY", "cjrh/biodome-rs"),
+            ("GITHUB_RUN_ID", "1234"),
+        ]);
+        let ci = detect_with(&|key| vars.get(key).map(|v| v.to_string()));
+        assert_eq!(
+            ci,
+            Some(Ci {
+                system: CiSystem::GitHubActions,
+                branch: Some("fix-timeouts".to_string()),
+                commit: Some("8f2c1e0".to_string()),
+                pr_number: Some(42),
+                build_url: Some("https://github.com/cjrh/biodome-rs/actions/runs/1234".to_string()),
+            })
+        );
+        assert_eq!(detect_with(&|_| None), None);
+        let ci = detect_with(&|key| match key {
+            "JENKINS_URL" => Some("https://ci.internal/".to_string()),
+            "BRANCH_NAME" => Some("main".to_string()),
+            "CHANGE_ID" => Some(String::new()),
+            _ => None,
+        })
+        .unwrap();
+        assert_eq!(ci.system, CiSystem::Jenkins);
+        assert_eq!(ci.branch.as_deref(), Some("main"));
+        assert_eq!(ci.pr_number, None);
+    }
+}
@@ -0,0 +1,123 @@
+//! Env vars as the defaults of [clap](https://docs.rs/clap) arguments.
+//!
+//! [`ArgExt::biodome_default`] gives an argument the value of an env
+//! var as its default, read the way [`biodome`](crate::biodome) reads
+//! it, so the usual precedence follows: a flag on the command line
+//! wins over the env var, which wins over the built-in default.
+//! [`parse`] reads an argument with biodome's conversions, for the
+//! structured types that clap can't parse itself.
+//!
+//! ```rust
+//! use std::collections::HashMap;
+//! use biodome::clap::{parse, ArgExt};
+//! use clap::{Arg, ArgAction, Command};
+//!
+//! std::env::set_var("CLAP_WORKERS", "8");
+//! std::env::set_var("CLAP_VERBOSE", "yes");
+//! std::env::set_var("CLAP_LIMITS", "{ rate = 10 }");
+//!
+//! let cmd = Command::new("app")
+//!     .arg(
+//!         Arg::new("workers")
+//!             .long("workers")
+//!             .value_parser(parse::<u8>)
+//!             .biodome_default("CLAP_WORKERS", "4"),
+//!     )
+//!     .arg(
+//!         Arg::new("verbose")
+//!             .long("verbose")
+//!             .action(ArgAction::SetTrue)
+//!             .biodome_default("CLAP_VERBOSE", "false"),
+//!     )
+//!     .arg(
+//!         Arg::new("limits")
+//!             .long("limits")
+//!             .value_parser(parse::<HashMap<String, u32>>)
+//!             .biodome_default("CLAP_LIMITS", "{}"),
+//!     );
+//!
+//! let matches = cmd.get_matches_from(["app", "--workers", "16"]);
+//! assert_eq!(matches.get_one::<u8>("workers"), Some(&16));
+//! assert!(matches.get_flag("verbose"));
+//! assert_eq!(matches.get_one::<HashMap<String, u32>>("limits").unwrap()["rate"], 10);
+//! ```
+
+use ::clap::{Arg, ArgAction};
+
+use crate::{profile, rawconv, source, TryFromEnv};
+
+/// Env var defaults for clap arguments.
+pub trait ArgExt {
+    /// Use the value of the env var "key" as the default, or "default"
+    /// if it is not set. For flags, the value is read as a bool, so
+    /// `yes` and `on` turn them on.
+    fn biodome_default(self, key: &str, default: &str) -> Self;
+}
+
+impl ArgExt for Arg {
+    fn biodome_default(self, key: &str, default: &str) -> Self {
+        let Some(value) = source::value(key).map(profile::select) else {
+            return self.default_value(default.to_string());
+        };
+        let value = match self.get_action() {
+            ArgAction::SetTrue | ArgAction::SetFalse => rawconv::to_bool(&value).to_string(),
+            _ => value,
+        };
+        self.default_value(value)
+    }
+}
+
+/// Parse an argument as [`biodome`](crate::biodome) parses an env var,
+/// for use as a clap value parser.
+pub fn parse<T: TryFromEnv<T>>(value: &str) -> Result<T, String>
+where
+    T::Error: std::fmt::Debug,
+{
+    T::try_from_env(value).map_err(|err| format!("{:?}", err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::clap::Command;
+
+    fn command() -> Command {
+        Command::new("test")
+            .arg(
+                Arg::new("ports")
+                    .long("ports")
+                    .value_parser(parse::<Vec<u16>>)
+                    .biodome_default("CLAP_T_PORTS", "[80]"),
+            )
+            .arg(
+                Arg::new("quiet")
+                    .long("quiet")
+                    .action(ArgAction::SetTrue)
+                    .biodome_default("CLAP_T_QUIET", "false"),
+            )
+    }
+
+    #[test]
+    fn precedence() {
+        let matches = command().get_matches_from(["test"]);
+        assert_eq!(matches.get_one::<Vec<u16>>("ports"), Some(&vec![80]));
+        assert!(!matches.get_flag("quiet"));
+
+        std::env::set_var("CLAP_T_PORTS", "[81, 82]");
+        std::env::set_var("CLAP_T_QUIET", "on");
+        let matches = command().get_matches_from(["test"]);
+        assert_eq!(matches.get_one::<Vec<u16>>("ports"), Some(&vec![81, 82]));
+        assert!(matches.get_flag("quiet"));
+
+        let matches = command().get_matches_from(["test", "--ports", "[83]"]);
+        assert_eq!(matches.get_one::<Vec<u16>>("ports"), Some(&vec![83]));
+
+        let matches = command().get_matches_from(["test", "--ports", "83,84"]);
+        assert_eq!(matches.get_one::<Vec<u16>>("ports"), Some(&vec![83, 84]));
+
+        let err = command()
+            .try_get_matches_from(["test", "--ports", "http"])
+            .unwrap_err();
+        assert_eq!(err.kind(), ::clap::error::ErrorKind::ValueValidation);
+    }
+}
@@ -0,0 +1,132 @@
+use ::config::{ConfigError, Map, Source, Value, ValueKind};
+
+use crate::{rawconv, source};
+
+/// A [config-rs](https://docs.rs/config) source of the env vars that
+/// start with a prefix, for teams that already load their settings
+/// with `config::Config`.
+///
+/// Keys and values are read as for the figment provider: keys are the
+/// names of the env vars without the prefix, in lower case, and values
+/// have `${VAR}` references expanded and the part for the active
+/// [profile](crate::set_profile) picked out. A `{KEY}_FILE` var gives
+/// the contents of its file as "key". Values are then parsed in the
+/// TOML subset, so lists and inline tables work. Anything else is a
+/// string, which config-rs reads as a bool when it is a word like
+/// `on` or `no`. As the types of the fields aren't known here, lists
+/// must have their brackets: `a:80,b:80` is a string.
+///
+/// ```rust
+/// use biodome::BiodomeSource;
+/// use config::Config;
+///
+/// std::env::set_var("CONFIG_PORT", "8000");
+/// std::env::set_var("CONFIG_DEBUG", "on");
+/// std::env::set_var("CONFIG_PEERS", r#"["a:80", "b:80"]"#);
+///
+/// let config = Config::builder()
+///     .add_source(BiodomeSource::prefixed("CONFIG_"))
+///     .build()
+///     .unwrap();
+/// assert_eq!(config.get_int("port").unwrap(), 8000);
+/// assert!(config.get_bool("debug").unwrap());
+/// assert_eq!(config.get::<Vec<String>>("peers").unwrap(), ["a:80", "b:80"]);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct BiodomeSource {
+    prefix: String,
+}
+
+impl BiodomeSource {
+    /// Provide all env vars.
+    pub fn new() -> Self {
+        BiodomeSource::default()
+    }
+
+    /// Provide the env vars that start with "prefix".
+    pub fn prefixed(prefix: &str) -> Self {
+        BiodomeSource {
+            prefix: prefix.to_string(),
+        }
+    }
+}
+
+impl Source for BiodomeSource {
+    fn clone_into_box(&self) -> Box<dyn Source + Send + Sync> {
+        Box::new(self.clone())
+    }
+
+    fn collect(&self) -> Result<Map<String, Value>, ConfigError> {
+        let values = source::prefixed_values(&self.prefix)
+            .map_err(|err| ConfigError::Foreign(Box::new(err)))?;
+        Ok(values
+            .into_iter()
+            .map(|(key, raw)| {
+                let origin = format!("env var {}{}", self.prefix, key.to_uppercase());
+                let value = from_toml(&origin, rawconv::to_untyped(&raw));
+                (key, value)
+            })
+            .collect())
+    }
+}
+
+fn from_toml(origin: &String, value: toml::Value) -> Value {
+    let kind = match value {
+        toml::Value::String(s) => ValueKind::String(s),
+        toml::Value::Integer(i) => ValueKind::I64(i),
+        toml::Value::Float(f) => ValueKind::Float(f),
+        toml::Value::Boolean(b) => ValueKind::Boolean(b),
+        toml::Value::Datetime(d) => ValueKind::String(d.to_string()),
+        toml::Value::Array(items) => {
+            ValueKind::Array(items.into_iter().map(|v| from_toml(origin, v)).collect())
+        }
+        toml::Value::Table(table) => ValueKind::Table(
+            table
+                .into_iter()
+                .map(|(k, v)| (k, from_toml(origin, v)))
+                .collect(),
+        ),
+    };
+    Value::new(Some(origin), kind)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::config::Config;
+    use serde::Deserialize;
+    use std::collections::HashMap;
+
+    #[derive(Debug, Deserialize)]
+    struct Settings {
+        name: String,
+        limits: HashMap<String, u32>,
+        verbose: bool,
+        compression: String,
+        db_password: Option<String>,
+    }
+
+    #[test]
+    fn source() {
+        let path = std::env::temp_dir().join(format!("biodome-config-{}", std::process::id()));
+        std::fs::write(&path, "s3cr3t\n").unwrap();
+        std::env::set_var("CONFIG_T_NAME", "api");
+        std::env::set_var("CONFIG_T_LIMITS", "{ rate = 10 }");
+        std::env::set_var("CONFIG_T_VERBOSE", "on");
+        std::env::set_var("CONFIG_T_COMPRESSION", "off");
+        std::env::set_var("CONFIG_T_DB_PASSWORD_FILE", &path);
+        let config = Config::builder()
+            .set_default("name", "default")
+            .unwrap()
+            .add_source(BiodomeSource::prefixed("CONFIG_T_"))
+            .build()
+            .unwrap();
+        let settings: Settings = config.try_deserialize().unwrap();
+        assert_eq!(settings.name, "api");
+        assert_eq!(settings.limits["rate"], 10);
+        assert!(settings.verbose);
+        assert_eq!(settings.compression, "off");
+        assert_eq!(settings.db_password.as_deref(), Some("s3cr3t"));
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,542 @@
+//! A committed contract file, `biodome.lock`, that describes every
+//! env var an application reads: its type, its default, whether it is
+//! required or sensitive, and the values it is restricted to.
+//!
+//! Writing the contract from a test that reads all the settings, and
+//! verifying it in CI, makes any change to the configuration surface
+//! show up in review as a change to the lock file.
+//!
+//! ```rust,no_run
+//! use biodome::{biodome, contract::Contract};
+//!
+//! let PORT = biodome("PORT", 8080);
+//! let TIMEOUT = biodome("TIMEOUT", 30.0);
+//!
+//! // Once, and again whenever the configuration changes:
+//! Contract::current().write("biodome.lock").unwrap();
+//!
+//! // In CI:
+//! if let Err(problems) = Contract::current().verify("biodome.lock") {
+//!     panic!("biodome.lock is out of date:\n{}", problems);
+//! }
+//! ```
+//!
+//! [`Contract::diff`] compares two contracts, for example the lock
+//! file at the last release tag (see [`Contract::from_git`]) and the
+//! current one, and lists what changed for release notes.
+//!
+//! The file is TOML, with a table for each key:
+//!
+//! ```toml
+//! [PORT]
+//! type = "i32"
+//! default = "8080"
+//! ```
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::process::Command;
+
+use toml::Value;
+
+use crate::{docs, export, registry, tomlconv};
+
+/// What the contract says about one key.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct KeySpec {
+    /// The Rust type the value is read into, without module paths,
+    /// as from [`stable_type_name`].
+    pub type_name: String,
+    /// The default, as it would be written in the env var. Defaults
+    /// of sensitive keys are never recorded.
+    pub default: Option<String>,
+    /// Whether the key has no default, and must be set.
+    pub required: bool,
+    pub sensitive: bool,
+    /// The only values allowed, or empty if any value is.
+    pub choices: Vec<String>,
+}
+
+/// The configuration surface of an application.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Contract {
+    pub keys: BTreeMap<String, KeySpec>,
+}
+
+impl Contract {
+    /// The contract for the env vars that have been read through
+    /// biodome so far. Make sure all settings have been read first.
+    pub fn current() -> Self {
+        let keys = registry::registry()
+            .into_iter()
+            .map(|entry| {
+                let sensitive = entry.redacted();
+                let spec = KeySpec {
+                    type_name: stable_type_name(entry.type_name),
+                    default: if sensitive { None } else { entry.default },
+                    required: entry.required,
+                    sensitive,
+                    choices: entry.choices,
+                };
+                (entry.key, spec)
+            })
+            .collect();
+        Contract { keys }
+    }
+
+    /// Read a contract from the text of a lock file.
+    pub fn parse(s: &str) -> Result<Self, &'static str> {
+        let doc: Value = s.parse().map_err(|_err| "parse error")?;
+        let table = doc.as_table().ok_or("expected a table")?;
+        let mut keys = BTreeMap::new();
+        for (key, spec) in table {
+            let spec = spec.as_table().ok_or("expected a table for each key")?;
+            let string = |name: &str| spec.get(name).and_then(Value::as_str).map(String::from);
+            let choices = match spec.get("choices") {
+                Some(Value::Array(items)) => items
+                    .iter()
+                    .map(|c| c.as_str().map(String::from).ok_or("expected a string"))
+                    .collect::<Result<_, _>>()?,
+                Some(_) => return Err("expected an array of choices"),
+                None => vec![],
+            };
+            let flag = |name: &str| spec.get(name).and_then(Value::as_bool).unwrap_or(false);
+            let spec = KeySpec {
+                type_name: string("type").ok_or("missing type")?,
+                default: string("default"),
+                required: flag("required"),
+                sensitive: flag("sensitive"),
+                choices,
+            };
+            keys.insert(key.clone(), spec);
+        }
+        Ok(Contract { keys })
+    }
+
+    /// Read a contract from a lock file.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let text = fs::read_to_string(path)?;
+        Contract::parse(&text).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+
+    /// Write the contract to a lock file, with [`export::write`].
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        export::write(path, &self.to_string())
+    }
+
+    /// Read a contract from a lock file as it was at a git revision,
+    /// such as a tag for the previous release.
+    pub fn from_git(rev: &str, path: &str) -> io::Result<Self> {
+        let output = Command::new("git")
+            .arg("show")
+            .arg(format!("{}:{}", rev, path))
+            .output()?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(io::Error::new(io::ErrorKind::NotFound, stderr.trim()));
+        }
+        let text = String::from_utf8_lossy(&output.stdout);
+        Contract::parse(&text).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+
+    /// Check that the lock file at "path" matches this contract. The
+    /// error lists the differences, as from [`Contract::diff`].
+    pub fn verify<P: AsRef<Path>>(&self, path: P) -> Result<(), String> {
+        let locked = Contract::load(path).map_err(|err| err.to_string())?;
+        let diff = locked.diff(self);
+        if diff.is_empty() {
+            Ok(())
+        } else {
+            Err(diff.to_string())
+        }
+    }
+
+    /// What changed from this contract to "newer", by category.
+    ///
+    /// ```rust
+    /// use biodome::contract::Contract;
+    ///
+    /// let old = Contract::parse("[PORT]\ntype = \"u16\"\ndefault = \"8080\"\n").unwrap();
+    /// let new = Contract::parse("[PORT]\ntype = \"u16\"\ndefault = \"9090\"\n").unwrap();
+    /// assert_eq!(
+    ///     old.diff(&new).to_string(),
+    ///     "### Changed defaults\n\n- `PORT`: '8080' -> '9090'\n"
+    /// );
+    /// ```
+    pub fn diff(&self, newer: &Contract) -> ContractDiff {
+        let mut diff = ContractDiff::default();
+        for (key, new) in &newer.keys {
+            let Some(old) = self.keys.get(key) else {
+                diff.added.push((key.clone(), new.clone()));
+                continue;
+            };
+            if old.type_name != new.type_name {
+                diff.changed_types.push(Change::new(
+                    key,
+                    old.type_name.clone(),
+                    new.type_name.clone(),
+                ));
+            }
+            if old.default != new.default {
+                diff.changed_defaults.push(Change::new(
+                    key,
+                    old.default.clone(),
+                    new.default.clone(),
+                ));
+            }
+            if old.choices != new.choices {
+                let rejects_old = !new.choices.is_empty()
+                    && (old.choices.is_empty()
+                        || old.choices.iter().any(|c| !new.choices.contains(c)));
+                let list = if rejects_old {
+                    &mut diff.tightened
+                } else {
+                    &mut diff.loosened
+                };
+                list.push(Change::new(key, old.choices.clone(), new.choices.clone()));
+            }
+            if old.required != new.required {
+                diff.changed_required
+                    .push(Change::new(key, old.required, new.required));
+            }
+            if old.sensitive != new.sensitive {
+                diff.changed_sensitivity
+                    .push(Change::new(key, old.sensitive, new.sensitive));
+            }
+        }
+        for (key, old) in &self.keys {
+            if !newer.keys.contains_key(key) {
+                diff.removed.push((key.clone(), old.clone()));
+            }
+        }
+        diff
+    }
+}
+
+/// One setting of one key, before and after.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Change<T> {
+    pub key: String,
+    pub old: T,
+    pub new: T,
+}
+
+impl<T> Change<T> {
+    fn new(key: &str, old: T, new: T) -> Self {
+        Change {
+            key: key.to_string(),
+            old,
+            new,
+        }
+    }
+}
+
+/// How the configuration surface changed between two contracts,
+/// from [`Contract::diff`].
+///
+/// It displays as Markdown, with a section for each kind of change,
+/// ready for release notes or an upgrade guide.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ContractDiff {
+    pub added: Vec<(String, KeySpec)>,
+    pub removed: Vec<(String, KeySpec)>,
+    pub changed_types: Vec<Change<String>>,
+    pub changed_defaults: Vec<Change<Option<String>>>,
+    /// Keys that now reject values that used to be allowed.
+    pub tightened: Vec<Change<Vec<String>>>,
+    /// Keys that allow more values than before, and nothing less.
+    pub loosened: Vec<Change<Vec<String>>>,
+    /// Keys that must now be set, which is a tightening, or that no
+    /// longer must be, which is a loosening.
+    pub changed_required: Vec<Change<bool>>,
+    pub changed_sensitivity: Vec<Change<bool>>,
+}
+
+impl ContractDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.changed_types.is_empty()
+            && self.changed_defaults.is_empty()
+            && self.tightened.is_empty()
+            && self.loosened.is_empty()
+            && self.changed_required.is_empty()
+            && self.changed_sensitivity.is_empty()
+    }
+}
+
+impl fmt::Display for ContractDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut sections: Vec<(&str, Vec<String>)> = vec![];
+        let added = self.added.iter().map(|(key, spec)| match &spec.default {
+            Some(d) => format!("`{}` ({}, default '{}')", key, spec.type_name, d),
+            None if spec.required => format!("`{}` ({}, required)", key, spec.type_name),
+            None => format!("`{}` ({})", key, spec.type_name),
+        });
+        sections.push(("Added", added.collect()));
+        let removed = self.removed.iter().map(|(key, _)| format!("`{}`", key));
+        sections.push(("Removed", removed.collect()));
+        let types = self
+            .changed_types
+            .iter()
+            .map(|c| format!("`{}`: {} -> {}", c.key, c.old, c.new));
+        sections.push(("Changed types", types.collect()));
+        let defaults = self
+            .changed_defaults
+            .iter()
+            .map(|c| format!("`{}`: {} -> {}", c.key, show(&c.old), show(&c.new)));
+        sections.push(("Changed defaults", defaults.collect()));
+        let choices = |changes: &[Change<Vec<String>>], required: bool| -> Vec<String> {
+            let choices = changes
+                .iter()
+                .map(|c| format!("`{}`: {} -> {}", c.key, allowed(&c.old), allowed(&c.new)));
+            let required = self
+                .changed_required
+                .iter()
+                .filter(|c| c.new == required)
+                .map(|c| match c.new {
+                    true => format!("`{}`: now required", c.key),
+                    false => format!("`{}`: no longer required", c.key),
+                });
+            choices.chain(required).collect()
+        };
+        sections.push(("Tightened constraints", choices(&self.tightened, true)));
+        sections.push(("Loosened constraints", choices(&self.loosened, false)));
+        let sensitivity = self.changed_sensitivity.iter().map(|c| match c.new {
+            true => format!("`{}`: now sensitive", c.key),
+            false => format!("`{}`: no longer sensitive", c.key),
+        });
+        sections.push(("Changed sensitivity", sensitivity.collect()));
+
+        let mut first = true;
+        for (title, items) in sections {
+            if items.is_empty() {
+                continue;
+            }
+            if !first {
+                writeln!(f)?;
+            }
+            first = false;
+            writeln!(f, "### {}\n", title)?;
+            for item in items {
+                writeln!(f, "- {}", item)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn show(default: &Option<String>) -> String {
+    match default {
+        Some(d) => format!("'{}'", d),
+        None => "none".to_string(),
+    }
+}
+
+fn allowed(choices: &[String]) -> String {
+    if choices.is_empty() {
+        "any value".to_string()
+    } else {
+        format!("one of [{}]", choices.join(", "))
+    }
+}
+
+/// The name of a type as the contract records it: without module
+/// paths, and with `NonZero<u16>` written as `NonZeroU16`, as older
+/// compilers name it. `std::any::type_name` is not stable across
+/// toolchains, and the lock file shouldn't change with them.
+pub fn stable_type_name(type_name: &str) -> String {
+    let mut name = docs::short_type_name(type_name);
+    while let Some(start) = name.find("NonZero<") {
+        let inner = start + "NonZero<".len();
+        let Some(len) = name[inner..].find('>') else {
+            break;
+        };
+        let prim = &name[inner..inner + len];
+        if !prim.chars().all(|c| c.is_ascii_alphanumeric()) {
+            break;
+        }
+        let mut chars = prim.chars();
+        let prim: String = chars
+            .next()
+            .map(|c| c.to_ascii_uppercase())
+            .into_iter()
+            .chain(chars)
+            .collect();
+        name.replace_range(start..inner + len + 1, &format!("NonZero{}", prim));
+    }
+    name
+}
+
+/// The contents of the lock file.
+impl fmt::Display for Contract {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "# Generated by biodome. Regenerate it when the configuration changes."
+        )?;
+        for (key, spec) in &self.keys {
+            write!(f, "\n[{}]\n", tomlconv::inline_key(key))?;
+            writeln!(f, "type = {}", Value::String(spec.type_name.clone()))?;
+            if let Some(default) = &spec.default {
+                writeln!(f, "default = {}", Value::String(default.clone()))?;
+            }
+            if spec.required {
+                writeln!(f, "required = true")?;
+            }
+            if spec.sensitive {
+                writeln!(f, "sensitive = true")?;
+            }
+            if !spec.choices.is_empty() {
+                let choices = spec.choices.iter().cloned().map(Value::String).collect();
+                writeln!(f, "choices = {}", Value::Array(choices))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec(type_name: &str, default: Option<&str>) -> KeySpec {
+        KeySpec {
+            type_name: type_name.to_string(),
+            default: default.map(String::from),
+            ..KeySpec::default()
+        }
+    }
+
+    fn sample() -> Contract {
+        let mut keys = BTreeMap::new();
+        keys.insert("PORT".to_string(), spec("i32", Some("8080")));
+        keys.insert(
+            "FORMAT".to_string(),
+            KeySpec {
+                choices: vec!["json".to_string(), "text".to_string()],
+                ..spec("alloc::string::String", Some("json"))
+            },
+        );
+        keys.insert(
+            "API_TOKEN".to_string(),
+            KeySpec {
+                required: true,
+                sensitive: true,
+                ..spec("alloc::string::String", None)
+            },
+        );
+        Contract { keys }
+    }
+
+    #[test]
+    fn round_trip() {
+        let contract = sample();
+        let text = contract.to_string();
+        assert!(text.contains("[PORT]\ntype = \"i32\"\ndefault = \"8080\"\n"));
+        assert!(text.contains("required = true\nsensitive = true\n"));
+        assert_eq!(Contract::parse(&text), Ok(contract));
+        assert_eq!(
+            Contract::parse("PORT = 1"),
+            Err("expected a table for each key")
+        );
+        assert_eq!(
+            Contract::parse("[PORT]\ndefault = \"1\""),
+            Err("missing type")
+        );
+    }
+
+    #[test]
+    fn differences() {
+        let locked = sample();
+        assert!(locked.diff(&locked).is_empty());
+
+        let mut current = sample();
+        current.keys.remove("API_TOKEN");
+        current
+            .keys
+            .insert("WORKERS".to_string(), spec("u8", Some("4")));
+        current.keys.get_mut("PORT").unwrap().default = Some("9090".to_string());
+        current.keys.get_mut("FORMAT").unwrap().choices.pop();
+        let diff = locked.diff(&current);
+        assert_eq!(
+            diff.added,
+            vec![("WORKERS".to_string(), spec("u8", Some("4")))]
+        );
+        assert_eq!(diff.removed[0].0, "API_TOKEN");
+        assert_eq!(diff.tightened.len(), 1);
+        assert!(diff.loosened.is_empty());
+        assert_eq!(
+            diff.to_string(),
+            "### Added\n\n\
+             - `WORKERS` (u8, default '4')\n\n\
+             ### Removed\n\n\
+             - `API_TOKEN`\n\n\
+             ### Changed defaults\n\n\
+             - `PORT`: '8080' -> '9090'\n\n\
+             ### Tightened constraints\n\n\
+             - `FORMAT`: one of [json, text] -> one of [json]\n"
+        );
+
+        let reverse = current.diff(&locked);
+        assert_eq!(reverse.loosened.len(), 1);
+        assert_eq!(reverse.changed_defaults[0].old, Some("9090".to_string()));
+    }
+
+    #[test]
+    fn constraints() {
+        let with = |choices: &[&str]| {
+            let mut contract = sample();
+            contract.keys.get_mut("FORMAT").unwrap().choices =
+                choices.iter().map(|c| c.to_string()).collect();
+            contract
+        };
+        let any = with(&[]);
+        let two = with(&["json", "text"]);
+        let other = with(&["json", "yaml"]);
+        assert_eq!(any.diff(&two).tightened.len(), 1);
+        assert_eq!(two.diff(&any).loosened.len(), 1);
+        assert_eq!(two.diff(&other).tightened.len(), 1);
+        assert_eq!(with(&["json"]).diff(&two).loosened.len(), 1);
+    }
+
+    #[test]
+    fn required() {
+        let locked = sample();
+        let mut current = sample();
+        current.keys.get_mut("PORT").unwrap().required = true;
+        let diff = locked.diff(&current);
+        assert_eq!(
+            diff.changed_required,
+            vec![Change::new("PORT", false, true)]
+        );
+        assert_eq!(
+            diff.to_string(),
+            "### Tightened constraints\n\n- `PORT`: now required\n"
+        );
+        assert_eq!(
+            current.diff(&locked).to_string(),
+            "### Loosened constraints\n\n- `PORT`: no longer required\n"
+        );
+    }
+
+    #[test]
+    fn type_names() {
+        assert_eq!(
+            stable_type_name("core::num::nonzero::NonZeroU16"),
+            "NonZeroU16"
+        );
+        assert_eq!(
+            stable_type_name("core::num::nonzero::NonZero<u16>"),
+            "NonZeroU16"
+        );
+        assert_eq!(
+            stable_type_name("alloc::vec::Vec<core::num::nonzero::NonZero<usize>>"),
+            "Vec<NonZeroUsize>"
+        );
+        assert_eq!(stable_type_name("alloc::string::String"), "String");
+    }
+}
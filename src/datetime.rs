@@ -0,0 +1,198 @@
+use toml::value::Datetime;
+
+use crate::{rawconv, TryFromEnv, TryIntoEnv};
+
+/// Accepts TOML datetimes: offset datetimes like
+/// `1979-05-27T07:32:00-08:00`, local datetimes, dates and times.
+impl TryFromEnv<Self> for Datetime {
+    type Error = &'static str;
+
+    fn try_from_env(value: &str) -> Result<Self, Self::Error> {
+        rawconv::to_datetime(value.trim())
+    }
+
+    fn to_env(value: &Self) -> Option<String> {
+        value.try_into_env().ok()
+    }
+}
+
+impl TryIntoEnv<String> for Datetime {
+    type Error = &'static str;
+
+    fn try_into_env(&self) -> Result<String, Self::Error> {
+        Ok(self.to_string())
+    }
+}
+
+#[cfg(feature = "chrono")]
+mod chrono_impls {
+    use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
+
+    use crate::{rawconv, TryFromEnv, TryIntoEnv};
+
+    /// Accepts RFC 3339 timestamps, e.g. `2021-06-01T12:00:00Z` or
+    /// `2021-06-01T14:00:00+02:00`. The result is converted to UTC.
+    impl TryFromEnv<Self> for DateTime<Utc> {
+        type Error = &'static str;
+
+        fn try_from_env(value: &str) -> Result<Self, Self::Error> {
+            DateTime::parse_from_rfc3339(value.trim())
+                .map(|dt| dt.with_timezone(&Utc))
+                .map_err(|_err| "parse error")
+        }
+
+        fn to_env(value: &Self) -> Option<String> {
+            value.try_into_env().ok()
+        }
+    }
+
+    impl TryIntoEnv<String> for DateTime<Utc> {
+        type Error = &'static str;
+
+        fn try_into_env(&self) -> Result<String, Self::Error> {
+            Ok(self.to_rfc3339())
+        }
+    }
+
+    /// Accepts dates like `2021-06-01`.
+    impl TryFromEnv<Self> for NaiveDate {
+        type Error = &'static str;
+
+        fn try_from_env(value: &str) -> Result<Self, Self::Error> {
+            rawconv::to_prim(value.trim())
+        }
+
+        fn to_env(value: &Self) -> Option<String> {
+            value.try_into_env().ok()
+        }
+    }
+
+    impl TryIntoEnv<String> for NaiveDate {
+        type Error = &'static str;
+
+        fn try_into_env(&self) -> Result<String, Self::Error> {
+            Ok(self.to_string())
+        }
+    }
+
+    /// Accepts times like `23:30` or `23:30:00`.
+    impl TryFromEnv<Self> for NaiveTime {
+        type Error = &'static str;
+
+        fn try_from_env(value: &str) -> Result<Self, Self::Error> {
+            let value = value.trim();
+            rawconv::to_prim(value).or_else(|_| {
+                NaiveTime::parse_from_str(value, "%H:%M").map_err(|_err| "parse error")
+            })
+        }
+
+        fn to_env(value: &Self) -> Option<String> {
+            value.try_into_env().ok()
+        }
+    }
+
+    impl TryIntoEnv<String> for NaiveTime {
+        type Error = &'static str;
+
+        fn try_into_env(&self) -> Result<String, Self::Error> {
+            Ok(self.to_string())
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn chrono() {
+            let dt = DateTime::<Utc>::try_from_env("2021-06-01T14:00:00+02:00").unwrap();
+            assert_eq!(dt.to_rfc3339(), "2021-06-01T12:00:00+00:00");
+            assert_eq!(dt.try_into_env().unwrap(), "2021-06-01T12:00:00+00:00");
+            assert!(DateTime::<Utc>::try_from_env("2021-06-01").is_err());
+            let d = NaiveDate::try_from_env("2021-06-01").unwrap();
+            assert_eq!(d, NaiveDate::from_ymd_opt(2021, 6, 1).unwrap());
+            let t = NaiveTime::try_from_env("23:30").unwrap();
+            assert_eq!(t, NaiveTime::from_hms_opt(23, 30, 0).unwrap());
+            let t = NaiveTime::try_from_env("23:30:15").unwrap();
+            assert_eq!(t, NaiveTime::from_hms_opt(23, 30, 15).unwrap());
+        }
+    }
+}
+
+#[cfg(feature = "time")]
+mod time_impls {
+    use time::format_description::well_known::Rfc3339;
+    use time::{Date, Month, OffsetDateTime};
+
+    use crate::{rawconv, TryFromEnv, TryIntoEnv};
+
+    /// Accepts RFC 3339 timestamps, e.g. `2021-06-01T12:00:00Z` or
+    /// `2021-06-01T14:00:00+02:00`. The offset is kept as given.
+    impl TryFromEnv<Self> for OffsetDateTime {
+        type Error = &'static str;
+
+        fn try_from_env(value: &str) -> Result<Self, Self::Error> {
+            OffsetDateTime::parse(value.trim(), &Rfc3339).map_err(|_err| "parse error")
+        }
+
+        fn to_env(value: &Self) -> Option<String> {
+            value.try_into_env().ok()
+        }
+    }
+
+    impl TryIntoEnv<String> for OffsetDateTime {
+        type Error = &'static str;
+
+        fn try_into_env(&self) -> Result<String, Self::Error> {
+            self.format(&Rfc3339).map_err(|_err| "format error")
+        }
+    }
+
+    /// Accepts dates like `2021-06-01`.
+    impl TryFromEnv<Self> for Date {
+        type Error = &'static str;
+
+        fn try_from_env(value: &str) -> Result<Self, Self::Error> {
+            let parts: Vec<&str> = value.trim().splitn(3, '-').collect();
+            if parts.len() != 3 || parts[0].len() != 4 {
+                return Err("parse error");
+            }
+            let year: i32 = rawconv::to_prim(parts[0])?;
+            let month: u8 = rawconv::to_prim(parts[1])?;
+            let day: u8 = rawconv::to_prim(parts[2])?;
+            let month = Month::try_from(month).map_err(|_err| "parse error")?;
+            Date::from_calendar_date(year, month, day).map_err(|_err| "parse error")
+        }
+
+        fn to_env(value: &Self) -> Option<String> {
+            value.try_into_env().ok()
+        }
+    }
+
+    impl TryIntoEnv<String> for Date {
+        type Error = &'static str;
+
+        fn try_into_env(&self) -> Result<String, Self::Error> {
+            Ok(self.to_string())
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn time() {
+            let dt = OffsetDateTime::try_from_env("2021-06-01T14:00:00+02:00").unwrap();
+            assert_eq!(dt.unix_timestamp(), 1622548800);
+            assert_eq!(dt.offset().whole_hours(), 2);
+            assert_eq!(dt.try_into_env().unwrap(), "2021-06-01T14:00:00+02:00");
+            assert!(OffsetDateTime::try_from_env("2021-06-01").is_err());
+            let d = Date::try_from_env("2021-06-01").unwrap();
+            assert_eq!(d, Date::from_calendar_date(2021, Month::June, 1).unwrap());
+            assert_eq!(d.try_into_env().unwrap(), "2021-06-01");
+            assert!(Date::try_from_env("2021-02-30").is_err());
+            assert!(Date::try_from_env("21-06-01").is_err());
+        }
+    }
+}
@@ -0,0 +1,423 @@
+//! Read any struct that implements serde's `Deserialize` from env vars,
+//! as [envy](https://github.com/softprops/envy) does, but with
+//! biodome's parsing of values.
+//!
+//! Each field is read from the env var named after it in upper case,
+//! so `max_connections` is read from `MAX_CONNECTIONS`. Values are
+//! parsed as [`biodome`](crate::biodome) parses them: bools can be
+//! `yes` or `on`, numbers follow the [`NumberFormat`](crate::NumberFormat),
+//! and lists, maps and nested structs are written in the TOML subset.
+//! Lists can also be written without brackets, like `a:80,b:80`.
+//! Fields that are not set get their `#[serde(default)]`, or are
+//! `None` if they are an `Option`; otherwise the env var is required.
+//!
+//! ```rust
+//! use serde::Deserialize;
+//!
+//! #[derive(Deserialize)]
+//! struct Server {
+//!     host: String,
+//!     port: u16,
+//!     debug: bool,
+//!     peers: Vec<String>,
+//!     #[serde(default)]
+//!     workers: u8,
+//!     timeout: Option<f64>,
+//! }
+//!
+//! std::env::set_var("DE_HOST", "0.0.0.0");
+//! std::env::set_var("DE_PORT", "8080");
+//! std::env::set_var("DE_DEBUG", "yes");
+//! std::env::set_var("DE_PEERS", r#"["a:80", "b:80"]"#);
+//!
+//! let server: Server = biodome::de::from_env_prefixed("DE_").unwrap();
+//! assert_eq!(server.port, 8080);
+//! assert!(server.debug);
+//! assert_eq!(server.peers, ["a:80", "b:80"]);
+//! assert_eq!((server.workers, server.timeout), (0, None));
+//! ```
+
+use std::fmt;
+
+use serde::de::{self, DeserializeOwned, DeserializeSeed, IntoDeserializer, MapAccess, Visitor};
+
+use crate::{profile, rawconv, source, BiodomeError};
+
+/// Read a "T" from the env vars named after its fields.
+pub fn from_env<T: DeserializeOwned>() -> Result<T, BiodomeError> {
+    from_env_prefixed("")
+}
+
+/// Like [`from_env`], but with "prefix" in front of every env var name,
+/// so that `port` is read from `APP_PORT` with a prefix of `APP_`.
+pub fn from_env_prefixed<T: DeserializeOwned>(prefix: &str) -> Result<T, BiodomeError> {
+    T::deserialize(Env { prefix }).map_err(|Error(err)| match err {
+        // Only the fields of the struct itself can be missing; nested
+        // structs are parsed from TOML.
+        BiodomeError::Missing { key } => BiodomeError::Missing {
+            key: var_name(prefix, &key),
+        },
+        err => err,
+    })
+}
+
+fn var_name(prefix: &str, field: &str) -> String {
+    format!("{}{}", prefix, field.to_uppercase())
+}
+
+/// A [`BiodomeError`] that serde can produce.
+#[derive(Debug)]
+struct Error(BiodomeError);
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl de::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        // The key is filled in by the field that failed.
+        Error(BiodomeError::Parse {
+            key: String::new(),
+            reason: msg.to_string(),
+        })
+    }
+
+    fn missing_field(field: &'static str) -> Self {
+        Error(BiodomeError::Missing {
+            key: field.to_string(),
+        })
+    }
+}
+
+impl From<BiodomeError> for Error {
+    fn from(err: BiodomeError) -> Self {
+        Error(err)
+    }
+}
+
+/// The whole environment, as a struct.
+struct Env<'a> {
+    prefix: &'a str,
+}
+
+impl<'de> de::Deserializer<'de> for Env<'_> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Error> {
+        Err(de::Error::custom(
+            "only structs can be read from the environment",
+        ))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_map(Fields {
+            prefix: self.prefix,
+            fields: fields.iter(),
+            current: None,
+        })
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map enum identifier ignored_any
+    }
+}
+
+/// The fields of a struct that are set, one env var each.
+struct Fields<'a> {
+    prefix: &'a str,
+    fields: std::slice::Iter<'static, &'static str>,
+    current: Option<Raw>,
+}
+
+impl<'de> MapAccess<'de> for Fields<'_> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Error> {
+        for field in self.fields.by_ref() {
+            let key = var_name(self.prefix, field);
+            let Some(value) = source::try_value(&key)?.map(profile::select) else {
+                continue;
+            };
+            self.current = Some(Raw { key, value });
+            return seed.deserialize(field.into_deserializer()).map(Some);
+        }
+        Ok(None)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        let raw = self
+            .current
+            .take()
+            .expect("a value is only read after its key");
+        let key = raw.key.clone();
+        seed.deserialize(raw).map_err(|Error(err)| match err {
+            BiodomeError::Parse { key: k, reason } if k.is_empty() => {
+                Error(BiodomeError::Parse { key, reason })
+            }
+            err => Error(err),
+        })
+    }
+}
+
+/// The value of one env var.
+struct Raw {
+    key: String,
+    value: String,
+}
+
+impl Raw {
+    fn parse<T: crate::TryFromEnv<T>>(&self) -> Result<T, Error>
+    where
+        T::Error: fmt::Debug,
+    {
+        Ok(crate::parse::<T, T>(&self.key, &self.value)?)
+    }
+
+    /// A parse error for this value.
+    fn error(&self, reason: impl ToString) -> Error {
+        Error(BiodomeError::Parse {
+            key: self.key.clone(),
+            reason: reason.to_string(),
+        })
+    }
+
+    /// The value as TOML, for collections and nested structs.
+    fn toml(&self) -> Result<toml::Value, Error> {
+        rawconv::to_value(&self.value).map_err(|reason| self.error(reason))
+    }
+
+    fn read_toml<'de, V: Visitor<'de>>(
+        &self,
+        read: impl FnOnce(toml::Value) -> Result<V::Value, toml::de::Error>,
+    ) -> Result<V::Value, Error> {
+        read(self.toml()?).map_err(|err| self.error(err))
+    }
+}
+
+macro_rules! deserialize_parsed {
+    ($($method:ident => $visit:ident: $t:ty),*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+                visitor.$visit(self.parse::<$t>()?)
+            }
+        )*
+    };
+}
+
+impl<'de> IntoDeserializer<'de, Error> for Raw {
+    type Deserializer = Raw;
+
+    fn into_deserializer(self) -> Raw {
+        self
+    }
+}
+
+impl<'de> de::Deserializer<'de> for Raw {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_string(self.value)
+    }
+
+    deserialize_parsed!(
+        deserialize_bool => visit_bool: bool,
+        deserialize_i8 => visit_i8: i8,
+        deserialize_i16 => visit_i16: i16,
+        deserialize_i32 => visit_i32: i32,
+        deserialize_i64 => visit_i64: i64,
+        deserialize_i128 => visit_i128: i128,
+        deserialize_u8 => visit_u8: u8,
+        deserialize_u16 => visit_u16: u16,
+        deserialize_u32 => visit_u32: u32,
+        deserialize_u64 => visit_u64: u64,
+        deserialize_u128 => visit_u128: u128,
+        deserialize_f32 => visit_f32: f32,
+        deserialize_f64 => visit_f64: f64,
+        deserialize_char => visit_char: char
+    );
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_enum(self.value.into_deserializer())
+    }
+
+    /// A list written without brackets, like `a:80,b:80`, is read
+    /// item by item, as values of env vars are.
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let list = rawconv::to_list(&self.value).map_err(|reason| self.error(reason))?;
+        let items = match list {
+            rawconv::List::Array(value) => {
+                return de::Deserializer::deserialize_seq(value, visitor)
+                    .map_err(|err| self.error(err));
+            }
+            rawconv::List::Items(items) => items,
+        };
+        let items = items.into_iter().map(|item| Raw {
+            key: self.key.clone(),
+            value: item.to_string(),
+        });
+        let mut seq = de::value::SeqDeserializer::new(items);
+        let value = visitor.visit_seq(&mut seq)?;
+        seq.end()?;
+        Ok(value)
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value, Error> {
+        self.read_toml::<V>(|value| de::Deserializer::deserialize_tuple(value, len, visitor))
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.read_toml::<V>(|value| {
+            de::Deserializer::deserialize_tuple_struct(value, name, len, visitor)
+        })
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.read_toml::<V>(|value| de::Deserializer::deserialize_map(value, visitor))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.read_toml::<V>(|value| {
+            de::Deserializer::deserialize_struct(value, name, fields, visitor)
+        })
+    }
+
+    serde::forward_to_deserialize_any! {
+        str string bytes byte_buf unit unit_struct identifier ignored_any
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+    use std::collections::HashMap;
+    use std::env;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    #[serde(rename_all = "lowercase")]
+    enum Mode {
+        Dev,
+        Prod,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Limits {
+        rate: u32,
+        burst: u32,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct Config {
+        mode: Mode,
+        limits: Limits,
+        weights: HashMap<String, f64>,
+        origin: (String, u16),
+        #[serde(default)]
+        verbose: bool,
+    }
+
+    #[test]
+    fn structured() {
+        env::set_var("SERDE_A_MODE", "prod");
+        env::set_var("SERDE_A_LIMITS", "{ rate = 10, burst = 20 }");
+        env::set_var("SERDE_A_WEIGHTS", "{ a = 0.5 }");
+        env::set_var("SERDE_A_ORIGIN", r#"["host", 443]"#);
+        let config: Config = from_env_prefixed("SERDE_A_").unwrap();
+        assert_eq!(config.mode, Mode::Prod);
+        assert_eq!(
+            config.limits,
+            Limits {
+                rate: 10,
+                burst: 20
+            }
+        );
+        assert_eq!(config.weights["a"], 0.5);
+        assert_eq!(config.origin, ("host".to_string(), 443));
+        assert!(!config.verbose);
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct Peers {
+        peers: Vec<String>,
+        ports: Vec<u16>,
+    }
+
+    #[test]
+    fn comma_lists() {
+        env::set_var("SERDE_C_PEERS", "a:80,b:80");
+        env::set_var("SERDE_C_PORTS", "80, 443");
+        let peers: Peers = from_env_prefixed("SERDE_C_").unwrap();
+        assert_eq!(peers.peers, ["a:80", "b:80"]);
+        assert_eq!(peers.ports, [80, 443]);
+
+        env::set_var("SERDE_C_PORTS", "80,http");
+        let err = from_env_prefixed::<Peers>("SERDE_C_").unwrap_err();
+        assert!(matches!(err, BiodomeError::Parse { ref key, .. } if key == "SERDE_C_PORTS"));
+    }
+
+    #[test]
+    fn errors() {
+        env::set_var("SERDE_B_MODE", "dev");
+        env::set_var("SERDE_B_LIMITS", "{ rate = 10 }");
+        let err = from_env_prefixed::<Config>("SERDE_B_").unwrap_err();
+        assert!(matches!(err, BiodomeError::Parse { ref key, .. } if key == "SERDE_B_LIMITS"));
+
+        env::set_var("SERDE_B_LIMITS", "{ rate = 10, burst = 20 }");
+        let err = from_env_prefixed::<Config>("SERDE_B_").unwrap_err();
+        assert_eq!(
+            err,
+            BiodomeError::Missing {
+                key: "SERDE_B_WEIGHTS".to_string()
+            }
+        );
+
+        env::set_var("SERDE_B_WEIGHTS", "{}");
+        env::set_var("SERDE_B_ORIGIN", r#"["host", 443]"#);
+        env::set_var("SERDE_B_VERBOSE", "maybe?");
+        assert!(!from_env_prefixed::<Config>("SERDE_B_").unwrap().verbose);
+        env::set_var("SERDE_B_MODE", "staging");
+        let err = from_env_prefixed::<Config>("SERDE_B_").unwrap_err();
+        assert!(matches!(err, BiodomeError::Parse { ref key, .. } if key == "SERDE_B_MODE"));
+    }
+}
@@ -0,0 +1,83 @@
+//! Verbose logging of how each env var is resolved, turned on by
+//! setting `BIODOME_DEBUG` to a truthy value like `1`.
+//!
+//! Each step goes to stderr as a line starting with `biodome:`, so
+//! nothing has to be configured in the app to see why a variable was
+//! ignored. Values of sensitive keys are redacted.
+//!
+//! `BIODOME_DEBUG` is read once, on the first lookup, since every
+//! lookup checks it. [`set_debug`] turns logging on or off after that.
+
+use std::env;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use crate::rawconv;
+
+const UNKNOWN: u8 = 0;
+const OFF: u8 = 1;
+const ON: u8 = 2;
+
+static DEBUG: AtomicU8 = AtomicU8::new(UNKNOWN);
+
+/// Turn the logging of each lookup on or off, whatever `BIODOME_DEBUG`
+/// is set to.
+pub fn set_debug(on: bool) {
+    DEBUG.store(if on { ON } else { OFF }, Ordering::Relaxed);
+}
+
+/// Go back to `BIODOME_DEBUG`, which is read again on the next lookup.
+pub fn reset_debug() {
+    DEBUG.store(UNKNOWN, Ordering::Relaxed);
+}
+
+/// Whether debugging is turned on. `BIODOME_DEBUG` is read from the
+/// process environment, whatever source is installed.
+pub(crate) fn enabled() -> bool {
+    match DEBUG.load(Ordering::Relaxed) {
+        UNKNOWN => {
+            let on = env::var("BIODOME_DEBUG").is_ok_and(|v| rawconv::to_bool(&v));
+            set_debug(on);
+            on
+        }
+        state => state == ON,
+    }
+}
+
+/// Log one step of resolving "key", if debugging is on. The message
+/// is only built when it will be shown.
+pub(crate) fn log(key: &str, message: impl FnOnce() -> String) {
+    if enabled() {
+        eprintln!("biodome: {}: {}", key, message());
+    }
+}
+
+/// "value", or `[REDACTED]` if it is "sensitive".
+pub(crate) fn shown(value: &str, sensitive: bool) -> String {
+    if sensitive {
+        "[REDACTED]".to_string()
+    } else {
+        format!("{:?}", value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn switched() {
+        set_debug(true);
+        assert!(enabled());
+        set_debug(false);
+        assert!(!enabled());
+        reset_debug();
+        let from_env = env::var("BIODOME_DEBUG").is_ok_and(|v| rawconv::to_bool(&v));
+        assert_eq!(enabled(), from_env);
+    }
+
+    #[test]
+    fn shown_values() {
+        assert_eq!(shown("8080", false), "\"8080\"");
+        assert_eq!(shown("hunter2", true), "[REDACTED]");
+    }
+}
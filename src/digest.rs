@@ -0,0 +1,93 @@
+use crate::{profile, source};
+
+/// A short digest of the env vars named in "keys", for checking that
+/// the replicas of a clustered service agree on the settings that
+/// must match across the cluster.
+///
+/// The digest only depends on the keys and their values, not on the
+/// order of "keys", and it is the same on every platform and build,
+/// so nodes can gossip or log it and compare. The values are the ones
+/// [`biodome`](crate::biodome) would use: read from a `{key}_FILE` if
+/// need be, with `${VAR}` references expanded and the part for the
+/// active profile picked out. An unset key is distinct from a key set
+/// to an empty value, and from one whose value can't be read.
+///
+/// The digest is not a cryptographic hash, so leave secrets out of
+/// "keys".
+///
+/// ```rust
+/// use biodome::config_digest;
+///
+/// std::env::set_var("SHARD_COUNT", "16");
+/// std::env::set_var("REPLICATION", "3");
+/// let digest = config_digest(&["SHARD_COUNT", "REPLICATION"]);
+/// assert_eq!(digest.len(), 16);
+/// assert_eq!(digest, config_digest(&["REPLICATION", "SHARD_COUNT"]));
+/// ```
+pub fn config_digest(keys: &[&str]) -> String {
+    let mut keys = keys.to_vec();
+    keys.sort_unstable();
+    keys.dedup();
+    let mut input = Vec::new();
+    for key in keys {
+        input.extend_from_slice(key.as_bytes());
+        match source::try_value(key).map(|v| v.map(profile::select)) {
+            Ok(Some(value)) => {
+                input.push(b'=');
+                input.extend_from_slice(value.as_bytes());
+            }
+            Ok(None) => input.push(b'!'),
+            Err(_) => input.push(b'?'),
+        }
+        input.push(0);
+    }
+    format!("{:016x}", fnv1a(&input))
+}
+
+/// 64-bit FNV-1a. Unlike `std::hash`, its output is fixed, so it can
+/// be compared across processes and builds.
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+    const OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+    bytes
+        .iter()
+        .fold(OFFSET, |hash, &b| (hash ^ b as u64).wrapping_mul(PRIME))
+}
+
+/// A stable number below "buckets" for "subject", which is the same
+/// for the same "salt" in every process.
+pub(crate) fn bucket(salt: &str, subject: &str, buckets: u64) -> u64 {
+    fnv1a(format!("{}\0{}", salt, subject).as_bytes()) % buckets
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn fnv() {
+        assert_eq!(fnv1a(b""), 0xcbf29ce484222325);
+        assert_eq!(fnv1a(b"a"), 0xaf63dc4c8601ec8c);
+        assert_eq!(fnv1a(b"foobar"), 0x85944171f73967e8);
+    }
+
+    #[test]
+    fn digest() {
+        env::set_var("DIGEST_SHARDS", "16");
+        let a = config_digest(&["DIGEST_SHARDS", "DIGEST_MISSING"]);
+        env::set_var("DIGEST_SHARDS", "32");
+        let b = config_digest(&["DIGEST_SHARDS", "DIGEST_MISSING"]);
+        assert_ne!(a, b);
+        env::set_var("DIGEST_MISSING", "");
+        let c = config_digest(&["DIGEST_SHARDS", "DIGEST_MISSING"]);
+        assert_ne!(b, c);
+
+        env::set_var("DIGEST_SHARDS", "${DIGEST_SHARD_COUNT}");
+        env::set_var("DIGEST_SHARD_COUNT", "32");
+        assert_eq!(config_digest(&["DIGEST_SHARDS", "DIGEST_MISSING"]), c);
+        env::remove_var("DIGEST_SHARDS");
+        env::remove_var("DIGEST_SHARD_COUNT");
+        env::remove_var("DIGEST_MISSING");
+    }
+}
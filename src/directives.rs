@@ -0,0 +1,214 @@
+use std::fmt;
+
+use tracing::level_filters::LevelFilter;
+
+use crate::{rawconv, TryFromEnv};
+
+/// One `target=level` part of a filter directive string.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Directive {
+    /// The module path the directive applies to, or `None` for the
+    /// default level.
+    pub target: Option<String>,
+    /// A span and field filter, like `[request{id=7}]`, kept as
+    /// written.
+    pub span: Option<String>,
+    pub level: LevelFilter,
+}
+
+impl fmt::Display for Directive {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let level = self.level.to_string().to_lowercase();
+        match (&self.target, &self.span) {
+            (None, None) => write!(f, "{}", level),
+            (target, span) => write!(
+                f,
+                "{}{}={}",
+                target.as_deref().unwrap_or(""),
+                span.as_deref().unwrap_or(""),
+                level
+            ),
+        }
+    }
+}
+
+/// Filter directives in the syntax of `tracing-subscriber`'s
+/// `EnvFilter`, e.g. `RUST_LOG=info,my_app=debug,hyper=warn`.
+///
+/// The directives are checked when the env var is read, so a typo in
+/// a level is reported like any other bad value. To use them, hand
+/// the string form to `EnvFilter`, or ask for the level of a target:
+///
+/// ```rust
+/// use biodome::{biodome, Directives};
+/// use tracing::level_filters::LevelFilter;
+///
+/// std::env::set_var("RUST_LOG", "warn,my_app=debug,my_app::db=trace");
+/// let RUST_LOG: Directives = biodome("RUST_LOG", Directives::default());
+/// assert_eq!(RUST_LOG.level_for("my_app::db::pool"), LevelFilter::TRACE);
+/// assert_eq!(RUST_LOG.level_for("my_app::http"), LevelFilter::DEBUG);
+/// assert_eq!(RUST_LOG.level_for("hyper"), LevelFilter::WARN);
+/// assert_eq!(RUST_LOG.to_string(), "warn,my_app=debug,my_app::db=trace");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Directives(pub Vec<Directive>);
+
+impl Directives {
+    /// The level for events from "target", from the directive with
+    /// the longest matching target. Directives with span filters are
+    /// not considered, since they depend on the span an event is in.
+    /// Without a matching directive, the level is `ERROR`, as it is
+    /// for `EnvFilter`.
+    pub fn level_for(&self, target: &str) -> LevelFilter {
+        self.0
+            .iter()
+            .filter(|d| d.span.is_none())
+            .filter_map(|d| match &d.target {
+                None => Some((0, d.level)),
+                Some(t) if target_matches(t, target) => Some((t.len(), d.level)),
+                Some(_) => None,
+            })
+            .max_by_key(|&(len, _)| len)
+            .map(|(_, level)| level)
+            .unwrap_or(LevelFilter::ERROR)
+    }
+}
+
+/// Whether "prefix" is "target" or one of its parent modules.
+fn target_matches(prefix: &str, target: &str) -> bool {
+    match target.strip_prefix(prefix) {
+        Some(rest) => rest.is_empty() || rest.starts_with("::"),
+        None => false,
+    }
+}
+
+impl fmt::Display for Directives {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let parts: Vec<String> = self.0.iter().map(|d| d.to_string()).collect();
+        write!(f, "{}", parts.join(","))
+    }
+}
+
+impl TryFromEnv<Self> for Directives {
+    type Error = &'static str;
+
+    fn try_from_env(value: &str) -> Result<Self, Self::Error> {
+        split_directives(value)?
+            .into_iter()
+            .map(|part| part.trim())
+            .filter(|part| !part.is_empty())
+            .map(parse_directive)
+            .collect::<Result<_, _>>()
+            .map(Directives)
+    }
+
+    fn to_env(value: &Self) -> Option<String> {
+        Some(value.to_string())
+    }
+}
+
+/// Split on the commas that separate directives, leaving alone the
+/// ones inside span filters.
+fn split_directives(s: &str) -> Result<Vec<&str>, &'static str> {
+    let mut parts = vec![];
+    let mut depth = 0usize;
+    let mut start = 0;
+    for (i, c) in s.char_indices() {
+        match c {
+            '[' | '{' => depth += 1,
+            ']' | '}' => depth = depth.checked_sub(1).ok_or("unbalanced brackets")?,
+            ',' if depth == 0 => {
+                parts.push(&s[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    if depth != 0 {
+        return Err("unbalanced brackets");
+    }
+    parts.push(&s[start..]);
+    Ok(parts)
+}
+
+fn parse_directive(s: &str) -> Result<Directive, &'static str> {
+    let (selector, level) = match s.rsplit_once('=') {
+        // An `=` inside a field filter is not the level separator.
+        Some((selector, level)) if !level.contains(['[', ']', '{', '}']) => (selector, Some(level)),
+        _ => (s, None),
+    };
+    let level = match level {
+        Some(level) => parse_level(level)?,
+        None => match parse_level(selector) {
+            Ok(level) => return Ok(default_directive(level)),
+            // A target on its own enables everything from it.
+            Err(_) => LevelFilter::TRACE,
+        },
+    };
+    let (target, span) = match selector.find('[') {
+        Some(i) if selector.ends_with(']') => (&selector[..i], Some(&selector[i..])),
+        Some(_) => return Err("expected a span filter in brackets"),
+        None => (selector, None),
+    };
+    if target.contains(|c: char| c.is_whitespace() || c == '=') {
+        return Err("invalid target");
+    }
+    Ok(Directive {
+        target: Some(target).filter(|t| !t.is_empty()).map(String::from),
+        span: span.map(String::from),
+        level,
+    })
+}
+
+fn default_directive(level: LevelFilter) -> Directive {
+    Directive {
+        target: None,
+        span: None,
+        level,
+    }
+}
+
+fn parse_level(s: &str) -> Result<LevelFilter, &'static str> {
+    rawconv::to_prim(s.trim())
+        .map_err(|_err| "expected a level: off, error, warn, info, debug or trace")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(s: &str) -> Result<Directives, &'static str> {
+        Directives::try_from_env(s)
+    }
+
+    #[test]
+    fn directives() {
+        let d = parse("info, my_app=debug,tower_http::trace=off").unwrap();
+        assert_eq!(d.0.len(), 3);
+        assert_eq!(d.0[0], default_directive(LevelFilter::INFO));
+        assert_eq!(d.0[2].target.as_deref(), Some("tower_http::trace"));
+        assert_eq!(d.0[2].level, LevelFilter::OFF);
+        assert_eq!(d.to_string(), "info,my_app=debug,tower_http::trace=off");
+
+        let d = parse("my_app[request{id=7,user=x}]=trace,hyper").unwrap();
+        assert_eq!(d.0[0].span.as_deref(), Some("[request{id=7,user=x}]"));
+        assert_eq!(d.0[1].level, LevelFilter::TRACE);
+        assert_eq!(
+            d.to_string(),
+            "my_app[request{id=7,user=x}]=trace,hyper=trace"
+        );
+
+        assert_eq!(parse(""), Ok(Directives::default()));
+        assert!(parse("my_app=loud").is_err());
+        assert!(parse("my_app[request=debug").is_err());
+    }
+
+    #[test]
+    fn levels() {
+        let d = parse("my_app=debug,my_app::db=trace,my_app[span]=trace").unwrap();
+        assert_eq!(d.level_for("my_app"), LevelFilter::DEBUG);
+        assert_eq!(d.level_for("my_app::db"), LevelFilter::TRACE);
+        assert_eq!(d.level_for("my_app_two"), LevelFilter::ERROR);
+        assert_eq!(d.level_for("other"), LevelFilter::ERROR);
+    }
+}
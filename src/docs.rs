@@ -0,0 +1,136 @@
+//! Generate documentation for the env vars an app reads.
+//!
+//! The documentation is built from the registry of env vars that have
+//! been read, so it can't drift from the code. Descriptions come from
+//! [`describe`](crate::describe), or from doc comments on the fields
+//! of settings structs with `#[derive(Settings)]`.
+
+use crate::registry::{self, RegistryEntry};
+
+/// A Markdown table of every env var read so far, with its type,
+/// default and description.
+///
+/// ```rust
+/// biodome::describe("WORKERS", "Number of worker threads");
+/// let WORKERS = biodome::biodome("WORKERS", 4);
+/// let docs = biodome::docs::markdown();
+/// assert!(docs.contains("| `WORKERS` | `i32` | `4` | Number of worker threads |"));
+/// ```
+///
+/// Defaults of sensitive settings are left out.
+pub fn markdown() -> String {
+    render(&registry::registry())
+}
+
+fn render(entries: &[RegistryEntry]) -> String {
+    let mut out = String::from("| Variable | Type | Default | Description |\n");
+    out += "|---|---|---|---|\n";
+    for entry in entries {
+        let default = match &entry.default {
+            _ if entry.required => "*required*".to_string(),
+            _ if entry.redacted() => "*hidden*".to_string(),
+            Some(d) => format!("`{}`", d),
+            None => String::new(),
+        };
+        let mut description = registry::description(&entry.key).unwrap_or_default();
+        if !entry.choices.is_empty() {
+            let choices: Vec<String> = entry.choices.iter().map(|c| format!("`{}`", c)).collect();
+            if !description.is_empty() {
+                description += " ";
+            }
+            description += &format!("One of: {}.", choices.join(", "));
+        }
+        out += &format!(
+            "| `{}` | `{}` | {} | {} |\n",
+            entry.key,
+            cell(&short_type_name(entry.type_name)),
+            cell(&default),
+            cell(&description)
+        );
+    }
+    out
+}
+
+/// Make "s" safe to put in a table cell.
+fn cell(s: &str) -> String {
+    s.replace('|', "\\|")
+        .replace("\r\n", "<br>")
+        .replace('\n', "<br>")
+}
+
+/// "type_name" without module paths, e.g. `Vec<String>` rather than
+/// `alloc::vec::Vec<alloc::string::String>`.
+pub(crate) fn short_type_name(type_name: &str) -> String {
+    let mut out = String::new();
+    let mut path = String::new();
+    for c in type_name.chars() {
+        if c.is_alphanumeric() || c == '_' || c == ':' {
+            path.push(c);
+        } else {
+            out += path.rsplit("::").next().unwrap_or("");
+            path.clear();
+            out.push(c);
+        }
+    }
+    out += path.rsplit("::").next().unwrap_or("");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn type_names() {
+        assert_eq!(short_type_name("i32"), "i32");
+        assert_eq!(
+            short_type_name("std::collections::hash::map::HashMap<alloc::string::String, f64>"),
+            "HashMap<String, f64>"
+        );
+        assert_eq!(
+            short_type_name("(alloc::string::String, u16)"),
+            "(String, u16)"
+        );
+    }
+
+    #[test]
+    fn table() {
+        let entries = vec![
+            RegistryEntry {
+                key: "DOCS_FORMAT".to_string(),
+                type_name: "alloc::string::String",
+                value: None,
+                from_env: false,
+                hashed: false,
+                default: Some("a|b".to_string()),
+                sensitive: false,
+                text: false,
+                required: false,
+                choices: vec!["json".to_string(), "text".to_string()],
+            },
+            RegistryEntry {
+                key: "DOCS_TOKEN".to_string(),
+                type_name: "biodome::secret::Secret<alloc::string::String>",
+                value: None,
+                from_env: false,
+                hashed: false,
+                default: Some("dev".to_string()),
+                sensitive: true,
+                text: false,
+                required: false,
+                choices: vec![],
+            },
+        ];
+        let table = render(&entries);
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(
+            lines[2],
+            "| `DOCS_FORMAT` | `String` | `a\\|b` | One of: `json`, `text`. |"
+        );
+        assert_eq!(
+            lines[3],
+            "| `DOCS_TOKEN` | `Secret<String>` | *hidden* |  |"
+        );
+    }
+}
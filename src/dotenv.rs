@@ -0,0 +1,419 @@
+//! Read `.env` files.
+//!
+//! [`load`] reads a `.env` file and makes its entries visible to
+//! `biodome()`, beneath the process environment, so a variable that
+//! is really set always wins over the file:
+//!
+//! ```rust,no_run
+//! biodome::dotenv::load(".env").unwrap();
+//! let PORT = biodome::biodome("PORT", 8080);
+//! ```
+//!
+//! The file has one `KEY=value` per line. Lines may start with
+//! `export`, and blank lines and lines starting with `#` are skipped.
+//! Values can be:
+//!
+//! - unquoted, ending at the end of the line or at a ` #` comment, with
+//!   surrounding whitespace removed;
+//! - single-quoted, taken literally;
+//! - double-quoted, with `\n`, `\t`, `\"` and `\\` escapes.
+//!
+//! Quoted values can span several lines.
+//!
+//! [`load_overlays`] follows the convention of many web frameworks,
+//! where `APP_ENV=staging` adds `.env.staging` over `.env`.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+use crate::source::{self, EnvSource, Layers, StdEnv};
+
+/// The entries of a `.env` file.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Dotenv {
+    pub vars: BTreeMap<String, String>,
+}
+
+impl Dotenv {
+    /// Parse the text of a `.env` file. The error names the line with
+    /// the problem.
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let mut vars = BTreeMap::new();
+        let mut rest = s;
+        let mut line = 1;
+        while !rest.is_empty() {
+            let (entry, remaining) =
+                parse_line(rest).map_err(|err| format!("line {}: {}", line, err))?;
+            line += rest[..rest.len() - remaining.len()].matches('\n').count();
+            rest = remaining;
+            if let Some((key, value)) = entry {
+                vars.insert(key, value);
+            }
+        }
+        Ok(Dotenv { vars })
+    }
+
+    /// Read and parse a `.env` file.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let text = fs::read_to_string(path)?;
+        Dotenv::parse(&text).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+}
+
+impl EnvSource for Dotenv {
+    fn get(&self, key: &str) -> Option<String> {
+        self.vars.get(key).cloned()
+    }
+
+    fn names(&self) -> Vec<String> {
+        self.vars.keys().cloned().collect()
+    }
+
+    fn kinds(&self) -> Vec<&'static str> {
+        vec!["dotenv"]
+    }
+}
+
+/// Read the `.env` file at "path" and look up env vars in the process
+/// environment first, then in the file.
+///
+/// The file is read again when the source is
+/// [refreshed](crate::refresh_source).
+pub fn load<P: AsRef<Path>>(path: P) -> io::Result<()> {
+    let path = path.as_ref().to_path_buf();
+    let paths = vec![path.clone()];
+    let dotenv = Reread::new(paths, move || Dotenv::from_path(&path))?;
+    source::set_source(layered(dotenv)?);
+    Ok(())
+}
+
+/// A stack of `.env` files, where a file overrides the ones after it.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Overlay {
+    /// The files that exist, highest priority first.
+    pub files: Vec<(PathBuf, Dotenv)>,
+}
+
+impl Overlay {
+    /// Read the `.env` files in "dir" for the environment "app_env",
+    /// in this order, the first taking priority:
+    ///
+    /// 1. `.env.{app_env}.local`
+    /// 2. `.env.local`
+    /// 3. `.env.{app_env}`
+    /// 4. `.env`
+    ///
+    /// Files that don't exist are skipped. Without "app_env", only
+    /// `.env.local` and `.env` are read.
+    pub fn read<P: AsRef<Path>>(dir: P, app_env: Option<&str>) -> io::Result<Self> {
+        let mut names = vec![];
+        if let Some(app_env) = app_env {
+            names.push(format!(".env.{}.local", app_env));
+        }
+        names.push(".env.local".to_string());
+        if let Some(app_env) = app_env {
+            names.push(format!(".env.{}", app_env));
+        }
+        names.push(".env".to_string());
+        let mut files = vec![];
+        for name in names {
+            let path = dir.as_ref().join(name);
+            match Dotenv::from_path(&path) {
+                Ok(dotenv) => files.push((path, dotenv)),
+                Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(Overlay { files })
+    }
+
+    /// The file that supplies "key", if any of them has it.
+    pub fn origin(&self, key: &str) -> Option<&Path> {
+        self.files
+            .iter()
+            .find(|(_, dotenv)| dotenv.vars.contains_key(key))
+            .map(|(path, _)| path.as_path())
+    }
+}
+
+impl EnvSource for Overlay {
+    fn get(&self, key: &str) -> Option<String> {
+        self.files.iter().find_map(|(_, dotenv)| dotenv.get(key))
+    }
+
+    fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.files.iter().flat_map(|(_, d)| d.names()).collect();
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    fn provenance(&self, key: &str) -> Option<String> {
+        self.origin(key).map(|path| path.display().to_string())
+    }
+
+    fn watch_paths(&self) -> Vec<PathBuf> {
+        self.files.iter().map(|(path, _)| path.clone()).collect()
+    }
+
+    fn kinds(&self) -> Vec<&'static str> {
+        vec!["dotenv"]
+    }
+}
+
+/// Read the `.env` files in "dir" for the environment named by the
+/// `APP_ENV` env var, in the order given by [`Overlay::read`], and
+/// look up env vars in the process environment first, then in the
+/// files. The files are read again when the source is
+/// [refreshed](crate::refresh_source).
+///
+/// The returned [`Overlay`] tells which file supplied a value:
+///
+/// ```rust,no_run
+/// let overlay = biodome::dotenv::load_overlays(".").unwrap();
+/// if let Some(path) = overlay.origin("DATABASE_URL") {
+///     println!("DATABASE_URL is from {}", path.display());
+/// }
+/// ```
+pub fn load_overlays<P: AsRef<Path>>(dir: P) -> io::Result<Overlay> {
+    let app_env = source::get("APP_ENV").filter(|e| !e.is_empty());
+    let dir = dir.as_ref().to_path_buf();
+    let paths = vec![dir.clone()];
+    let overlay = Reread::new(paths, move || Overlay::read(&dir, app_env.as_deref()))?;
+    let files = overlay.current.read().unwrap().clone();
+    source::set_source(layered(overlay)?);
+    Ok(files)
+}
+
+/// The process environment over "files", or an error if the policy of
+/// the active profile doesn't allow `.env` files.
+fn layered(files: impl EnvSource + 'static) -> io::Result<Layers> {
+    Layers::new()
+        .with(StdEnv)
+        .try_with(files)
+        .map_err(|err| io::Error::new(io::ErrorKind::PermissionDenied, err))
+}
+
+type Read<T> = Box<dyn Fn() -> io::Result<T> + Send + Sync>;
+
+/// Files that are read with "read", and read again when refreshed.
+/// Changes to "paths" are changes to the files.
+struct Reread<T> {
+    paths: Vec<PathBuf>,
+    read: Read<T>,
+    current: RwLock<T>,
+}
+
+impl<T> Reread<T> {
+    fn new(
+        paths: Vec<PathBuf>,
+        read: impl Fn() -> io::Result<T> + Send + Sync + 'static,
+    ) -> io::Result<Self> {
+        Ok(Reread {
+            paths,
+            current: RwLock::new(read()?),
+            read: Box::new(read),
+        })
+    }
+}
+
+impl<T: EnvSource> EnvSource for Reread<T> {
+    fn get(&self, key: &str) -> Option<String> {
+        self.current.read().unwrap().get(key)
+    }
+
+    fn names(&self) -> Vec<String> {
+        self.current.read().unwrap().names()
+    }
+
+    fn provenance(&self, key: &str) -> Option<String> {
+        self.current.read().unwrap().provenance(key)
+    }
+
+    fn refresh(&self) -> io::Result<()> {
+        let files = (self.read)()?;
+        *self.current.write().unwrap() = files;
+        Ok(())
+    }
+
+    fn watch_paths(&self) -> Vec<PathBuf> {
+        self.paths.clone()
+    }
+
+    fn kinds(&self) -> Vec<&'static str> {
+        self.current.read().unwrap().kinds()
+    }
+}
+
+type Entry = Option<(String, String)>;
+
+/// Parse one entry from the start of "s", which may take more than
+/// one line if the value is quoted. Returns the entry, if the line had
+/// one, and the text after it.
+fn parse_line(s: &str) -> Result<(Entry, &str), &'static str> {
+    let (first, after_first) = split_line(s);
+    let trimmed = first.trim();
+    if trimmed.is_empty() || trimmed.starts_with('#') {
+        return Ok((None, after_first));
+    }
+    let start = s.len() - s.trim_start().len();
+    let mut body = &s[start..];
+    if let Some(stripped) = body.strip_prefix("export") {
+        if stripped.starts_with([' ', '\t']) {
+            body = stripped.trim_start_matches([' ', '\t']);
+        }
+    }
+    let eq = split_line(body).0.find('=').ok_or("expected KEY=value")?;
+    let key = body[..eq].trim();
+    if !valid_key(key) {
+        return Err("invalid key");
+    }
+    let value = body[eq + 1..].trim_start_matches([' ', '\t']);
+    let (value, rest) = match value.chars().next() {
+        Some('\'') => {
+            let end = value[1..].find('\'').ok_or("unterminated single quote")?;
+            (value[1..end + 1].to_string(), &value[end + 2..])
+        }
+        Some('"') => parse_double_quoted(&value[1..])?,
+        _ => {
+            let (line, rest) = split_line(value);
+            let line = match line.find(" #").or_else(|| line.find("\t#")) {
+                Some(i) => &line[..i],
+                None => line,
+            };
+            return Ok((Some((key.to_string(), line.trim().to_string())), rest));
+        }
+    };
+    // Only a comment may follow a quoted value.
+    let (trailing, rest) = split_line(rest);
+    let trailing = trailing.trim();
+    if !trailing.is_empty() && !trailing.starts_with('#') {
+        return Err("unexpected text after quoted value");
+    }
+    Ok((Some((key.to_string(), value)), rest))
+}
+
+/// The value of a double-quoted string whose opening quote has been
+/// consumed, and the text after the closing quote.
+fn parse_double_quoted(s: &str) -> Result<(String, &str), &'static str> {
+    let mut value = String::new();
+    let mut chars = s.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Ok((value, &s[i + 1..])),
+            '\\' => match chars.next() {
+                Some((_, 'n')) => value.push('\n'),
+                Some((_, 'r')) => value.push('\r'),
+                Some((_, 't')) => value.push('\t'),
+                Some((_, c @ ('"' | '\\' | '$'))) => value.push(c),
+                Some((_, c)) => {
+                    value.push('\\');
+                    value.push(c);
+                }
+                None => break,
+            },
+            c => value.push(c),
+        }
+    }
+    Err("unterminated double quote")
+}
+
+/// The first line of "s", without its line ending, and the rest.
+fn split_line(s: &str) -> (&str, &str) {
+    match s.find('\n') {
+        Some(i) => (s[..i].trim_end_matches('\r'), &s[i + 1..]),
+        None => (s, ""),
+    }
+}
+
+fn valid_key(key: &str) -> bool {
+    let mut chars = key.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let text = concat!(
+            "# Settings for local development\n",
+            "\n",
+            "PORT=8080\n",
+            "export HOST = localhost  # the dev box\n",
+            "PATTERN='a #literal $HOME \\n'\n",
+            "MOTD=\"line one\\nline \\\"two\\\"\" # comment\n",
+            "CERT=\"-----BEGIN-----\n",
+            "abc\n",
+            "-----END-----\"\n",
+            "EMPTY=\n",
+            "URL=http://x/#anchor\r\n",
+            "exported=1",
+        );
+        let vars = Dotenv::parse(text).unwrap().vars;
+        assert_eq!(vars["PORT"], "8080");
+        assert_eq!(vars["HOST"], "localhost");
+        assert_eq!(vars["PATTERN"], "a #literal $HOME \\n");
+        assert_eq!(vars["MOTD"], "line one\nline \"two\"");
+        assert_eq!(vars["CERT"], "-----BEGIN-----\nabc\n-----END-----");
+        assert_eq!(vars["EMPTY"], "");
+        assert_eq!(vars["URL"], "http://x/#anchor");
+        assert_eq!(vars["exported"], "1");
+        assert_eq!(vars.len(), 8);
+        let vars = Dotenv::parse("A=\"1\n\n2\"\nB=3 4=5").unwrap().vars;
+        assert_eq!(vars["A"], "1\n\n2");
+        assert_eq!(vars["B"], "3 4=5");
+    }
+
+    #[test]
+    fn overlays() {
+        let dir = std::env::temp_dir().join(format!("biodome-overlay-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join(".env"), "A=base\nB=base\nC=base\n").unwrap();
+        fs::write(dir.join(".env.staging"), "A=staging\nB=staging\n").unwrap();
+        fs::write(dir.join(".env.staging.local"), "A=staging-local\n").unwrap();
+        let overlay = Overlay::read(&dir, Some("staging")).unwrap();
+        let base = Overlay::read(&dir, None).unwrap();
+        let missing = Overlay::read(dir.join("missing"), Some("staging")).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(overlay.files.len(), 3);
+        assert_eq!(overlay.get("A"), Some("staging-local".to_string()));
+        assert_eq!(overlay.get("B"), Some("staging".to_string()));
+        assert_eq!(overlay.get("C"), Some("base".to_string()));
+        assert_eq!(overlay.get("D"), None);
+        assert_eq!(
+            overlay.origin("B"),
+            Some(dir.join(".env.staging").as_path())
+        );
+        assert_eq!(overlay.origin("C"), Some(dir.join(".env").as_path()));
+        assert_eq!(overlay.origin("D"), None);
+        assert_eq!(base.get("A"), Some("base".to_string()));
+        assert!(missing.files.is_empty());
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            Dotenv::parse("A=1\nB\nC=2"),
+            Err("line 2: expected KEY=value".to_string())
+        );
+        assert_eq!(
+            Dotenv::parse("A=\"1\n\n2\"\nB=3 4=5\n1X=2"),
+            Err("line 5: invalid key".to_string())
+        );
+        assert_eq!(
+            Dotenv::parse("A='1"),
+            Err("line 1: unterminated single quote".to_string())
+        );
+        assert_eq!(
+            Dotenv::parse("A=\"1\" 2"),
+            Err("line 1: unexpected text after quoted value".to_string())
+        );
+    }
+}
@@ -0,0 +1,168 @@
+use std::collections::BTreeMap;
+use std::ops::RangeInclusive;
+use std::time::Duration;
+
+use crate::rawconv::from_duration;
+use crate::{biodome_or_error, BiodomeError};
+
+/// A group of named durations read from one env var, such as
+/// timeouts, with a default and bounds for each name.
+///
+/// The env var is a TOML inline table of durations like `"2s"` or
+/// `"500ms"`, or whole numbers of seconds. Its entries are merged
+/// over the defaults, so only the ones that differ need to be set.
+/// Names that weren't declared, and durations outside their bounds,
+/// are errors.
+///
+/// ```rust
+/// use std::time::Duration;
+/// use biodome::DurationMap;
+///
+/// let secs = Duration::from_secs;
+/// std::env::set_var("TIMEOUTS", r#"{ request = "30s" }"#);
+/// let timeouts = DurationMap::new()
+///     .bounded("connect", secs(2), secs(1)..=secs(10))
+///     .bounded("request", secs(10), secs(1)..=secs(60))
+///     .with("idle", secs(90))
+///     .read("TIMEOUTS")
+///     .unwrap();
+/// assert_eq!(timeouts.get("connect"), Some(secs(2)));
+/// assert_eq!(timeouts.get("request"), Some(secs(30)));
+///
+/// std::env::set_var("TIMEOUTS", r#"{ request = "5m" }"#);
+/// let err = DurationMap::new()
+///     .bounded("request", secs(10), secs(1)..=secs(60))
+///     .read("TIMEOUTS")
+///     .unwrap_err();
+/// assert_eq!(
+///     err.to_string(),
+///     "Invalid value for env var TIMEOUTS: request is 5m, not between 1s and 1m"
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct DurationMap {
+    entries: BTreeMap<String, Entry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Entry {
+    value: Duration,
+    bounds: RangeInclusive<Duration>,
+}
+
+impl DurationMap {
+    pub fn new() -> Self {
+        DurationMap::default()
+    }
+
+    /// Declare "name", with no bounds.
+    pub fn with(self, name: &str, default: Duration) -> Self {
+        self.bounded(name, default, Duration::ZERO..=Duration::MAX)
+    }
+
+    /// Declare "name", which must be within "bounds".
+    pub fn bounded(
+        mut self,
+        name: &str,
+        default: Duration,
+        bounds: RangeInclusive<Duration>,
+    ) -> Self {
+        let entry = Entry {
+            value: default,
+            bounds,
+        };
+        self.entries.insert(name.to_string(), entry);
+        self
+    }
+
+    /// The duration for "name", if it has been declared.
+    pub fn get(&self, name: &str) -> Option<Duration> {
+        self.entries.get(name).map(|entry| entry.value)
+    }
+
+    /// The names and durations, in order of name.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, Duration)> {
+        self.entries
+            .iter()
+            .map(|(name, entry)| (name.as_str(), entry.value))
+    }
+
+    /// Read the env var "key", and merge its entries over the
+    /// defaults.
+    pub fn read(&self, key: &str) -> Result<DurationMap, BiodomeError> {
+        let defaults: BTreeMap<String, Duration> =
+            self.iter().map(|(n, d)| (n.to_string(), d)).collect();
+        let set = biodome_or_error::<BTreeMap<String, Duration>, _>(key, defaults)
+            .map_err(|(err, _default)| err)?;
+        let mut merged = self.clone();
+        for (name, value) in set {
+            let Some(entry) = merged.entries.get_mut(&name) else {
+                let names: Vec<&str> = self.entries.keys().map(|n| n.as_str()).collect();
+                return Err(BiodomeError::Parse {
+                    key: key.to_string(),
+                    reason: format!(
+                        "unknown name {}, expected one of: {}",
+                        name,
+                        names.join(", ")
+                    ),
+                });
+            };
+            if !entry.bounds.contains(&value) {
+                return Err(BiodomeError::Invalid {
+                    key: key.to_string(),
+                    reason: format!(
+                        "{} is {}, not between {} and {}",
+                        name,
+                        from_duration(value),
+                        from_duration(*entry.bounds.start()),
+                        from_duration(*entry.bounds.end())
+                    ),
+                });
+            }
+            entry.value = value;
+        }
+        Ok(merged)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merge() {
+        let spec = DurationMap::new()
+            .with("connect", Duration::from_secs(2))
+            .bounded(
+                "request",
+                Duration::from_secs(10),
+                Duration::from_secs(1)..=Duration::from_secs(60),
+            );
+        assert_eq!(spec.read("DURATIONS_UNSET"), Ok(spec.clone()));
+
+        std::env::set_var("DURATIONS_MERGE", "{ connect = 5, request = \"1500ms\" }");
+        let map = spec.read("DURATIONS_MERGE").unwrap();
+        assert_eq!(
+            map.iter().collect::<Vec<_>>(),
+            vec![
+                ("connect", Duration::from_secs(5)),
+                ("request", Duration::from_millis(1500)),
+            ]
+        );
+
+        std::env::set_var("DURATIONS_MERGE", "{ conect = \"1s\" }");
+        assert_eq!(
+            spec.read("DURATIONS_MERGE"),
+            Err(BiodomeError::Parse {
+                key: "DURATIONS_MERGE".to_string(),
+                reason: "unknown name conect, expected one of: connect, request".to_string()
+            })
+        );
+        std::env::set_var("DURATIONS_MERGE", "{ request = \"500ms\" }");
+        assert!(matches!(
+            spec.read("DURATIONS_MERGE"),
+            Err(BiodomeError::Invalid { .. })
+        ));
+        std::env::remove_var("DURATIONS_MERGE");
+    }
+}
@@ -0,0 +1,108 @@
+use std::fmt;
+use std::sync::{Arc, RwLock};
+
+/// Errors produced while reading env vars.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BiodomeError {
+    /// The value of the env var could not be parsed.
+    Parse { key: String, reason: String },
+    /// The env var is required, but not set.
+    Missing { key: String },
+    /// The value of the env var parsed, but was refused by a
+    /// [`Validator`](crate::Validator).
+    Invalid { key: String, reason: String },
+    /// The value of the env var is not one of the allowed choices.
+    /// `suggestions` holds the closest choices, best first.
+    NotOneOf {
+        key: String,
+        value: String,
+        choices: Vec<String>,
+        suggestions: Vec<String>,
+    },
+    /// The env var is read by more than one group of
+    /// [`Settings`](crate::Settings), which would then share its value
+    /// by accident.
+    Collision { key: String, owners: Vec<String> },
+    /// A source of this kind is not allowed in the active profile, by
+    /// [`allow_sources`](crate::allow_sources).
+    Forbidden { kind: String, profile: String },
+}
+
+/// Turns a [`BiodomeError`] into the message shown to operators.
+///
+/// Install your own renderer with [`set_renderer`] to localize or
+/// rephrase messages. The structured error data is untouched; only
+/// its `Display` output (and therefore panic messages) changes.
+pub trait ErrorRenderer: Send + Sync {
+    fn render(&self, error: &BiodomeError) -> String;
+}
+
+/// The built-in English messages.
+pub struct DefaultRenderer;
+
+impl ErrorRenderer for DefaultRenderer {
+    fn render(&self, error: &BiodomeError) -> String {
+        match error {
+            BiodomeError::Parse { key, reason } => {
+                format!("Failed to parse env var {}: {}", key, reason)
+            }
+            BiodomeError::Missing { key } => format!("Missing required env var {}", key),
+            BiodomeError::Invalid { key, reason } => {
+                format!("Invalid value for env var {}: {}", key, reason)
+            }
+            BiodomeError::NotOneOf {
+                key,
+                value,
+                choices,
+                suggestions,
+            } => {
+                let mut msg = format!("Invalid value for env var {}: got '{}'", key, value);
+                if suggestions.is_empty() {
+                    msg += &format!(", expected one of: {}", choices.join(", "));
+                } else {
+                    let quoted: Vec<String> =
+                        suggestions.iter().map(|s| format!("'{}'", s)).collect();
+                    msg += &format!(", did you mean {}?", quoted.join(" or "));
+                }
+                msg
+            }
+            BiodomeError::Collision { key, owners } => {
+                format!(
+                    "Env var {} is read by more than one of: {}",
+                    key,
+                    owners.join(", ")
+                )
+            }
+            BiodomeError::Forbidden { kind, profile } => {
+                format!("Source {} is not allowed in profile {}", kind, profile)
+            }
+        }
+    }
+}
+
+static RENDERER: RwLock<Option<Arc<dyn ErrorRenderer>>> = RwLock::new(None);
+
+/// Use "renderer" for all error messages from now on.
+pub fn set_renderer(renderer: impl ErrorRenderer + 'static) {
+    *RENDERER.write().unwrap() = Some(Arc::new(renderer));
+}
+
+/// Go back to the built-in messages.
+pub fn reset_renderer() {
+    *RENDERER.write().unwrap() = None;
+}
+
+impl fmt::Display for BiodomeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // The lock isn't held while the renderer runs, so that one
+        // that panics can't poison it.
+        let renderer = RENDERER.read().unwrap().clone();
+        let msg = match renderer {
+            Some(r) => r.render(self),
+            None => DefaultRenderer.render(self),
+        };
+        f.write_str(&msg)
+    }
+}
+
+impl std::error::Error for BiodomeError {}
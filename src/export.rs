@@ -0,0 +1,805 @@
+//! Write env vars out as shell scripts.
+//!
+//! [`resolved`] captures the env vars that have been read through
+//! biodome, so a configuration can be reproduced in another shell.
+//! [`render`] writes any list of variables, and [`Envrc`] builds a
+//! direnv `.envrc` for local development.
+//!
+//! The same settings can also be written out for deployment tools:
+//! [`compose_environment`] for docker-compose,
+//! [`github_actions_env`] for GitHub Actions workflows,
+//! [`terraform_variables`] for Terraform, [`nomad_env`] for Nomad jobs
+//! and [`ecs_environment`] for ECS task definitions.
+//!
+//! [`write`] saves any of them to a file that other processes may be
+//! reading, such as a shared `.env`.
+//!
+//! ```rust
+//! use biodome::export::{render, Format};
+//!
+//! let vars = [("GREETING", "it's a \"test\"")];
+//! assert_eq!(render(&vars, Format::Sh), "export GREETING='it'\\''s a \"test\"'\n");
+//! assert_eq!(render(&vars, Format::Fish), "set -gx GREETING 'it\\'s a \"test\"'\n");
+//! ```
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+
+use crate::registry;
+
+/// The shells that env vars can be exported for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// POSIX `sh`: `export KEY='value'`
+    Sh,
+    /// `bash`: like `Sh`, but values with control characters such as
+    /// newlines use `$'...'` quoting so they survive on one line.
+    Bash,
+    /// `fish`: `set -gx KEY 'value'`
+    Fish,
+    /// PowerShell: `$env:KEY = 'value'`
+    PowerShell,
+    /// Windows `cmd` batch files: `set "KEY=value"`. `cmd` has no way
+    /// to put a newline in a variable, so newlines become spaces.
+    Cmd,
+}
+
+/// Quote "value" so that the shell for "format" reads it back
+/// unchanged, whatever spaces, quotes or newlines it contains.
+pub fn quote(value: &str, format: Format) -> String {
+    match format {
+        Format::Sh => quote_sh(value),
+        Format::Bash => {
+            if value.chars().any(|c| c.is_control()) {
+                quote_ansi_c(value)
+            } else {
+                quote_sh(value)
+            }
+        }
+        Format::Fish => format!("'{}'", value.replace('\\', "\\\\").replace('\'', "\\'")),
+        Format::PowerShell => format!("'{}'", quote_powershell_inner(value)),
+        Format::Cmd => value.replace('%', "%%").replace(['\r', '\n'], " "),
+    }
+}
+
+/// One line that sets "key" to "value" in the shell for "format".
+pub fn line(key: &str, value: &str, format: Format) -> String {
+    let value = quote(value, format);
+    match format {
+        Format::Sh | Format::Bash => format!("export {}={}", key, value),
+        Format::Fish => format!("set -gx {} {}", key, value),
+        Format::PowerShell => format!("$env:{} = {}", key, value),
+        Format::Cmd => format!("set \"{}={}\"", key, value),
+    }
+}
+
+fn comment(text: &str, format: Format) -> String {
+    match format {
+        Format::Cmd => format!("rem {}", text),
+        _ => format!("# {}", text),
+    }
+}
+
+/// A script that sets all of "vars" in the shell for "format".
+pub fn render<K: AsRef<str>, V: AsRef<str>>(vars: &[(K, V)], format: Format) -> String {
+    vars.iter()
+        .map(|(k, v)| line(k.as_ref(), v.as_ref(), format) + "\n")
+        .collect()
+}
+
+/// A script that sets every env var read through biodome so far to
+/// the value it had. Keys that used their default are left out, since
+/// they resolve the same way without being set. Secrets, keys whose
+/// names look sensitive, and values too long to keep in the
+/// [registry](crate::registry), are not written; a comment marks
+/// where they were left out.
+pub fn resolved(format: Format) -> String {
+    let mut out = String::new();
+    for entry in registry::registry() {
+        if !entry.from_env() {
+            continue;
+        }
+        match &entry.value {
+            _ if entry.redacted() => {
+                out += &comment(
+                    &format!("{} is a secret and was not exported", entry.key),
+                    format,
+                )
+            }
+            Some(value) if !entry.hashed => out += &line(&entry.key, value, format),
+            _ => {
+                out += &comment(
+                    &format!("{} is too long and was not exported", entry.key),
+                    format,
+                )
+            }
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// Builds a direnv `.envrc` for local development from the settings
+/// that have been read through biodome.
+///
+/// Every key gets an `export` line with the value it resolved to:
+/// the env var if it was set, otherwise the default. Secrets, and
+/// keys without a default that can be written out, get a commented
+/// placeholder to fill in.
+///
+/// ```rust
+/// use biodome::{biodome, export::Envrc};
+///
+/// let PORT = biodome("PORT", 8080);
+/// let envrc = Envrc::new().uses("nix").watch_file("config.toml").render();
+/// assert!(envrc.contains("use nix\n"));
+/// assert!(envrc.contains("watch_file 'config.toml'\n"));
+/// assert!(envrc.contains("export PORT='8080'\n"));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Envrc {
+    uses: Vec<String>,
+    watch_files: Vec<String>,
+    dotenvs: Vec<String>,
+}
+
+impl Envrc {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a `use` line, e.g. `uses("nix")` or `uses("flake")`.
+    pub fn uses(mut self, name: &str) -> Self {
+        self.uses.push(name.to_string());
+        self
+    }
+
+    /// Reload the environment when "path" changes.
+    pub fn watch_file(mut self, path: &str) -> Self {
+        self.watch_files.push(path.to_string());
+        self
+    }
+
+    /// Also load "path", a dotenv file, if it exists. It is loaded
+    /// after the generated exports, so it can override them.
+    pub fn dotenv_if_exists(mut self, path: &str) -> Self {
+        self.dotenvs.push(path.to_string());
+        self
+    }
+
+    pub fn render(&self) -> String {
+        let mut out = String::from("# Generated by biodome\n");
+        for name in &self.uses {
+            out += &format!("use {}\n", name);
+        }
+        for path in &self.watch_files {
+            out += &format!("watch_file {}\n", quote(path, Format::Bash));
+        }
+        for (key, value) in resolved_values() {
+            match value {
+                Some(v) => out += &line(&key, &v, Format::Bash),
+                None => out += &format!("# export {}=", key),
+            }
+            out.push('\n');
+        }
+        for path in &self.dotenvs {
+            out += &format!("dotenv_if_exists {}\n", quote(path, Format::Bash));
+        }
+        out
+    }
+
+    /// Render the `.envrc` and [`write`] it to "path".
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        write(path, &self.render())
+    }
+}
+
+/// Write "contents" to the file at "path", safely when other processes
+/// read or write it at the same time.
+///
+/// Writers take an advisory lock on `{path}.lock` first, so they take
+/// turns instead of interleaving. The contents go to a temporary file
+/// that then replaces "path" in one step, so readers, which don't
+/// need the lock, see either the old file or the new one and never
+/// half of one.
+///
+/// ```rust,no_run
+/// use biodome::export::{self, Format};
+///
+/// export::write(".env", &export::resolved(Format::Sh)).unwrap();
+/// ```
+pub fn write<P: AsRef<Path>>(path: P, contents: &str) -> io::Result<()> {
+    let path = path.as_ref();
+    let mut lock_path = path.as_os_str().to_owned();
+    lock_path.push(".lock");
+    let lock = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(lock_path)?;
+    lock.lock()?;
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(format!(".{}.tmp", std::process::id()));
+    let result =
+        write_synced(Path::new(&tmp_path), contents).and_then(|()| fs::rename(&tmp_path, path));
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp_path);
+    }
+    result
+}
+
+fn write_synced(path: &Path, contents: &str) -> io::Result<()> {
+    let mut file = File::create(path)?;
+    file.write_all(contents.as_bytes())?;
+    file.sync_all()
+}
+
+/// A docker-compose `environment:` block for the settings that have
+/// been read through biodome, indented to go under a service.
+///
+/// Each key gets the value it resolved to. Secrets, and keys without
+/// a value that can be written out, are emitted as `${KEY}`
+/// placeholders, which compose fills in from the shell or from an
+/// `.env` file next to the compose file; see [`compose_env_file`].
+///
+/// ```rust
+/// use biodome::{biodome, export};
+///
+/// let PORT = biodome("PORT", 8080);
+/// let block = export::compose_environment();
+/// assert!(block.starts_with("    environment:\n"));
+/// assert!(block.contains("      PORT: \"8080\"\n"));
+/// ```
+pub fn compose_environment() -> String {
+    let mut out = String::from("    environment:\n");
+    for (key, value) in resolved_values() {
+        let value = match value {
+            Some(v) => quote_yaml(&v.replace('$', "$$")),
+            None => format!("${{{}}}", key),
+        };
+        out += &format!("      {}: {}\n", key, value);
+    }
+    out
+}
+
+/// A stub for the `.env` file that fills in the placeholders left by
+/// [`compose_environment`]. Fill in the values and keep the file out
+/// of version control.
+pub fn compose_env_file() -> String {
+    let mut out = String::from("# Values for the placeholders in docker-compose.yml\n");
+    for (key, value) in resolved_values() {
+        if value.is_none() {
+            out += &format!("{}=\n", key);
+        }
+    }
+    out
+}
+
+/// What can be written out for one key.
+enum Resolved {
+    Value(String),
+    /// The value must not be written out.
+    Secret,
+    /// The default was used, and it can't be written out.
+    Unknown,
+}
+
+impl Resolved {
+    fn value(self) -> Option<String> {
+        match self {
+            Resolved::Value(v) => Some(v),
+            _ => None,
+        }
+    }
+}
+
+/// Every key read so far, with the value it resolved to.
+fn resolved_entries() -> Vec<(String, Resolved)> {
+    registry::registry()
+        .into_iter()
+        .map(|entry| {
+            let value = if entry.redacted() {
+                Resolved::Secret
+            } else if entry.hashed {
+                Resolved::Unknown
+            } else {
+                match entry.value.or(entry.default) {
+                    Some(v) => Resolved::Value(v),
+                    None => Resolved::Unknown,
+                }
+            };
+            (entry.key, value)
+        })
+        .collect()
+}
+
+/// Every key read so far, with the value it resolved to, or `None`
+/// if that must not, or can't, be written out.
+fn resolved_values() -> Vec<(String, Option<String>)> {
+    resolved_entries()
+        .into_iter()
+        .map(|(key, value)| (key, value.value()))
+        .collect()
+}
+
+/// A GitHub Actions `env:` mapping for the settings that have been
+/// read through biodome, for use at the workflow or job level.
+///
+/// Each key gets the value it resolved to. Secrets become
+/// `${{ secrets.KEY }}` references, which need a repository secret
+/// of the same name; [`github_actions_secrets`] lists them. Keys
+/// without a default that can be written out become
+/// `${{ vars.KEY }}` references to repository variables.
+///
+/// ```rust
+/// use biodome::{biodome, export, Secret};
+///
+/// let PORT = biodome("PORT", 8080);
+/// let API_TOKEN = biodome("API_TOKEN", Secret::new(String::new()));
+/// let block = export::github_actions_env();
+/// assert!(block.contains("  PORT: \"8080\"\n"));
+/// assert!(block.contains("  API_TOKEN: ${{ secrets.API_TOKEN }}\n"));
+/// assert_eq!(export::github_actions_secrets(), vec!["API_TOKEN"]);
+/// ```
+pub fn github_actions_env() -> String {
+    let mut out = String::from("env:\n");
+    for (key, value) in resolved_entries() {
+        let value = match value {
+            Resolved::Value(v) => quote_yaml(&v),
+            Resolved::Secret => format!("${{{{ secrets.{} }}}}", key),
+            Resolved::Unknown => format!("${{{{ vars.{} }}}}", key),
+        };
+        out += &format!("  {}: {}\n", key, value);
+    }
+    out
+}
+
+/// The keys that [`github_actions_env`] expects to find as
+/// repository secrets.
+pub fn github_actions_secrets() -> Vec<String> {
+    resolved_entries()
+        .into_iter()
+        .filter(|(_, value)| matches!(value, Resolved::Secret))
+        .map(|(key, _)| key)
+        .collect()
+}
+
+/// A Terraform `variables.tf` with a variable for each setting that
+/// has been read through biodome, named after the key in lower case.
+///
+/// Types are mapped to `number`, `bool`, `string`, or a `list` of
+/// those; anything else, such as a map, is a `string` holding the
+/// value as it would be written in the env var. The default comes
+/// from the code, and the description from [`describe`](crate::describe).
+/// Secrets are marked `sensitive` and have no default. Pair it with
+/// [`terraform_env`], which turns the variables back into env vars.
+///
+/// ```rust
+/// use biodome::{biodome, export};
+///
+/// biodome::describe("PORT", "Port the HTTP server listens on");
+/// let PORT = biodome("PORT", 8080);
+/// let tf = export::terraform_variables();
+/// assert!(tf.contains(concat!(
+///     "variable \"port\" {\n",
+///     "  type        = number\n",
+///     "  description = \"Port the HTTP server listens on\"\n",
+///     "  default     = 8080\n",
+///     "}\n",
+/// )));
+/// ```
+pub fn terraform_variables() -> String {
+    let mut out = String::from("# Generated by biodome\n");
+    for entry in registry::registry() {
+        let tf_type = terraform_type(entry.type_name);
+        let description = registry::description(&entry.key)
+            .unwrap_or_else(|| format!("Value for the {} env var.", entry.key));
+        out += &format!("\nvariable \"{}\" {{\n", entry.key.to_lowercase());
+        out += &format!("  type        = {}\n", tf_type);
+        out += &format!("  description = {}\n", quote_hcl(&description));
+        if entry.redacted() {
+            out += "  sensitive   = true\n";
+        } else if let Some(default) = entry.default.and_then(|d| hcl_default(&d, &tf_type)) {
+            out += &format!("  default     = {}\n", default);
+        }
+        out += "}\n";
+    }
+    out
+}
+
+/// A Terraform `locals` block with an `env` map that turns the
+/// variables from [`terraform_variables`] back into env var values,
+/// ready to hand to a container or function definition.
+///
+/// ```rust
+/// use biodome::{biodome, export};
+///
+/// let PORT = biodome("PORT", 8080);
+/// assert!(export::terraform_env().contains("    PORT = tostring(var.port)\n"));
+/// ```
+pub fn terraform_env() -> String {
+    let mut out = String::from("locals {\n  env = {\n");
+    for entry in registry::registry() {
+        let var = format!("var.{}", entry.key.to_lowercase());
+        let value = match terraform_type(entry.type_name).as_str() {
+            "string" => var,
+            "number" | "bool" => format!("tostring({})", var),
+            _ => format!("jsonencode({})", var),
+        };
+        out += &format!("    {} = {}\n", entry.key, value);
+    }
+    out += "  }\n}\n";
+    out
+}
+
+/// A Nomad `env` stanza for the settings that have been read through
+/// biodome, to go inside a task.
+///
+/// Keys that used a default that can't be written out are left out,
+/// since the task resolves them the same way without them. Secrets
+/// don't belong in a job file; each one gets a comment instead, as a
+/// reminder to provide it another way, such as a `template` block
+/// with `env = true`.
+///
+/// ```rust
+/// use biodome::{biodome, export};
+///
+/// let PORT = biodome("PORT", 8080);
+/// let stanza = export::nomad_env();
+/// assert!(stanza.starts_with("env {\n"));
+/// assert!(stanza.contains("  PORT = \"8080\"\n"));
+/// ```
+pub fn nomad_env() -> String {
+    let mut out = String::from("env {\n");
+    for (key, value) in resolved_entries() {
+        match value {
+            Resolved::Value(v) => out += &format!("  {} = {}\n", key, quote_hcl(&v)),
+            Resolved::Secret => {
+                out += &format!("  # {} is a secret; set it from a template\n", key)
+            }
+            Resolved::Unknown => {}
+        }
+    }
+    out += "}\n";
+    out
+}
+
+/// The `environment` array of an ECS container definition, as JSON,
+/// for the settings that have been read through biodome.
+///
+/// Secrets are left out; they go in the `secrets` array from
+/// [`ecs_secrets`]. So are keys that used a default that can't be
+/// written out, since the container resolves them the same way
+/// without them.
+///
+/// ```rust
+/// use biodome::{biodome, export};
+///
+/// let PORT = biodome("PORT", 8080);
+/// assert!(export::ecs_environment().contains("  { \"name\": \"PORT\", \"value\": \"8080\" }"));
+/// ```
+pub fn ecs_environment() -> String {
+    let items: Vec<String> = resolved_entries()
+        .into_iter()
+        .filter_map(|(key, value)| match value {
+            Resolved::Value(v) => Some(format!(
+                "{{ \"name\": {}, \"value\": {} }}",
+                quote_yaml(&key),
+                quote_yaml(&v)
+            )),
+            _ => None,
+        })
+        .collect();
+    json_array(&items)
+}
+
+/// The `secrets` array of an ECS container definition, as JSON. Each
+/// secret is looked up at "arn_prefix" followed by its key, e.g.
+/// `arn:aws:ssm:us-east-1:123456789012:parameter/myapp/` for SSM
+/// parameters named after the keys.
+///
+/// ```rust
+/// use biodome::{biodome, export, Secret};
+///
+/// let API_TOKEN = biodome("API_TOKEN", Secret::new(String::new()));
+/// let secrets = export::ecs_secrets("arn:aws:ssm:us-east-1:123456789012:parameter/app/");
+/// assert!(secrets.contains(concat!(
+///     "  { \"name\": \"API_TOKEN\", ",
+///     "\"valueFrom\": \"arn:aws:ssm:us-east-1:123456789012:parameter/app/API_TOKEN\" }",
+/// )));
+/// ```
+pub fn ecs_secrets(arn_prefix: &str) -> String {
+    let items: Vec<String> = resolved_entries()
+        .into_iter()
+        .filter(|(_, value)| matches!(value, Resolved::Secret))
+        .map(|(key, _)| {
+            format!(
+                "{{ \"name\": {}, \"valueFrom\": {} }}",
+                quote_yaml(&key),
+                quote_yaml(&format!("{}{}", arn_prefix, key))
+            )
+        })
+        .collect();
+    json_array(&items)
+}
+
+/// A JSON array with one item per line.
+fn json_array(items: &[String]) -> String {
+    if items.is_empty() {
+        return "[]\n".to_string();
+    }
+    format!("[\n  {}\n]\n", items.join(",\n  "))
+}
+
+/// The Terraform type for a Rust type name, as given by
+/// `std::any::type_name`.
+fn terraform_type(type_name: &str) -> String {
+    let (path, args) = match type_name.find('<') {
+        Some(i) => (&type_name[..i], &type_name[i + 1..type_name.len() - 1]),
+        None => (type_name, ""),
+    };
+    let name = path.rsplit("::").next().unwrap_or(path);
+    match name {
+        "i8" | "i16" | "i32" | "i64" | "i128" | "isize" | "u8" | "u16" | "u32" | "u64" | "u128"
+        | "usize" | "f32" | "f64" | "ByteSize" => "number".to_string(),
+        _ if name.starts_with("NonZero") => "number".to_string(),
+        "bool" => "bool".to_string(),
+        "Secret" => terraform_type(args),
+        "Vec" | "HashSet" | "BTreeSet" => match terraform_type(args).as_str() {
+            t @ ("number" | "bool" | "string") => format!("list({})", t),
+            _ => "string".to_string(),
+        },
+        _ => "string".to_string(),
+    }
+}
+
+/// A default written as an env var value, as an HCL expression of
+/// type "tf_type".
+fn hcl_default(value: &str, tf_type: &str) -> Option<String> {
+    match tf_type {
+        "number" | "bool" => Some(value.to_string()),
+        "string" => Some(quote_hcl(value)),
+        _ => {
+            let doc: toml::Value = format!("x = {}", value).parse().ok()?;
+            let items: Option<Vec<String>> = doc
+                .get("x")?
+                .as_array()?
+                .iter()
+                .map(|item| match item {
+                    toml::Value::String(s) => Some(quote_hcl(s)),
+                    toml::Value::Integer(_) | toml::Value::Float(_) | toml::Value::Boolean(_) => {
+                        Some(item.to_string())
+                    }
+                    _ => None,
+                })
+                .collect();
+            Some(format!("[{}]", items?.join(", ")))
+        }
+    }
+}
+
+/// A double-quoted HCL string, with template sequences escaped so
+/// the value is taken literally.
+fn quote_hcl(value: &str) -> String {
+    let mut out = String::with_capacity(value.len() + 2);
+    out.push('"');
+    let mut chars = value.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            '$' | '%' if chars.peek() == Some(&'{') => {
+                out.push(c);
+                out.push(c);
+            }
+            c if c.is_control() => out += &format!("\\u{:04x}", c as u32),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// A double-quoted YAML string. Only escapes that JSON shares are
+/// used, so this is a valid JSON string too.
+fn quote_yaml(value: &str) -> String {
+    let mut out = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '"' => out.push_str("\\\""),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => out += &format!("\\u{:04x}", c as u32),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn quote_sh(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+fn quote_ansi_c(value: &str) -> String {
+    let mut out = String::from("$'");
+    for c in value.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '\'' => out.push_str("\\'"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() && (c as u32) < 0x100 => out += &format!("\\x{:02x}", c as u32),
+            c => out.push(c),
+        }
+    }
+    out.push('\'');
+    out
+}
+
+/// PowerShell treats the typographic single quotes like `'`, so all
+/// of them need doubling inside a single-quoted string.
+fn quote_powershell_inner(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '\'' | '\u{2018}' | '\u{2019}' | '\u{201a}' | '\u{201b}') {
+            out.push(c);
+        }
+        out.push(c);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn concurrent_writes() {
+        let dir = std::env::temp_dir().join(format!("biodome-write-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(".env");
+        let contents: Vec<String> = (0..4)
+            .map(|i| format!("N={}\n", i).repeat(10_000))
+            .collect();
+        std::thread::scope(|scope| {
+            for text in &contents {
+                let path = &path;
+                scope.spawn(move || {
+                    for _ in 0..10 {
+                        write(path, text).unwrap();
+                    }
+                });
+            }
+            scope.spawn(|| {
+                for _ in 0..100 {
+                    if let Ok(text) = fs::read_to_string(&path) {
+                        assert!(contents.contains(&text));
+                    }
+                }
+            });
+        });
+        let names: Vec<_> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().into_string().unwrap())
+            .collect();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(names.len(), 2, "{:?}", names);
+    }
+
+    #[test]
+    fn quoting() {
+        let value = "a b 'c' \"d\" $HOME \\ e\nf";
+        assert_eq!(
+            quote(value, Format::Sh),
+            "'a b '\\''c'\\'' \"d\" $HOME \\ e\nf'"
+        );
+        assert_eq!(
+            quote(value, Format::Bash),
+            "$'a b \\'c\\' \"d\" $HOME \\\\ e\\nf'"
+        );
+        assert_eq!(quote("plain", Format::Bash), "'plain'");
+        assert_eq!(
+            quote(value, Format::Fish),
+            "'a b \\'c\\' \"d\" $HOME \\\\ e\nf'"
+        );
+        assert_eq!(
+            quote(value, Format::PowerShell),
+            "'a b ''c'' \"d\" $HOME \\ e\nf'"
+        );
+        assert_eq!(
+            quote("it\u{2019}s", Format::PowerShell),
+            "'it\u{2019}\u{2019}s'"
+        );
+        assert_eq!(quote("bell\u{7}", Format::Bash), "$'bell\\x07'");
+        assert_eq!(
+            quote("100% \"sure\" & ok\n", Format::Cmd),
+            "100%% \"sure\" & ok "
+        );
+    }
+
+    #[test]
+    fn yaml() {
+        assert_eq!(quote_yaml("plain"), "\"plain\"");
+        assert_eq!(quote_yaml("a \"b\" \\ c\nd"), "\"a \\\"b\\\" \\\\ c\\nd\"");
+        assert_eq!(quote_yaml("bell\u{7}"), "\"bell\\u0007\"");
+    }
+
+    #[test]
+    fn hcl() {
+        assert_eq!(quote_hcl("plain"), "\"plain\"");
+        assert_eq!(
+            quote_hcl("a \"b\" \\ ${c} %{d} $e\n"),
+            "\"a \\\"b\\\" \\\\ $${c} %%{d} $e\\n\""
+        );
+        assert_eq!(
+            hcl_default(r#"["a", "b"]"#, "list(string)"),
+            Some("[\"a\", \"b\"]".to_string())
+        );
+        assert_eq!(
+            hcl_default("[1, 2]", "list(number)"),
+            Some("[1, 2]".to_string())
+        );
+        assert_eq!(hcl_default("[[1]]", "list(number)"), None);
+    }
+
+    #[test]
+    fn json() {
+        assert_eq!(json_array(&[]), "[]\n");
+        assert_eq!(
+            json_array(&["1".to_string(), "2".to_string()]),
+            "[\n  1,\n  2\n]\n"
+        );
+    }
+
+    #[test]
+    fn terraform_types() {
+        assert_eq!(terraform_type("i32"), "number");
+        assert_eq!(terraform_type("core::num::nonzero::NonZero<u16>"), "number");
+        assert_eq!(terraform_type("core::num::nonzero::NonZeroU16"), "number");
+        assert_eq!(terraform_type("biodome::bytesize::ByteSize"), "number");
+        assert_eq!(terraform_type("bool"), "bool");
+        assert_eq!(terraform_type("alloc::string::String"), "string");
+        assert_eq!(
+            terraform_type("biodome::secret::Secret<alloc::string::String>"),
+            "string"
+        );
+        assert_eq!(terraform_type("alloc::vec::Vec<f64>"), "list(number)");
+        assert_eq!(
+            terraform_type("alloc::vec::Vec<alloc::vec::Vec<f64>>"),
+            "string"
+        );
+        assert_eq!(
+            terraform_type("std::collections::hash::map::HashMap<alloc::string::String, i32>"),
+            "string"
+        );
+    }
+
+    #[test]
+    fn lines() {
+        let vars = vec![("A", "1"), ("B", "two words")];
+        assert_eq!(
+            render(&vars, Format::Sh),
+            "export A='1'\nexport B='two words'\n"
+        );
+        assert_eq!(
+            render(&vars, Format::Fish),
+            "set -gx A '1'\nset -gx B 'two words'\n"
+        );
+        assert_eq!(
+            render(&vars, Format::PowerShell),
+            "$env:A = '1'\n$env:B = 'two words'\n"
+        );
+        assert_eq!(
+            render(&vars, Format::Cmd),
+            "set \"A=1\"\nset \"B=two words\"\n"
+        );
+    }
+}
@@ -0,0 +1,148 @@
+use ::figment::value::{Dict, Map, Value};
+use ::figment::{Error, Metadata, Profile, Provider};
+
+use crate::{rawconv, source};
+
+/// A [figment](https://docs.rs/figment) provider of the env vars that
+/// start with a prefix, for apps like those built on Rocket that
+/// already assemble their config with figment.
+///
+/// Keys are the names of the env vars without the prefix, in lower
+/// case. Values are read the way biodome reads them, with `${VAR}`
+/// references expanded and the part for the active
+/// [profile](crate::set_profile) picked out, and a `{KEY}_FILE` var
+/// gives the contents of its file as "key". Values are then parsed in
+/// the TOML subset, so lists and inline tables work. Anything else is
+/// a string; use `extract_lossy` to read words like `on` and `no` into
+/// bool fields. As the types of the fields aren't known here, lists
+/// must have their brackets: `a:80,b:80` is a string.
+///
+/// ```rust
+/// use figment::Figment;
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct Config {
+///     port: u16,
+///     debug: bool,
+///     peers: Vec<String>,
+/// }
+///
+/// std::env::set_var("FIGMENT_PORT", "8000");
+/// std::env::set_var("FIGMENT_DEBUG", "on");
+/// std::env::set_var("FIGMENT_PEERS", r#"["a:80", "b:80"]"#);
+///
+/// let config: Config = Figment::from(biodome::Biodome::prefixed("FIGMENT_"))
+///     .extract_lossy()
+///     .unwrap();
+/// assert_eq!(config.port, 8000);
+/// assert!(config.debug);
+/// assert_eq!(config.peers, ["a:80", "b:80"]);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Biodome {
+    prefix: String,
+}
+
+impl Biodome {
+    /// Provide all env vars.
+    pub fn new() -> Self {
+        Biodome::default()
+    }
+
+    /// Provide the env vars that start with "prefix".
+    pub fn prefixed(prefix: &str) -> Self {
+        Biodome {
+            prefix: prefix.to_string(),
+        }
+    }
+}
+
+impl Provider for Biodome {
+    fn metadata(&self) -> Metadata {
+        let prefix = self.prefix.clone();
+        Metadata::named("biodome env var")
+            .interpolater(move |_, keys| format!("{}{}", prefix, keys.join(".").to_uppercase()))
+    }
+
+    fn data(&self) -> Result<Map<Profile, Dict>, Error> {
+        let values =
+            source::prefixed_values(&self.prefix).map_err(|err| Error::from(err.to_string()))?;
+        let dict = values
+            .into_iter()
+            .map(|(key, raw)| (key, from_toml(rawconv::to_untyped(&raw))))
+            .collect();
+        Ok(Profile::Default.collect(dict))
+    }
+}
+
+fn from_toml(value: toml::Value) -> Value {
+    match value {
+        toml::Value::String(s) => Value::from(s),
+        toml::Value::Integer(i) => Value::from(i),
+        toml::Value::Float(f) => Value::from(f),
+        toml::Value::Boolean(b) => Value::from(b),
+        toml::Value::Datetime(d) => Value::from(d.to_string()),
+        toml::Value::Array(items) => {
+            Value::from(items.into_iter().map(from_toml).collect::<Vec<_>>())
+        }
+        toml::Value::Table(table) => {
+            let dict: Dict = table.into_iter().map(|(k, v)| (k, from_toml(v))).collect();
+            Value::from(dict)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::figment::Figment;
+    use serde::Deserialize;
+    use std::collections::HashMap;
+
+    #[derive(Debug, Deserialize)]
+    struct Config {
+        name: String,
+        limits: HashMap<String, u32>,
+        #[serde(default)]
+        verbose: bool,
+    }
+
+    #[test]
+    fn provider() {
+        std::env::set_var("FIGMENT_T_NAME", "api");
+        std::env::set_var("FIGMENT_T_LIMITS", "{ rate = 10 }");
+        let figment = Figment::new()
+            .merge(("verbose", true))
+            .merge(Biodome::prefixed("FIGMENT_T_"));
+        let config: Config = figment.extract().unwrap();
+        assert_eq!(config.name, "api");
+        assert_eq!(config.limits["rate"], 10);
+        assert!(config.verbose);
+
+        std::env::set_var("FIGMENT_T_LIMITS", "{ rate = -1 }");
+        let figment = Figment::from(Biodome::prefixed("FIGMENT_T_"));
+        let err = figment.extract::<Config>().unwrap_err();
+        assert!(err.to_string().contains("FIGMENT_T_LIMITS"), "{}", err);
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct Secrets {
+        compression: String,
+        db_password: String,
+    }
+
+    #[test]
+    fn words_and_files() {
+        let path = std::env::temp_dir().join(format!("biodome-figment-{}", std::process::id()));
+        std::fs::write(&path, "s3cr3t\n").unwrap();
+        std::env::set_var("FIGMENT_F_COMPRESSION", "off");
+        std::env::set_var("FIGMENT_F_DB_PASSWORD_FILE", &path);
+        let secrets: Secrets = Figment::from(Biodome::prefixed("FIGMENT_F_"))
+            .extract()
+            .unwrap();
+        assert_eq!(secrets.compression, "off");
+        assert_eq!(secrets.db_password, "s3cr3t");
+        std::fs::remove_file(path).unwrap();
+    }
+}
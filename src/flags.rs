@@ -0,0 +1,330 @@
+use std::collections::BTreeMap;
+
+use toml::Value;
+
+use crate::digest::bucket;
+use crate::tomlconv::{self, FromTomlValue};
+use crate::{rawconv, TryFromEnv};
+
+/// What a [`Flag`] is evaluated against. Implement it for the struct
+/// that describes the current request or user.
+pub trait FlagContext {
+    /// The id of the user, if there is one.
+    fn user_id(&self) -> Option<&str>;
+}
+
+/// A bare user id.
+impl FlagContext for str {
+    fn user_id(&self) -> Option<&str> {
+        Some(self)
+    }
+}
+
+/// A feature flag: either a plain boolean, or a small table of
+/// targeting rules.
+///
+/// ```rust
+/// use biodome::{biodome, Flag};
+///
+/// std::env::set_var(
+///     "NEW_CHECKOUT",
+///     r#"{ default = false, allow_users = ["42", "77"], allow_percent = 10 }"#,
+/// );
+/// let NEW_CHECKOUT = biodome("NEW_CHECKOUT", Flag::new(false));
+/// assert!(NEW_CHECKOUT.enabled_for("42"));
+/// ```
+///
+/// The rules are checked in order: users in `allow_users` get the
+/// flag; then a stable `allow_percent` share of the other users do;
+/// everyone else gets `default`. The share is chosen by hashing the
+/// user id with `salt`, so give each flag its own salt if they should
+/// not all reach the same users. A plain value like `true` or `off`
+/// is read the same way as a `bool`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Flag {
+    pub default: bool,
+    pub allow_users: Vec<String>,
+    /// From 0 to 100.
+    pub allow_percent: u8,
+    pub salt: String,
+}
+
+impl Flag {
+    /// A flag without rules, that is always "default".
+    pub fn new(default: bool) -> Self {
+        Flag {
+            default,
+            ..Flag::default()
+        }
+    }
+
+    /// Whether the flag is on for "context".
+    pub fn enabled_for<C: FlagContext + ?Sized>(&self, context: &C) -> bool {
+        let Some(user) = context.user_id() else {
+            return self.default;
+        };
+        if self.allow_users.iter().any(|u| u == user) {
+            return true;
+        }
+        if bucket(&self.salt, user, 100) < self.allow_percent as u64 {
+            return true;
+        }
+        self.default
+    }
+}
+
+impl FromTomlValue for Flag {
+    fn from_toml(value: &Value) -> Result<Self, &'static str> {
+        let table = match value {
+            Value::Boolean(b) => return Ok(Flag::new(*b)),
+            Value::Table(table) => table,
+            _ => return Err("expected a boolean or a table of rules"),
+        };
+        let mut flag = Flag::default();
+        for (name, value) in table {
+            match name.as_str() {
+                "default" => flag.default = bool::from_toml(value)?,
+                "allow_users" => flag.allow_users = Vec::from_toml(value)?,
+                "allow_percent" => {
+                    flag.allow_percent = u8::from_toml(value)?;
+                    if flag.allow_percent > 100 {
+                        return Err("allow_percent must be at most 100");
+                    }
+                }
+                "salt" => flag.salt = String::from_toml(value)?,
+                _ => return Err("unknown flag rule"),
+            }
+        }
+        Ok(flag)
+    }
+
+    fn to_toml(&self) -> Option<Value> {
+        if *self == Flag::new(self.default) {
+            return Some(Value::Boolean(self.default));
+        }
+        let mut table = toml::value::Table::new();
+        table.insert("default".into(), Value::Boolean(self.default));
+        if !self.allow_users.is_empty() {
+            table.insert("allow_users".into(), self.allow_users.to_toml()?);
+        }
+        if self.allow_percent > 0 {
+            table.insert("allow_percent".into(), self.allow_percent.to_toml()?);
+        }
+        if !self.salt.is_empty() {
+            table.insert("salt".into(), Value::String(self.salt.clone()));
+        }
+        Some(Value::Table(table))
+    }
+}
+
+impl TryFromEnv<Self> for Flag {
+    type Error = &'static str;
+
+    fn try_from_env(value: &str) -> Result<Self, Self::Error> {
+        if value.trim_start().starts_with('{') {
+            rawconv::to_value(value)
+        } else {
+            Ok(Flag::new(rawconv::to_bool(value)))
+        }
+    }
+
+    fn to_env(value: &Self) -> Option<String> {
+        value.to_toml().map(|v| tomlconv::to_inline(&v))
+    }
+}
+
+/// An A/B experiment: named variants with weights, parsed from a TOML
+/// inline table such as `{ control = 90, new_ranking = 10 }`.
+///
+/// [`assign`](Experiment::assign) puts each subject in a variant by
+/// hashing its id, so a subject stays in the same variant in every
+/// process for as long as the variants and weights don't change. A
+/// plain variant name, like `control`, puts every subject in that
+/// variant.
+///
+/// ```rust
+/// use biodome::{biodome, Experiment};
+///
+/// std::env::set_var("RANKING_EXPERIMENT", "{ control = 90, new_ranking = 10 }");
+/// let RANKING = biodome("RANKING_EXPERIMENT", Experiment::new(&[("control", 1)]));
+/// let variant = RANKING.assign("user-42").unwrap();
+/// assert!(variant == "control" || variant == "new_ranking");
+/// assert_eq!(RANKING.assign("user-42"), Some(variant));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Experiment {
+    /// Variant names and their weights, in order.
+    pub variants: Vec<(String, u32)>,
+}
+
+impl Experiment {
+    pub fn new(variants: &[(&str, u32)]) -> Self {
+        Experiment {
+            variants: variants
+                .iter()
+                .map(|&(name, weight)| (name.to_string(), weight))
+                .collect(),
+        }
+    }
+
+    /// The variant for "subject_id", or `None` if no variant has any
+    /// weight.
+    pub fn assign(&self, subject_id: &str) -> Option<&str> {
+        let total: u64 = self.variants.iter().map(|(_, w)| *w as u64).sum();
+        if total == 0 {
+            return None;
+        }
+        // Salting with the variant names keeps experiments from all
+        // splitting subjects the same way.
+        let names: Vec<&str> = self.variants.iter().map(|(n, _)| n.as_str()).collect();
+        let mut point = bucket(&names.join(","), subject_id, total);
+        for (name, weight) in &self.variants {
+            if point < *weight as u64 {
+                return Some(name);
+            }
+            point -= *weight as u64;
+        }
+        None
+    }
+}
+
+impl FromTomlValue for Experiment {
+    fn from_toml(value: &Value) -> Result<Self, &'static str> {
+        match value {
+            Value::String(name) => Ok(Experiment::new(&[(name, 1)])),
+            Value::Table(_) => {
+                let variants: BTreeMap<String, u32> = tomlconv::from_table(value)?;
+                Ok(Experiment {
+                    variants: variants.into_iter().collect(),
+                })
+            }
+            _ => Err("expected a table of variant weights"),
+        }
+    }
+
+    fn to_toml(&self) -> Option<Value> {
+        let table = self
+            .variants
+            .iter()
+            .map(|(name, weight)| Some((name.clone(), weight.to_toml()?)))
+            .collect::<Option<toml::value::Table>>()?;
+        Some(Value::Table(table))
+    }
+}
+
+impl TryFromEnv<Self> for Experiment {
+    type Error = &'static str;
+
+    fn try_from_env(value: &str) -> Result<Self, Self::Error> {
+        let value = value.trim();
+        if value.starts_with('{') {
+            rawconv::to_value(value)
+        } else if value.is_empty() {
+            Err("expected a table of variant weights")
+        } else {
+            Ok(Experiment::new(&[(value, 1)]))
+        }
+    }
+
+    fn to_env(value: &Self) -> Option<String> {
+        value.to_toml().map(|v| tomlconv::to_inline(&v))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Request {
+        user: Option<String>,
+    }
+
+    impl FlagContext for Request {
+        fn user_id(&self) -> Option<&str> {
+            self.user.as_deref()
+        }
+    }
+
+    #[test]
+    fn rules() {
+        let flag = Flag::try_from_env(
+            r#"{ default = false, allow_users = ["42", "77"], allow_percent = 10 }"#,
+        )
+        .unwrap();
+        assert!(flag.enabled_for("42"));
+        assert!(!flag.enabled_for(&Request { user: None }));
+        let enabled = (0..1000)
+            .filter(|i| flag.enabled_for(i.to_string().as_str()))
+            .count();
+        assert!((70..=130).contains(&enabled), "{}", enabled);
+        assert_eq!(
+            flag.enabled_for("1234"),
+            flag.enabled_for(&Request {
+                user: Some("1234".to_string())
+            })
+        );
+    }
+
+    #[test]
+    fn plain() {
+        assert_eq!(Flag::try_from_env("on"), Ok(Flag::new(true)));
+        assert_eq!(Flag::try_from_env("0"), Ok(Flag::new(false)));
+        assert_eq!(Flag::to_env(&Flag::new(true)), Some("true".to_string()));
+        assert!(Flag::new(true).enabled_for("anyone"));
+    }
+
+    #[test]
+    fn invalid() {
+        assert_eq!(
+            Flag::try_from_env("{ allow_user = [\"1\"] }"),
+            Err("unknown flag rule")
+        );
+        assert_eq!(
+            Flag::try_from_env("{ allow_percent = 300 }"),
+            Err("integer out of range")
+        );
+        assert_eq!(
+            Flag::try_from_env("{ allow_percent = 101 }"),
+            Err("allow_percent must be at most 100")
+        );
+    }
+
+    #[test]
+    fn experiments() {
+        let experiment = Experiment::try_from_env("{ control = 3, treatment = 1 }").unwrap();
+        assert_eq!(
+            experiment,
+            Experiment::new(&[("control", 3), ("treatment", 1)])
+        );
+        let treated = (0..4000)
+            .filter(|i| experiment.assign(&i.to_string()) == Some("treatment"))
+            .count();
+        assert!((800..=1200).contains(&treated), "{}", treated);
+        assert_eq!(experiment.assign("7"), experiment.clone().assign("7"));
+
+        let forced = Experiment::try_from_env("treatment").unwrap();
+        assert_eq!(forced.assign("7"), Some("treatment"));
+        assert_eq!(Experiment::new(&[("a", 0)]).assign("7"), None);
+        assert!(Experiment::try_from_env("{ a = -1 }").is_err());
+        assert_eq!(
+            Experiment::to_env(&experiment),
+            Some("{ control = 3, treatment = 1 }".to_string())
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        let flag = Flag {
+            default: true,
+            allow_users: vec!["7".to_string()],
+            allow_percent: 5,
+            salt: "checkout".to_string(),
+        };
+        let env = Flag::to_env(&flag).unwrap();
+        assert_eq!(
+            env,
+            r#"{ allow_percent = 5, allow_users = ["7"], default = true, salt = "checkout" }"#
+        );
+        assert_eq!(Flag::try_from_env(&env), Ok(flag));
+    }
+}
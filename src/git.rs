@@ -0,0 +1,106 @@
+use std::process::Command;
+
+use crate::{ci, source};
+
+/// The commit and branch a binary was built from, for version
+/// metadata.
+///
+/// Deployment platforms and build scripts pass these in env vars
+/// with a few different names: `GIT_SHA` or `SOURCE_VERSION` (Heroku)
+/// for the commit, and `GIT_BRANCH` for the branch. In CI builds,
+/// the CI system's own variables are used as well, and in debug
+/// builds `git` itself is asked, so that local runs have the same
+/// metadata without any setup.
+///
+/// ```rust
+/// use biodome::GitInfo;
+///
+/// std::env::set_var("SOURCE_VERSION", "8f2c1e0d");
+/// std::env::set_var("GIT_BRANCH", "main");
+/// let git = GitInfo::resolve();
+/// assert_eq!(git.sha.as_deref(), Some("8f2c1e0d"));
+/// assert_eq!(git.short_sha(), Some("8f2c1e0"));
+/// assert_eq!(git.branch.as_deref(), Some("main"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct GitInfo {
+    pub sha: Option<String>,
+    pub branch: Option<String>,
+}
+
+impl GitInfo {
+    /// Read the commit and branch from the env vars, and the CI system
+    /// if there is one, falling back to `git` in debug builds.
+    pub fn resolve() -> Self {
+        let mut info = GitInfo::from_env();
+        if let Some(ci) = ci::detect() {
+            info.sha = info.sha.or(ci.commit);
+            info.branch = info.branch.or(ci.branch);
+        }
+        if cfg!(debug_assertions) {
+            if info.sha.is_none() {
+                info.sha = git(&["rev-parse", "HEAD"]);
+            }
+            if info.branch.is_none() {
+                // This is `HEAD` for a detached checkout.
+                info.branch = git(&["rev-parse", "--abbrev-ref", "HEAD"]).filter(|b| b != "HEAD");
+            }
+        }
+        info
+    }
+
+    /// Read the commit and branch from the env vars only. They are
+    /// build metadata, not settings, so they are not recorded in the
+    /// [registry](crate::registry).
+    pub fn from_env() -> Self {
+        GitInfo {
+            sha: var("GIT_SHA").or_else(|| var("SOURCE_VERSION")),
+            branch: var("GIT_BRANCH"),
+        }
+    }
+
+    /// The first seven characters of the commit.
+    pub fn short_sha(&self) -> Option<&str> {
+        let sha = self.sha.as_deref()?;
+        Some(sha.get(..7).unwrap_or(sha))
+    }
+}
+
+/// The value of the env var "key", if it is set and not empty.
+fn var(key: &str) -> Option<String> {
+    let value = source::try_value(key).ok().flatten()?;
+    Some(value).filter(|v| !v.is_empty())
+}
+
+/// The output of `git` with "args", if it succeeds.
+fn git(args: &[&str]) -> Option<String> {
+    let output = Command::new("git").args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let out = String::from_utf8(output.stdout).ok()?;
+    Some(out.trim().to_string()).filter(|s| !s.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn short_sha() {
+        let info = GitInfo {
+            sha: Some("abc".to_string()),
+            branch: None,
+        };
+        assert_eq!(info.short_sha(), Some("abc"));
+        assert_eq!(GitInfo::default().short_sha(), None);
+        assert_eq!(git(&["not-a-git-command"]), None);
+    }
+
+    #[test]
+    fn unrecorded() {
+        let _ = GitInfo::from_env();
+        let keys: Vec<String> = crate::registry().into_iter().map(|e| e.key).collect();
+        assert!(!keys.iter().any(|k| k == "GIT_SHA" || k == "GIT_BRANCH"));
+    }
+}
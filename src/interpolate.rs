@@ -0,0 +1,221 @@
+/// Expand `$VAR` and `${VAR}` references in "s" using "lookup". On
+/// Windows, `%VAR%` references are expanded too. References to
+/// variables that "lookup" can't resolve are left untouched so that
+/// they are easy to spot.
+pub fn expand_vars(s: &str, lookup: impl Fn(&str) -> Option<String>) -> String {
+    expand(s, lookup, cfg!(windows))
+}
+
+fn expand(s: &str, lookup: impl Fn(&str) -> Option<String>, percent: bool) -> String {
+    let is_marker = |c: char| c == '$' || (percent && c == '%');
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(pos) = rest.find(is_marker) {
+        out.push_str(&rest[..pos]);
+        let marker = &rest[pos..pos + 1];
+        let after = &rest[pos + 1..];
+        let (name, len) = if marker == "%" {
+            match after.find(['%', '=', '\n']) {
+                Some(end) if after[end..].starts_with('%') => (&after[..end], end + 1),
+                _ => ("", 0),
+            }
+        } else if let Some(braced) = after.strip_prefix('{') {
+            match braced.find('}') {
+                Some(end) => (&braced[..end], end + 2),
+                None => ("", 0),
+            }
+        } else {
+            let end = after
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(after.len());
+            (&after[..end], end)
+        };
+        match lookup(name) {
+            Some(value) if !name.is_empty() => {
+                out.push_str(&value);
+                rest = &after[len..];
+            }
+            _ => {
+                out.push_str(marker);
+                rest = after;
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Expand `${VAR}` and `${VAR:-default}` references in the value of
+/// the env var "key", which is "s". Values of referenced variables
+/// are expanded in turn, and a reference back to a variable that is
+/// being expanded is an error. `$${` is a literal `${`. References to
+/// variables that "lookup" can't resolve, and that have no default,
+/// are left untouched, as they are for [`expand_vars`].
+pub fn interpolate(
+    key: &str,
+    s: &str,
+    lookup: &dyn Fn(&str) -> Option<String>,
+) -> Result<String, String> {
+    interpolate_in(s, lookup, &mut vec![key.to_string()])
+}
+
+fn interpolate_in(
+    s: &str,
+    lookup: &dyn Fn(&str) -> Option<String>,
+    stack: &mut Vec<String>,
+) -> Result<String, String> {
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(pos) = rest.find('$') {
+        out.push_str(&rest[..pos]);
+        let after = &rest[pos..];
+        if let Some(escaped) = after.strip_prefix("$${") {
+            out.push_str("${");
+            rest = escaped;
+            continue;
+        }
+        let Some(end) = after.strip_prefix("${").and_then(closing_brace) else {
+            out.push('$');
+            rest = &after[1..];
+            continue;
+        };
+        let reference = &after[2..end + 2];
+        rest = &after[end + 3..];
+        let (name, default) = match reference.split_once(":-") {
+            Some((name, default)) => (name, Some(default)),
+            None => (reference, None),
+        };
+        if stack.iter().any(|k| k == name) {
+            stack.push(name.to_string());
+            return Err(format!("circular reference: {}", stack.join(" -> ")));
+        }
+        match lookup(name) {
+            Some(value) => {
+                stack.push(name.to_string());
+                out.push_str(&interpolate_in(&value, lookup, stack)?);
+                stack.pop();
+            }
+            None => match default {
+                Some(default) => out.push_str(&interpolate_in(default, lookup, stack)?),
+                None => out.push_str(&after[..end + 3]),
+            },
+        }
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// The position of the `}` that closes a `${` whose contents start
+/// "s", allowing for nested references in defaults.
+fn closing_brace(s: &str) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in s.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' if depth == 0 => return Some(i),
+            '}' => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Replace a leading `~` with "home", if there is one.
+pub fn expand_tilde(s: &str, home: Option<String>) -> String {
+    let home = match home {
+        Some(h) => h,
+        None => return s.to_string(),
+    };
+    match s.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') || rest.starts_with('\\') => {
+            format!("{}{}", home, rest)
+        }
+        _ => s.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lookup(name: &str) -> Option<String> {
+        match name {
+            "APP" => Some("demo".to_string()),
+            "HOME" => Some("/home/me".to_string()),
+            "APPDATA" => Some(r"C:\Users\me\AppData\Roaming".to_string()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn vars() {
+        assert_eq!(expand_vars("plain", lookup), "plain");
+        assert_eq!(expand_vars("/data/$APP", lookup), "/data/demo");
+        assert_eq!(expand_vars("/data/${APP}/x", lookup), "/data/demo/x");
+        assert_eq!(expand_vars("$HOME/$APP.log", lookup), "/home/me/demo.log");
+        assert_eq!(expand_vars("/data/$MISSING/x", lookup), "/data/$MISSING/x");
+        assert_eq!(expand_vars("${MISSING}", lookup), "${MISSING}");
+        assert_eq!(expand_vars("cost: 5$", lookup), "cost: 5$");
+        assert_eq!(expand_vars("${APP", lookup), "${APP");
+    }
+
+    #[test]
+    fn interpolation() {
+        let lookup = |name: &str| match name {
+            "DB_HOST" => Some("db".to_string()),
+            "DB_PORT" => Some("5432".to_string()),
+            "DB_URL" => Some("postgres://${DB_HOST}:${DB_PORT}/app".to_string()),
+            "A" => Some("${B}".to_string()),
+            "B" => Some("x${A}".to_string()),
+            _ => None,
+        };
+        let expand = |s| interpolate("KEY", s, &lookup);
+        assert_eq!(
+            expand("${DB_URL}?ssl=${SSL:-off}"),
+            Ok("postgres://db:5432/app?ssl=off".to_string())
+        );
+        assert_eq!(expand("${DB_HOST:-other}"), Ok("db".to_string()));
+        assert_eq!(expand("${X:-${DB_PORT}}"), Ok("5432".to_string()));
+        assert_eq!(
+            expand("${MISSING} $5 $${DB_HOST}"),
+            Ok("${MISSING} $5 ${DB_HOST}".to_string())
+        );
+        assert_eq!(expand("${DB_HOST"), Ok("${DB_HOST".to_string()));
+        assert_eq!(
+            expand("${A}"),
+            Err("circular reference: KEY -> A -> B -> A".to_string())
+        );
+        assert_eq!(
+            interpolate("A", "${B}", &lookup),
+            Err("circular reference: A -> B -> A".to_string())
+        );
+    }
+
+    #[test]
+    fn percent_vars() {
+        let expand = |s| expand(s, lookup, true);
+        assert_eq!(
+            expand(r"%APPDATA%\demo"),
+            r"C:\Users\me\AppData\Roaming\demo"
+        );
+        assert_eq!(
+            expand(r"%APPDATA%\$APP"),
+            r"C:\Users\me\AppData\Roaming\demo"
+        );
+        assert_eq!(expand("%MISSING%/x"), "%MISSING%/x");
+        assert_eq!(expand("50% of %APP%"), "50% of demo");
+        assert_eq!(expand("100%"), "100%");
+        assert_eq!(expand("%%"), "%%");
+        assert_eq!(super::expand("%APP%", lookup, false), "%APP%");
+    }
+
+    #[test]
+    fn tilde() {
+        let home = || Some("/home/me".to_string());
+        assert_eq!(expand_tilde("~", home()), "/home/me");
+        assert_eq!(expand_tilde("~/data", home()), "/home/me/data");
+        assert_eq!(expand_tilde("~other/data", home()), "~other/data");
+        assert_eq!(expand_tilde("/data/~", home()), "/data/~");
+        assert_eq!(expand_tilde("~/data", None), "~/data");
+    }
+}
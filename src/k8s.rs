@@ -0,0 +1,87 @@
+//! Find services inside a Kubernetes cluster.
+//!
+//! Kubernetes injects `{NAME}_SERVICE_HOST` and `{NAME}_SERVICE_PORT`
+//! into pods for the services that existed when they started, with the
+//! name upper-cased and dashes replaced by underscores. Services
+//! created later can only be found through DNS, by their name.
+
+use std::fmt;
+use std::io;
+use std::net::{SocketAddr, ToSocketAddrs};
+
+use crate::biodome;
+
+/// Where a service can be reached.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Service {
+    pub host: String,
+    pub port: u16,
+}
+
+impl Service {
+    /// The address of the service, resolving its host name if needed.
+    pub fn socket_addr(&self) -> io::Result<SocketAddr> {
+        (self.host.as_str(), self.port)
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no addresses for host"))
+    }
+
+    /// A URL for the service, like `redis://10.0.0.11:6379`.
+    pub fn url(&self, scheme: &str) -> String {
+        format!("{}://{}", scheme, self)
+    }
+}
+
+impl fmt::Display for Service {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.host.contains(':') {
+            write!(f, "[{}]:{}", self.host, self.port)
+        } else {
+            write!(f, "{}:{}", self.host, self.port)
+        }
+    }
+}
+
+/// The service "name", from its `{NAME}_SERVICE_HOST` and
+/// `{NAME}_SERVICE_PORT` env vars. Without them, the host is "name"
+/// itself, for cluster DNS to resolve, and the port is "default_port".
+///
+/// ```rust
+/// std::env::set_var("REDIS_CACHE_SERVICE_HOST", "10.0.0.11");
+/// std::env::set_var("REDIS_CACHE_SERVICE_PORT", "6380");
+/// let redis = biodome::k8s::service("redis-cache", 6379);
+/// assert_eq!(redis.url("redis"), "redis://10.0.0.11:6380");
+///
+/// let search = biodome::k8s::service("search", 9200);
+/// assert_eq!(search.to_string(), "search:9200");
+/// ```
+pub fn service(name: &str, default_port: u16) -> Service {
+    let prefix = name.to_uppercase().replace(['-', '.'], "_");
+    Service {
+        host: biodome(&format!("{}_SERVICE_HOST", prefix), name.to_string()),
+        port: biodome(&format!("{}_SERVICE_PORT", prefix), default_port),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn services() {
+        env::set_var("K8S_TEST_API_SERVICE_HOST", "fd00::1");
+        env::set_var("K8S_TEST_API_SERVICE_PORT", "8443");
+        let api = service("k8s-test-api", 443);
+        assert_eq!(api.url("https"), "https://[fd00::1]:8443");
+        assert_eq!(
+            api.socket_addr().unwrap(),
+            "[fd00::1]:8443".parse().unwrap()
+        );
+
+        let local = service("localhost", 80);
+        assert_eq!(local.host, "localhost");
+        assert_eq!(local.socket_addr().unwrap().port(), 80);
+    }
+}
@@ -0,0 +1,92 @@
+use std::fmt;
+use std::ops::Deref;
+use std::sync::OnceLock;
+
+/// A setting that is read the first time it is used, and then kept
+/// for the life of the process. Declare one with [`biodome_lazy!`].
+pub struct Lazy<T> {
+    key: &'static str,
+    init: fn() -> T,
+    cell: OnceLock<T>,
+}
+
+impl<T> Lazy<T> {
+    #[doc(hidden)]
+    pub const fn new(key: &'static str, init: fn() -> T) -> Self {
+        Lazy {
+            key,
+            init,
+            cell: OnceLock::new(),
+        }
+    }
+
+    /// The env var the setting is read from.
+    pub fn key(&self) -> &'static str {
+        self.key
+    }
+}
+
+impl<T> Deref for Lazy<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.cell.get_or_init(self.init)
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Lazy<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+/// Declare process-wide settings that are read from the environment
+/// the first time they are used, so they can be referenced from
+/// anywhere without passing a struct around.
+///
+/// Each is a `static` [`Lazy`] that dereferences to the value, given
+/// as the key and the default that [`biodome`](crate::biodome) takes.
+/// The default has the type of the setting. It panics, on first use,
+/// if the env var is set to a value that doesn't parse.
+///
+/// ```rust
+/// use biodome::biodome_lazy;
+///
+/// biodome_lazy! {
+///     static TIMEOUT: u64 = ("LAZY_TIMEOUT", 10);
+///     /// Where the database is.
+///     pub static DB_HOST: String = ("LAZY_DB_HOST", "localhost".to_string());
+/// }
+///
+/// std::env::set_var("LAZY_TIMEOUT", "30");
+/// assert_eq!(*TIMEOUT, 30);
+/// assert_eq!(DB_HOST.as_str(), "localhost");
+/// ```
+#[macro_export]
+macro_rules! biodome_lazy {
+    ($($(#[$attr:meta])* $vis:vis static $name:ident: $t:ty = ($key:expr, $default:expr));+ $(;)?) => {
+        $(
+            $(#[$attr])*
+            $vis static $name: $crate::Lazy<$t> =
+                $crate::Lazy::new($key, || $crate::biodome::<$t, $t>($key, $default));
+        )+
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    crate::biodome_lazy!(static WORKERS: u8 = ("LAZY_WORKERS", 4));
+
+    #[test]
+    fn lazy() {
+        env::set_var("LAZY_WORKERS", "8");
+        assert_eq!(WORKERS.key(), "LAZY_WORKERS");
+        assert_eq!(*WORKERS, 8);
+        env::set_var("LAZY_WORKERS", "16");
+        assert_eq!(*WORKERS, 8);
+        assert_eq!(format!("{:?}", WORKERS), "8");
+        env::remove_var("LAZY_WORKERS");
+    }
+}
@@ -38,7 +38,8 @@
 //!   type`PORTS` will always be a `Vec<i32>`.
 //! - *biodome* will parse the env var, if set, to make that
 //!   happen. An env var like this (bash) would
-//!   work: `export PORTS=[81, 82]`
+//!   work: `export PORTS=[81, 82]`, and so would a plain
+//!   comma-separated list, `export PORTS=81,82`.
 //!
 //! # A Callable
 //!
@@ -68,6 +69,30 @@
 //! have to provide the type of the callable since Rust
 //! does not do inference for const values.
 //!
+//! The closure panics if the env var is changed to a value that
+//! doesn't parse. `biodome_callable_try` returns a closure that gives
+//! a `Result` instead, so a bad update can't crash the process.
+//!
+//! Each call reads and parses the env var again. In a hot loop, use
+//! `biodome_cached` instead, which does that at most once per TTL:
+//!
+//! ```rust
+//! use std::time::Duration;
+//!
+//! let TIMEOUT = biodome::biodome_cached("TIMEOUT", 5.0, Duration::from_secs(30));
+//! ```
+//!
+//! For settings that are read once and used everywhere,
+//! `biodome_lazy!` declares statics that are read on first use:
+//!
+//! ```rust
+//! biodome::biodome_lazy!(static TIMEOUT: u64 = ("TIMEOUT", 10));
+//!
+//! fn get_data() -> u64 {
+//!     *TIMEOUT
+//! }
+//! ```
+//!
 //! # Simple Types
 //!
 //! In the above example, the literal integer `10` is of type
@@ -109,6 +134,30 @@
 //! of these values are (case-insensitively) `true`, `t`, `yes`, `y`,
 //! `on`, `active`, `enabled`, `1`, `ok` and so on.
 //!
+//! Some settings, like API keys, have no sensible default. Read them
+//! with `biodome_required`, which fails if the env var is not set:
+//!
+//! ```rust
+//! use biodome::biodome_required;
+//!
+//! std::env::set_var("API_KEY", "s3cr3t");
+//! let API_KEY: String = biodome_required("API_KEY").unwrap();
+//!
+//! let err = biodome_required::<String>("MISSING_KEY").unwrap_err();
+//! assert_eq!(err.to_string(), "Missing required env var MISSING_KEY");
+//! ```
+//!
+//! Values that parse but make no sense, like a port of 0, can be
+//! checked with `biodome_validated`:
+//!
+//! ```rust
+//! use biodome::biodome_validated;
+//!
+//! std::env::set_var("HTTP_PORT", "0");
+//! let HTTP_PORT = biodome_validated("HTTP_PORT", 8080u32, |v: &u32| *v > 0 && *v < 65536);
+//! assert!(HTTP_PORT.is_err());
+//! ```
+//!
 //! # Structured Types
 //!
 //! If all *biodome* did was cast primitive types, it would be
@@ -164,6 +213,170 @@
 //! `TIMEOUTS` will be a `HashMap<String, f64>`, and `PROXIES` will be
 //! a `Vec<String>`.
 //!
+//! Structured types can be nested. For example, with
+//! `export ROUTES='{ api = ["10.0.0.1", "10.0.0.2"], web = ["10.0.1.1"] }'`,
+//! `biodome("ROUTES", HashMap::<String, Vec<String>>::new())` gives a
+//! map from each name to its list of addresses. To use your own types
+//! inside collections, implement `FromTomlValue` for them, or wrap them
+//! in `FromStrValue` to read them with their `FromStr` impl, as
+//! versions before 0.3 did for every item. Arrays of
+//! tables, like `[{ name = "a", weight = 1 }, { name = "b", weight = 2 }]`,
+//! can be read as a `Vec<HashMap<String, toml::Value>>` when the entries
+//! mix types.
+//!
+//! Small tuples are read from TOML arrays with one element per field, so
+//! `export ORIGIN='["host.example", 443]'` can be read as a `(String, u16)`.
+//!
+//! # Profiles
+//!
+//! On platforms where one set of env vars is shared by every
+//! environment, a value can have a different setting per profile:
+//! with `export TIMEOUT='10;staging:20;prod:30'`,
+//! `biodome("TIMEOUT", 5)` is 30 when the active profile is `prod`,
+//! and 10 for any other profile. The profile is chosen with
+//! `set_profile()`, or the `BIODOME_PROFILE` env var, and the other
+//! profiles are made known with `set_profiles(&["staging", "prod"])`.
+//! Values are only split up for known profiles, so one like
+//! `host1:26379;host2:26379` is read as it is.
+//!
+//! A profile can also limit where settings come from: after
+//! `allow_sources("prod", &["env", "vault"])`, installing a `.env` file,
+//! a map of overrides or any other kind of source while `prod` is
+//! active is refused, so local-dev conveniences can't leak into
+//! production.
+//!
+//! # Secrets in Files
+//!
+//! As with Docker and Kubernetes secrets, when `DB_PASSWORD` is not set
+//! but `DB_PASSWORD_FILE` is, the value is read from the file it names,
+//! without surrounding whitespace.
+//!
+//! # Interpolation
+//!
+//! `${VAR}` references in values are expanded before parsing, so with
+//! `export DATABASE_URL='postgres://${DB_HOST}:${DB_PORT:-5432}/app'`,
+//! the host and port come from their own env vars, with `5432` used when
+//! `DB_PORT` is not set. Referenced values are expanded too, and circular
+//! references are an error. Write `$${` for a literal `${`.
+//!
+//! # Testing
+//!
+//! `biodome::testing::jail` gives a test its own environment, in place of
+//! the process environment, and a temporary directory for `.env` files
+//! and config dirs. Everything is cleaned up afterwards, and a jail only
+//! applies to the thread that runs it, so tests that run in parallel
+//! don't see each other's settings, whether they use jails or not:
+//!
+//! ```rust
+//! use biodome::{biodome, testing::jail};
+//!
+//! jail(|env| {
+//!     env.set("WORKERS", "8");
+//!     env.dotenv(".env", "TIMEOUT=5\n").unwrap();
+//!     assert_eq!(biodome("WORKERS", 4), 8);
+//!     assert_eq!(biodome("TIMEOUT", 30), 5);
+//! });
+//! ```
+//!
+//! When the code under test reads the process environment itself, or
+//! starts child processes, `biodome::testing::with_vars(&[("WORKERS", "8")], || ...)`
+//! sets the env vars for the duration of the closure instead, and puts
+//! back their old values afterwards.
+//!
+//! For tests that only need a few values, `biodome::testing::override_var`
+//! sets an env var for the current thread alone, without touching the
+//! process environment, so such tests don't even need to take turns.
+//!
+//! # Debugging
+//!
+//! To see how each env var is resolved, set `BIODOME_DEBUG=1`. Every
+//! step is then logged to stderr: whether the variable was set, which
+//! file or layer it came from, the value used (sensitive values are
+//! redacted) and any parse error. The variable is read once, on the
+//! first lookup; `biodome::set_debug` turns logging on or off later:
+//!
+//! ```text
+//! biodome: PORT: found in layer 2 of 3
+//! biodome: PORT: failed to parse: "parse error"
+//! ```
+//!
+//! # Optional Features
+//!
+//! Support for types from other crates is available behind
+//! cargo features:
+//!
+//! - `chrono`: `DateTime<Utc>` (RFC 3339), `NaiveDate` and `NaiveTime`.
+//! - `time`: `OffsetDateTime` (RFC 3339) and `Date`.
+//! - `log`: `LevelFilter` and `Level`, e.g. `LOG_LEVEL=debug`.
+//! - `tracing`: `Level`, `LevelFilter`, and `Directives` for filter strings
+//!   like `RUST_LOG=info,my_app=debug`.
+//! - `uuid`: `Uuid`.
+//! - `zeroize`: wipe the memory of a `Secret` when it is dropped.
+//! - `trace-reads`: emit a `tracing` event for every env var read, with
+//!   the key, whether the env var or the default was used, and any
+//!   error.
+//! - `sighup`: `reload::reload_on_sighup`, which reloads settings when
+//!   the process receives SIGHUP, on Unix.
+//! - `watch`: `reload::reload_on_change`, which reloads settings when
+//!   the `.env` files or config dirs they are read from change.
+//! - `serde`: `de::from_env`, which reads any struct that implements
+//!   serde's `Deserialize`, as envy does, with the same parsing of
+//!   values as `biodome()`.
+//! - `figment`: `Biodome`, a figment `Provider` of env vars, for Rocket
+//!   and other apps that build their config with figment.
+//! - `config`: `BiodomeSource`, a config-rs `Source` of env vars, read
+//!   the same way.
+//! - `clap`: `clap::ArgExt::biodome_default`, which makes an env var the
+//!   default of a command line argument, and `clap::parse`, which reads
+//!   arguments of any type that `biodome()` can read.
+//! - `json`: structured values can also be written in JSON, like
+//!   `{"root": "warn"}`, which is easier to template than TOML in many
+//!   orchestration systems.
+//! - `yaml`: structured values can also be written in YAML flow syntax,
+//!   like `{root: warn, http: info}` or `[a, b]`.
+//! - `ron`: structured values can also be written in RON, like
+//!   `(host: "db", port: 5432)` for a struct or `("db", 5432)` for a
+//!   tuple. The names of structs and enum variants are not kept.
+//!
+//! The `derive` feature adds `#[derive(FromEnvEnum)]`, which reads a
+//! fieldless enum by variant name, ignoring case:
+//!
+//! ```rust,ignore
+//! #[derive(Debug, PartialEq, biodome::FromEnvEnum)]
+//! enum Mode { Dev, Staging, Prod }
+//!
+//! std::env::set_var("MODE", "staging");
+//! assert_eq!(biodome("MODE", Mode::Dev), Mode::Staging);
+//! ```
+//!
+//! It also adds `#[derive(Settings)]`, which reads a struct field by
+//! field, from env vars named after the fields. Doc comments on the
+//! fields are used by `docs::markdown()`, which writes a table of every
+//! env var the app reads, and by the generated `json_schema()`, which
+//! describes the struct's env vars for deployment tooling:
+//!
+//! ```rust,ignore
+//! #[derive(biodome::Settings)]
+//! struct Server {
+//!     /// Port the HTTP server listens on.
+//!     #[biodome(default = 8080)]
+//!     port: u16,
+//! }
+//!
+//! let server: Server = biodome::load_settings("APP_").unwrap();
+//! println!("{}", biodome::docs::markdown());
+//! ```
+//!
+//! A field marked `#[biodome(flatten)]` embeds another struct of
+//! settings, such as a `TlsConfig` shared between services, and
+//! `#[biodome(flatten, prefix = "TLS_")]` puts its keys under a prefix
+//! of their own.
+//!
+//! Deriving `Settings` for an enum, with `#[biodome(tag = "BACKEND")]`,
+//! lets one env var choose between configurations: `STORAGE_BACKEND=s3`
+//! loads and validates `STORAGE_S3_*` for an `S3(S3Config)` variant, and
+//! nothing else.
+//!
 //! # Alternative Projects
 //!
 //! [envy](https://github.com/softprops/envy) uses the power of
@@ -177,16 +390,140 @@
 //! Please follow its instructions on how to set it up. The README
 //! file can be regenerated with `cargo readme > README.md`.
 
+mod banner;
+mod bytesize;
+mod child;
+pub mod ci;
+#[cfg(feature = "clap")]
+pub mod clap;
+#[cfg(feature = "config")]
+mod config;
+pub mod contract;
+mod datetime;
+#[cfg(feature = "serde")]
+pub mod de;
+mod debug;
+mod digest;
+#[cfg(feature = "tracing")]
+mod directives;
+pub mod docs;
+pub mod dotenv;
+mod durations;
+mod error;
+pub mod export;
+#[cfg(feature = "figment")]
+mod figment;
+mod flags;
+mod git;
+mod interpolate;
+pub mod k8s;
+mod lazy;
+mod limits;
+mod lint;
+mod numfmt;
+mod observer;
+pub mod paas;
+mod path;
+mod policy;
+mod profile;
 mod rawconv;
+mod registry;
+pub mod reload;
+#[cfg(feature = "derive")]
+pub mod schema;
+mod secret;
+mod sets;
+mod settings;
+pub mod snapshot;
+mod source;
+pub mod testing;
+// The earlier name of `testing`, kept so that existing tests build.
+#[doc(hidden)]
+pub use testing as test;
+mod tomlconv;
+mod validator;
+pub mod watch;
+
+pub use banner::banner;
+#[cfg(feature = "derive")]
+pub use biodome_derive::{FromEnvEnum, Settings};
+pub use bytesize::ByteSize;
+pub use child::ChildEnv;
+#[cfg(feature = "config")]
+pub use config::BiodomeSource;
+pub use debug::{reset_debug, set_debug};
+pub use digest::config_digest;
+#[cfg(feature = "tracing")]
+pub use directives::{Directive, Directives};
+pub use durations::DurationMap;
+pub use error::{reset_renderer, set_renderer, BiodomeError, DefaultRenderer, ErrorRenderer};
+#[cfg(feature = "figment")]
+pub use figment::Biodome;
+pub use flags::{Experiment, Flag, FlagContext};
+pub use git::GitInfo;
+pub use lazy::Lazy;
+pub use limits::{limits, set_limits, Limits};
+pub use lint::{doctor, lints, Lint};
+pub use numfmt::{number_format, set_number_format, NumberFormat};
+pub use observer::{reset_observer, set_observer, BiodomeObserver};
+pub use path::ExpandedPath;
+pub use policy::{allow_sources, reset_source_policy};
+pub use profile::{profile, reset_profile, set_profile, set_profiles};
+#[cfg(feature = "derive")]
+#[doc(hidden)]
+pub use rawconv::suggest;
+pub use registry::{crash_context, describe, mark_sensitive, registry, RegistryEntry};
+pub use secret::{Secret, Wipe};
+#[cfg(feature = "derive")]
+#[doc(hidden)]
+pub use serde_json;
+pub use sets::{set_duplicates, Duplicates};
+pub use settings::{load_settings, validate_all, Scope, Settings};
+pub use source::{
+    any_set, biodome_raw, is_set, refresh_source, reset_source, set_empty_values, set_source,
+    ConfigDir, EmptyValues, EnvSnapshot, EnvSource, Layers, RawValue, StdEnv,
+};
+pub use toml;
+pub use toml::value::Datetime;
+pub use tomlconv::{FromStrValue, FromTomlValue};
+pub use validator::Validator;
 
-use std::collections::HashMap;
-use std::env;
-use std::fmt::Debug;
-use std::str::FromStr;
+use std::collections::{BTreeMap, HashMap};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::num::{
+    NonZeroI128, NonZeroI16, NonZeroI32, NonZeroI64, NonZeroI8, NonZeroIsize, NonZeroU128,
+    NonZeroU16, NonZeroU32, NonZeroU64, NonZeroU8, NonZeroUsize,
+};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 
 pub trait TryFromEnv<T>: Sized {
     type Error;
+
+    /// Whether values of this type must be kept out of logs and
+    /// reports.
+    const SENSITIVE: bool = false;
+
+    /// Whether values of this type are free text, that any string is
+    /// valid for. [`doctor`] checks such values for ones that look like
+    /// they were meant as another type.
+    const TEXT: bool = false;
+
     fn try_from_env(value: &str) -> Result<T, Self::Error>;
+
+    /// Describe a likely problem with a value read from the environment,
+    /// if there is one. Whatever is returned here shows up in [`lints()`].
+    fn lint(_value: &T) -> Option<String> {
+        None
+    }
+
+    /// Write a value back out in the form that `try_from_env` reads,
+    /// if that is possible. This is how defaults show up in the
+    /// registry and in generated files.
+    fn to_env(_value: &T) -> Option<String> {
+        None
+    }
 }
 
 pub trait TryIntoEnv<T>: Sized {
@@ -194,20 +531,83 @@ pub trait TryIntoEnv<T>: Sized {
     fn try_into_env(&self) -> Result<T, Self::Error>;
 }
 
+/// Implement `TryIntoEnv<String>` for types whose `Display` output
+/// reads back with `TryFromEnv`.
+macro_rules! impl_try_into_env_display {
+    ($($t:ty),*) => {
+        $(
+            impl TryIntoEnv<String> for $t {
+                type Error = &'static str;
+
+                fn try_into_env(&self) -> Result<String, &'static str> {
+                    Ok(self.to_string())
+                }
+            }
+        )*
+    };
+}
+
+impl_try_into_env_display!(String, bool, char, IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr);
+
+/// Implement `TryIntoEnv<String>` for numbers, written in the
+/// [`NumberFormat`] in effect with the given method.
+macro_rules! impl_try_into_env_number {
+    ($method:ident: $($t:ty),*) => {
+        $(
+            impl TryIntoEnv<String> for $t {
+                type Error = &'static str;
+
+                fn try_into_env(&self) -> Result<String, &'static str> {
+                    Ok(number_format().$method(self))
+                }
+            }
+        )*
+    };
+}
+
+impl_try_into_env_number!(
+    int: i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize, NonZeroI8, NonZeroI16,
+    NonZeroI32, NonZeroI64, NonZeroI128, NonZeroIsize, NonZeroU8, NonZeroU16, NonZeroU32,
+    NonZeroU64, NonZeroU128, NonZeroUsize
+);
+impl_try_into_env_number!(float: f32, f64);
+
+impl TryIntoEnv<String> for PathBuf {
+    type Error = &'static str;
+
+    fn try_into_env(&self) -> Result<String, Self::Error> {
+        self.to_str()
+            .map(|s| s.to_string())
+            .ok_or("path is not valid unicode")
+    }
+}
+
 impl TryFromEnv<String> for &'static str {
     type Error = &'static str;
 
+    const TEXT: bool = true;
+
     fn try_from_env(value: &str) -> Result<String, Self::Error> {
         Ok(value.to_string())
     }
+
+    fn to_env(value: &String) -> Option<String> {
+        value.try_into_env().ok()
+    }
 }
 
 impl TryFromEnv<Self> for String {
     type Error = &'static str;
 
+    const TEXT: bool = true;
+
     fn try_from_env(value: &str) -> Result<Self, Self::Error> {
         Ok(value.to_string())
     }
+
+    fn to_env(value: &Self) -> Option<String> {
+        value.try_into_env().ok()
+    }
 }
 
 impl TryFromEnv<Self> for bool {
@@ -217,116 +617,387 @@ impl TryFromEnv<Self> for bool {
         // value.parse().map_err(|err| "parse error")
         Ok(rawconv::to_bool(value))
     }
+
+    fn to_env(value: &Self) -> Option<String> {
+        value.try_into_env().ok()
+    }
+}
+
+/// Implement `TryFromEnv` for types that parse with `FromStr`.
+macro_rules! impl_try_from_env_prim {
+    ($($t:ty),*) => {
+        $(
+            impl TryFromEnv<Self> for $t {
+                type Error = &'static str;
+
+                fn try_from_env(value: &str) -> Result<Self, Self::Error> {
+                    rawconv::to_prim(value)
+                }
+
+                fn to_env(value: &Self) -> Option<String> {
+                    value.try_into_env().ok()
+                }
+            }
+        )*
+    };
 }
 
-impl TryFromEnv<Self> for usize {
+impl_try_from_env_prim!(char);
+
+/// Implement `TryFromEnv` for numbers, which are read in the
+/// [`NumberFormat`] in effect.
+macro_rules! impl_try_from_env_number {
+    ($($t:ty),*) => {
+        $(
+            impl TryFromEnv<Self> for $t {
+                type Error = &'static str;
+
+                fn try_from_env(value: &str) -> Result<Self, Self::Error> {
+                    rawconv::to_prim(&number_format().normalize(value))
+                }
+
+                fn to_env(value: &Self) -> Option<String> {
+                    value.try_into_env().ok()
+                }
+            }
+        )*
+    };
+}
+
+impl_try_from_env_number!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize, f32, f64);
+
+/// Implement `TryFromEnv` for the `NonZero*` types, with an error
+/// that says what is wrong when the env var is `0`.
+macro_rules! impl_try_from_env_nonzero {
+    ($($t:ty => $int:ty),*) => {
+        $(
+            impl TryFromEnv<Self> for $t {
+                type Error = &'static str;
+
+                fn try_from_env(value: &str) -> Result<Self, Self::Error> {
+                    let n: $int = rawconv::to_prim(&number_format().normalize(value))?;
+                    <$t>::new(n).ok_or("value must not be zero")
+                }
+
+                fn to_env(value: &Self) -> Option<String> {
+                    value.try_into_env().ok()
+                }
+            }
+        )*
+    };
+}
+
+impl_try_from_env_nonzero!(
+    NonZeroI8 => i8, NonZeroI16 => i16, NonZeroI32 => i32, NonZeroI64 => i64,
+    NonZeroI128 => i128, NonZeroIsize => isize, NonZeroU8 => u8, NonZeroU16 => u16,
+    NonZeroU32 => u32, NonZeroU64 => u64, NonZeroU128 => u128, NonZeroUsize => usize
+);
+
+impl TryFromEnv<Self> for IpAddr {
     type Error = &'static str;
 
     fn try_from_env(value: &str) -> Result<Self, Self::Error> {
-        // value.parse().map_err(|err| "parse error")
-        rawconv::to_prim(value)
+        rawconv::to_prim(value.trim())
+    }
+
+    fn to_env(value: &Self) -> Option<String> {
+        value.try_into_env().ok()
     }
 }
 
-impl TryFromEnv<Self> for i8 {
+impl TryFromEnv<Self> for Ipv4Addr {
     type Error = &'static str;
 
     fn try_from_env(value: &str) -> Result<Self, Self::Error> {
-        rawconv::to_prim(value)
+        rawconv::to_prim(value.trim())
+    }
+
+    fn to_env(value: &Self) -> Option<String> {
+        value.try_into_env().ok()
     }
 }
 
-impl TryFromEnv<Self> for u8 {
+impl TryFromEnv<Self> for Ipv6Addr {
     type Error = &'static str;
 
     fn try_from_env(value: &str) -> Result<Self, Self::Error> {
-        rawconv::to_prim(value)
+        rawconv::to_prim(value.trim())
+    }
+
+    fn to_env(value: &Self) -> Option<String> {
+        value.try_into_env().ok()
     }
 }
 
-impl TryFromEnv<Self> for i16 {
+impl TryFromEnv<Self> for SocketAddr {
     type Error = &'static str;
 
     fn try_from_env(value: &str) -> Result<Self, Self::Error> {
-        rawconv::to_prim(value)
+        rawconv::to_prim(value.trim())
+    }
+
+    fn to_env(value: &Self) -> Option<String> {
+        value.try_into_env().ok()
     }
 }
 
-impl TryFromEnv<Self> for u16 {
+#[cfg(feature = "uuid")]
+impl_try_into_env_display!(uuid::Uuid);
+
+/// Accepts any of the usual UUID spellings: hyphenated, simple,
+/// braced or URN.
+#[cfg(feature = "uuid")]
+impl TryFromEnv<Self> for uuid::Uuid {
     type Error = &'static str;
 
     fn try_from_env(value: &str) -> Result<Self, Self::Error> {
-        rawconv::to_prim(value)
+        rawconv::to_prim(value.trim())
+    }
+
+    fn to_env(value: &Self) -> Option<String> {
+        value.try_into_env().ok()
     }
 }
 
-impl TryFromEnv<Self> for i32 {
+#[cfg(feature = "log")]
+impl_try_into_env_display!(log::LevelFilter, log::Level);
+
+/// One of `off`, `error`, `warn`, `info`, `debug` or `trace`, in any
+/// case.
+#[cfg(feature = "log")]
+impl TryFromEnv<Self> for log::LevelFilter {
     type Error = &'static str;
 
-    fn try_from_env(value: &str) -> Result<Self, Self::Error> {
-        rawconv::to_prim(value)
+    // `Self::Error` would be ambiguous with the `Error` variant.
+    fn try_from_env(value: &str) -> Result<Self, &'static str> {
+        value
+            .trim()
+            .parse()
+            .map_err(|_err| "expected one of: off, error, warn, info, debug, trace")
+    }
+
+    fn to_env(value: &Self) -> Option<String> {
+        value.try_into_env().ok()
+    }
+}
+
+/// One of `error`, `warn`, `info`, `debug` or `trace`, in any case.
+#[cfg(feature = "log")]
+impl TryFromEnv<Self> for log::Level {
+    type Error = &'static str;
+
+    fn try_from_env(value: &str) -> Result<Self, &'static str> {
+        value
+            .trim()
+            .parse()
+            .map_err(|_err| "expected one of: error, warn, info, debug, trace")
+    }
+
+    fn to_env(value: &Self) -> Option<String> {
+        value.try_into_env().ok()
     }
 }
 
-impl TryFromEnv<Self> for i64 {
+#[cfg(feature = "tracing")]
+impl_try_into_env_display!(tracing::Level, tracing::level_filters::LevelFilter);
+
+/// One of `error`, `warn`, `info`, `debug` or `trace`, in any case.
+#[cfg(feature = "tracing")]
+impl TryFromEnv<Self> for tracing::Level {
     type Error = &'static str;
 
     fn try_from_env(value: &str) -> Result<Self, Self::Error> {
-        rawconv::to_prim(value)
+        rawconv::to_prim(value.trim())
+            .map_err(|_err| "expected one of: error, warn, info, debug, trace")
+    }
+
+    fn to_env(value: &Self) -> Option<String> {
+        value.try_into_env().ok()
     }
 }
 
-impl TryFromEnv<Self> for u32 {
+/// One of `off`, `error`, `warn`, `info`, `debug` or `trace`, in any
+/// case.
+#[cfg(feature = "tracing")]
+impl TryFromEnv<Self> for tracing::level_filters::LevelFilter {
     type Error = &'static str;
 
     fn try_from_env(value: &str) -> Result<Self, Self::Error> {
-        rawconv::to_prim(value)
+        rawconv::to_prim(value.trim())
+            .map_err(|_err| "expected one of: off, error, warn, info, debug, trace")
+    }
+
+    fn to_env(value: &Self) -> Option<String> {
+        value.try_into_env().ok()
     }
 }
 
-impl TryFromEnv<Self> for u64 {
+/// A leading `~` is expanded to the home directory. Read an
+/// [`ExpandedPath`] to expand `$VAR` references as well.
+impl TryFromEnv<Self> for PathBuf {
     type Error = &'static str;
 
     fn try_from_env(value: &str) -> Result<Self, Self::Error> {
-        rawconv::to_prim(value)
+        Ok(rawconv::to_path(value))
+    }
+
+    fn to_env(value: &Self) -> Option<String> {
+        value.try_into_env().ok()
     }
 }
 
-impl TryFromEnv<Self> for f32 {
+/// Parsed from a TOML array, e.g. `[1, 2, 3]`. The elements can be
+/// anything that implements [`FromTomlValue`], including other
+/// collections.
+impl<T: FromTomlValue> TryFromEnv<Self> for Vec<T> {
     type Error = &'static str;
 
     fn try_from_env(value: &str) -> Result<Self, Self::Error> {
-        rawconv::to_prim(value)
+        rawconv::to_vec(value)
+    }
+
+    fn to_env(value: &Self) -> Option<String> {
+        value.to_toml().map(|v| tomlconv::to_inline(&v))
     }
 }
 
-impl TryFromEnv<Self> for f64 {
+/// Parsed from a TOML inline table, e.g. `{ a = 1, b = 2 }`. The
+/// values can be anything that implements [`FromTomlValue`],
+/// including other collections.
+impl<T: FromTomlValue> TryFromEnv<Self> for HashMap<String, T> {
     type Error = &'static str;
 
     fn try_from_env(value: &str) -> Result<Self, Self::Error> {
-        rawconv::to_prim(value)
+        rawconv::to_map(value)
+    }
+
+    fn to_env(value: &Self) -> Option<String> {
+        value.to_toml().map(|v| tomlconv::to_inline(&v))
     }
 }
 
-impl<T: FromStr + Debug> TryFromEnv<Self> for Vec<T>
-where
-    <T as FromStr>::Err: Debug,
-{
+/// Like `HashMap<String, T>`, with keys in sorted order.
+impl<T: FromTomlValue> TryFromEnv<Self> for BTreeMap<String, T> {
     type Error = &'static str;
 
     fn try_from_env(value: &str) -> Result<Self, Self::Error> {
-        rawconv::to_vec(value)
+        rawconv::to_map(value)
     }
+
+    fn to_env(value: &Self) -> Option<String> {
+        value.to_toml().map(|v| tomlconv::to_inline(&v))
+    }
+}
+
+/// Implement `TryFromEnv` for tuples, parsed from a TOML array with
+/// one element per field, e.g. `["host.example", 443]` for a
+/// `(String, u16)`.
+macro_rules! impl_try_from_env_tuple {
+    ($(($($t:ident),+)),*) => {
+        $(
+            impl<$($t: FromTomlValue),+> TryFromEnv<Self> for ($($t,)+) {
+                type Error = &'static str;
+
+                fn try_from_env(value: &str) -> Result<Self, Self::Error> {
+                    rawconv::to_value(value)
+                }
+
+                fn to_env(value: &Self) -> Option<String> {
+                    value.to_toml().map(|v| tomlconv::to_inline(&v))
+                }
+            }
+        )*
+    };
 }
 
-impl<T: FromStr + Debug> TryFromEnv<Self> for HashMap<String, T>
+impl_try_from_env_tuple!((A, B), (A, B, C), (A, B, C, D));
+
+fn parse<U, T: TryFromEnv<U>>(key: &str, value: &str) -> Result<U, BiodomeError>
 where
-    <T as FromStr>::Err: Debug,
+    <T as TryFromEnv<U>>::Error: std::fmt::Debug,
 {
-    type Error = &'static str;
+    if let Some(message) = lint::quoted_scalar(std::any::type_name::<U>(), value) {
+        lint::record(key, message);
+    }
+    T::try_from_env(value).map_err(|err| {
+        let mut reason = format!("{:?}", err);
+        // The error may quote the value, which must not end up in logs.
+        if T::SENSITIVE || registry::sensitive_name(key) {
+            reason = registry::scrub(&reason, value);
+        }
+        debug::log(key, || format!("failed to parse: {}", reason));
+        let err = BiodomeError::Parse {
+            key: key.to_string(),
+            reason,
+        };
+        observer::notify(|o| o.on_parse_error(key, &err));
+        #[cfg(feature = "trace-reads")]
+        trace_read(key, true, Some(&err));
+        err
+    })
+}
 
-    fn try_from_env(value: &str) -> Result<Self, Self::Error> {
-        rawconv::to_hashmap(value)
+/// Emit a `tracing` event for a read of "key", successful unless
+/// there is an "error". Values are left out, since they may be
+/// sensitive.
+#[cfg(feature = "trace-reads")]
+fn trace_read(key: &str, from_env: bool, error: Option<&BiodomeError>) {
+    let source = if from_env { "env" } else { "default" };
+    match error {
+        None => tracing::debug!(target: "biodome", key, source, "read env var"),
+        Some(err) => {
+            tracing::warn!(target: "biodome", key, source, error = %err, "failed to read env var")
+        }
+    }
+}
+
+/// Bookkeeping done for every value that has been resolved. The
+/// default is `None` when there is none, or it wasn't needed.
+fn resolved<U, T: TryFromEnv<U>>(
+    key: &str,
+    raw: Option<String>,
+    default: Option<&U>,
+    required: bool,
+    value: &U,
+) {
+    let from_env = raw.is_some();
+    let sensitive = T::SENSITIVE || registry::sensitive_name(key);
+    #[cfg(feature = "trace-reads")]
+    trace_read(key, from_env, None);
+    observer::notify(|o| match from_env {
+        true => o.on_read(key),
+        false => o.on_default_used(key),
+    });
+    debug::log(key, || match (&raw, default.and_then(T::to_env)) {
+        (Some(raw), _) => format!("using {}", debug::shown(raw, sensitive)),
+        (None, Some(default)) => {
+            format!("using the default {}", debug::shown(&default, sensitive))
+        }
+        (None, None) => "using the default".to_string(),
+    });
+    // Sensitive values are not kept at all, so that the registry never
+    // holds a copy of a secret after it has been dropped. Long values
+    // are only kept as a hash, so that reports stay small.
+    let (raw, hashed) = match raw.filter(|_| !sensitive).map(registry::recordable) {
+        Some((raw, hashed)) => (Some(raw), hashed),
+        None => (None, false),
+    };
+    registry::record(registry::RegistryEntry {
+        key: key.to_string(),
+        type_name: std::any::type_name::<U>(),
+        value: raw,
+        from_env,
+        hashed,
+        default: default.and_then(T::to_env),
+        sensitive: T::SENSITIVE,
+        text: T::TEXT,
+        required,
+        choices: vec![],
+    });
+    // Only values from the environment are linted: a default like an
+    // empty `Secret` is the author's choice, not a misconfiguration.
+    if let Some(message) = T::lint(value).filter(|_| from_env) {
+        lint::record(key, message);
     }
 }
 
@@ -338,15 +1009,301 @@ pub fn biodome<U: From<T>, T: TryFromEnv<U>>(key: &str, default: T) -> U
 where
     <T as TryFromEnv<U>>::Error: std::fmt::Debug,
 {
-    let opt = env::var(key).ok();
-    if let Some(v) = opt {
-        T::try_from_env(&v).expect("Failed to parse")
+    resolve::<U, T>(key, source::value(key), default.into())
+}
+
+/// Like [`biodome`], but look up "key" in "source" instead of in the
+/// installed [`EnvSource`].
+pub fn biodome_from<S: EnvSource + ?Sized, U: From<T>, T: TryFromEnv<U>>(
+    source: &S,
+    key: &str,
+    default: T,
+) -> U
+where
+    <T as TryFromEnv<U>>::Error: std::fmt::Debug,
+{
+    resolve::<U, T>(key, source::lookup(source, key), default.into())
+}
+
+/// Like [`biodome`], but `None` if "key" is not set. The default is
+/// only there to name the type; it is never used.
+pub(crate) fn biodome_optional<T: TryFromEnv<T>>(key: &str, default: T) -> Option<T>
+where
+    T::Error: std::fmt::Debug,
+{
+    source::value(key).map(|_| biodome(key, default))
+}
+
+/// Parse "raw" if there is a value, and record the result. Panics if
+/// the value doesn't parse.
+fn resolve<U, T: TryFromEnv<U>>(key: &str, raw: Option<String>, default: U) -> U
+where
+    <T as TryFromEnv<U>>::Error: std::fmt::Debug,
+{
+    try_resolve::<U, T>(key, raw, default).unwrap_or_else(|err| panic!("{}", err))
+}
+
+fn try_resolve<U, T: TryFromEnv<U>>(
+    key: &str,
+    raw: Option<String>,
+    default: U,
+) -> Result<U, BiodomeError>
+where
+    <T as TryFromEnv<U>>::Error: std::fmt::Debug,
+{
+    resolve_or_default::<U, T>(key, Ok(raw), default).map_err(|(err, _default)| err)
+}
+
+/// Parse "raw" if there is a value, and record the result. On error,
+/// "default" is handed back along with the error.
+fn resolve_or_default<U, T: TryFromEnv<U>>(
+    key: &str,
+    raw: Result<Option<String>, BiodomeError>,
+    default: U,
+) -> Result<U, (BiodomeError, U)>
+where
+    <T as TryFromEnv<U>>::Error: std::fmt::Debug,
+{
+    let raw = match raw {
+        Ok(raw) => raw,
+        Err(err) => return Err((err, default)),
+    };
+    match raw.map(profile::select) {
+        Some(v) => match parse::<U, T>(key, &v) {
+            Ok(value) => {
+                resolved::<U, T>(key, Some(v), Some(&default), false, &value);
+                Ok(value)
+            }
+            Err(err) => Err((err, default)),
+        },
+        None => {
+            resolved::<U, T>(key, None, Some(&default), false, &default);
+            Ok(default)
+        }
+    }
+}
+
+/// Like [`biodome`], but on error, return the default along with the
+/// error instead of panicking.
+pub(crate) fn biodome_or_error<U: From<T>, T: TryFromEnv<U>>(
+    key: &str,
+    default: T,
+) -> Result<U, (BiodomeError, U)>
+where
+    <T as TryFromEnv<U>>::Error: std::fmt::Debug,
+{
+    resolve_or_default::<U, T>(key, source::try_value(key), default.into())
+}
+
+/// Like [`biodome`], but the default is only computed, by calling
+/// "default", if the env var is not set. This is for defaults that
+/// are expensive to make, like reading a file or looking up the
+/// hostname.
+///
+/// ```rust
+/// use biodome::biodome_or_else;
+///
+/// std::env::set_var("NODE_NAME", "worker-3");
+/// let NODE_NAME = biodome_or_else("NODE_NAME", || -> String { unreachable!() });
+/// assert_eq!(NODE_NAME, "worker-3");
+///
+/// let CACHE_DIR = biodome_or_else("CACHE_DIR", || std::env::temp_dir().join("cache"));
+/// assert!(CACHE_DIR.ends_with("cache"));
+/// ```
+pub fn biodome_or_else<U: From<T>, T: TryFromEnv<U>>(key: &str, default: impl FnOnce() -> T) -> U
+where
+    <T as TryFromEnv<U>>::Error: std::fmt::Debug,
+{
+    let Some(raw) = source::value(key).map(profile::select) else {
+        return resolve::<U, T>(key, None, default().into());
+    };
+    let value = parse::<U, T>(key, &raw).unwrap_or_else(|err| panic!("{}", err));
+    resolved::<U, T>(key, Some(raw), None, false, &value);
+    value
+}
+
+/// Read the env var "key", for settings that have no sensible default,
+/// like API keys or database URLs. If the env var has not been set,
+/// the error says so.
+///
+/// ```rust
+/// use biodome::biodome_required;
+///
+/// let err = biodome_required::<String>("PAYMENTS_API_KEY").unwrap_err();
+/// assert_eq!(err.to_string(), "Missing required env var PAYMENTS_API_KEY");
+/// ```
+pub fn biodome_required<T: TryFromEnv<T>>(key: &str) -> Result<T, BiodomeError>
+where
+    T::Error: std::fmt::Debug,
+{
+    let raw = source::try_value(key)?
+        .map(profile::select)
+        .ok_or_else(|| BiodomeError::Missing {
+            key: key.to_string(),
+        })?;
+    let value = parse::<T, T>(key, &raw)?;
+    resolved::<T, T>(key, Some(raw), None, true, &value);
+    Ok(value)
+}
+
+/// Like [`biodome`], but the value must also pass "validator", which
+/// is checked after parsing. A value that fails is reported the same
+/// way as one that doesn't parse, instead of panicking.
+///
+/// ```rust
+/// use biodome::biodome_validated;
+///
+/// std::env::set_var("LISTEN_PORT", "0");
+/// let err = biodome_validated("LISTEN_PORT", 8080u32, |v: &u32| *v > 0 && *v < 65536);
+/// assert_eq!(
+///     err.unwrap_err().to_string(),
+///     "Invalid value for env var LISTEN_PORT: failed validation"
+/// );
+/// ```
+pub fn biodome_validated<U: From<T>, T: TryFromEnv<U>>(
+    key: &str,
+    default: T,
+    validator: impl Validator<U>,
+) -> Result<U, BiodomeError>
+where
+    <T as TryFromEnv<U>>::Error: std::fmt::Debug,
+{
+    let value = biodome_or_error::<U, T>(key, default).map_err(|(err, _default)| err)?;
+    validator.validate(&value).map_err(|reason| {
+        // There's no telling whether the reason shows the value.
+        let reason = if T::SENSITIVE || registry::sensitive_name(key) {
+            "failed validation".to_string()
+        } else {
+            reason
+        };
+        BiodomeError::Invalid {
+            key: key.to_string(),
+            reason,
+        }
+    })?;
+    Ok(value)
+}
+
+/// Like [`biodome`], but the value must be within "range", which is
+/// useful for thread counts and batch sizes.
+///
+/// ```rust
+/// use biodome::biodome_in_range;
+///
+/// std::env::set_var("BATCH_SIZE", "500");
+/// let err = biodome_in_range("BATCH_SIZE", 16u32, 1..=64).unwrap_err();
+/// assert_eq!(
+///     err.to_string(),
+///     "Invalid value for env var BATCH_SIZE: 500 is not between 1 and 64"
+/// );
+/// ```
+pub fn biodome_in_range<U: From<T> + PartialOrd + std::fmt::Display, T: TryFromEnv<U>>(
+    key: &str,
+    default: T,
+    range: std::ops::RangeInclusive<U>,
+) -> Result<U, BiodomeError>
+where
+    <T as TryFromEnv<U>>::Error: std::fmt::Debug,
+{
+    biodome_validated(key, default, range)
+}
+
+/// Like [`biodome_in_range`], but a value outside of "range" is moved
+/// to the nearest end of it, with a [`Lint`] to say so, instead of
+/// being an error.
+///
+/// ```rust
+/// use biodome::{biodome_clamped, lints};
+///
+/// std::env::set_var("WORKER_THREADS", "500");
+/// assert_eq!(biodome_clamped("WORKER_THREADS", 8u32, 1..=64), Ok(64));
+/// assert!(lints().iter().any(|lint| lint.key == "WORKER_THREADS"));
+/// ```
+pub fn biodome_clamped<U: From<T> + PartialOrd + std::fmt::Display + Clone, T: TryFromEnv<U>>(
+    key: &str,
+    default: T,
+    range: std::ops::RangeInclusive<U>,
+) -> Result<U, BiodomeError>
+where
+    <T as TryFromEnv<U>>::Error: std::fmt::Debug,
+{
+    let value = biodome_or_error::<U, T>(key, default).map_err(|(err, _default)| err)?;
+    let clamped = if value < *range.start() {
+        range.start().clone()
+    } else if value > *range.end() {
+        range.end().clone()
     } else {
-        default.into()
+        return Ok(value);
+    };
+    lint::record(
+        key,
+        format!(
+            "{} is not between {} and {}, using {}",
+            value,
+            range.start(),
+            range.end(),
+            clamped
+        ),
+    );
+    Ok(clamped)
+}
+
+/// Read the env var "key", which must be one of "choices". If the
+/// env var has not been set, "default" will be used. If the value is
+/// not one of the choices, the error carries the closest matches so
+/// that typos like `jsn` can be reported as "did you mean 'json'?".
+pub fn biodome_one_of(key: &str, default: &str, choices: &[&str]) -> Result<String, BiodomeError> {
+    one_of(key, default, choices, false)
+}
+
+/// Like [`biodome_one_of`], but ignoring case, so that `S3` matches
+/// the choice `s3`. The choice is returned as it is spelled in
+/// "choices".
+pub fn biodome_one_of_ignore_case(
+    key: &str,
+    default: &str,
+    choices: &[&str],
+) -> Result<String, BiodomeError> {
+    one_of(key, default, choices, true)
+}
+
+fn one_of(
+    key: &str,
+    default: &str,
+    choices: &[&str],
+    ignore_case: bool,
+) -> Result<String, BiodomeError> {
+    let opt = source::try_value(key)?.map(profile::select);
+    let value = opt.clone().unwrap_or_else(|| default.to_string());
+    let chosen = choices.iter().find(|&&c| match ignore_case {
+        true => c.eq_ignore_ascii_case(&value),
+        false => c == value,
+    });
+    if let Some(chosen) = chosen {
+        let chosen = chosen.to_string();
+        resolved::<String, String>(key, opt, Some(&default.to_string()), false, &chosen);
+        registry::set_choices(key, choices);
+        Ok(chosen)
+    } else {
+        // Suggestions are close to the value, so would give it away.
+        let (value, suggestions) = if registry::sensitive_name(key) {
+            ("[REDACTED]".to_string(), vec![])
+        } else {
+            let suggestions = rawconv::suggest(&value, choices);
+            (value, suggestions)
+        };
+        Err(BiodomeError::NotOneOf {
+            key: key.to_string(),
+            suggestions,
+            choices: choices.iter().map(|c| c.to_string()).collect(),
+            value,
+        })
     }
 }
 
-pub fn biodome_callable<U: From<T>, T: TryFromEnv<U> + Copy>(
+/// Like [`biodome`], but return a closure that reads the env var
+/// again each time it is called. The default is cloned for each call.
+pub fn biodome_callable<U: From<T>, T: TryFromEnv<U> + Clone>(
     key: &str,
     default: T,
 ) -> impl Fn() -> U
@@ -354,12 +1311,62 @@ where
     <T as TryFromEnv<U>>::Error: std::fmt::Debug,
 {
     let key = key.to_string();
+    move || resolve::<U, T>(&key, source::value(&key), default.clone().into())
+}
+
+/// Like [`biodome_callable`], but the closure returns an error
+/// instead of panicking when the value doesn't parse. Use it in
+/// long-running services, where a bad value set after startup should
+/// be reported rather than crash the process.
+///
+/// ```rust
+/// use biodome::biodome_callable_try;
+///
+/// let workers = biodome_callable_try("WORKERS", 4);
+/// assert_eq!(workers(), Ok(4));
+/// std::env::set_var("WORKERS", "many");
+/// assert!(workers().is_err());
+/// ```
+pub fn biodome_callable_try<U: From<T>, T: TryFromEnv<U> + Clone>(
+    key: &str,
+    default: T,
+) -> impl Fn() -> Result<U, BiodomeError>
+where
+    <T as TryFromEnv<U>>::Error: std::fmt::Debug,
+{
+    let key = key.to_string();
+    move || try_resolve::<U, T>(&key, source::try_value(&key)?, default.clone().into())
+}
+
+/// Like [`biodome_callable`], but the value is read and parsed at most
+/// once per "ttl". Calls in between return the value from the last
+/// read, so the closure is cheap enough for a hot loop.
+///
+/// ```rust
+/// use std::time::Duration;
+///
+/// let TIMEOUT = biodome::biodome_cached("TIMEOUT", 5.0, Duration::from_secs(30));
+/// assert_eq!(TIMEOUT(), 5.0);
+/// ```
+pub fn biodome_cached<U: From<T> + Clone, T: TryFromEnv<U> + Clone>(
+    key: &str,
+    default: T,
+    ttl: Duration,
+) -> impl Fn() -> U
+where
+    <T as TryFromEnv<U>>::Error: std::fmt::Debug,
+{
+    let read = biodome_callable::<U, T>(key, default);
+    let cache: Mutex<Option<(Instant, U)>> = Mutex::new(None);
     move || {
-        let opt = env::var(key.clone()).ok();
-        if let Some(v) = opt {
-            T::try_from_env(&v).expect("Failed to parse")
-        } else {
-            default.into()
+        let mut cache = cache.lock().unwrap();
+        match cache.as_ref() {
+            Some((at, value)) if at.elapsed() < ttl => value.clone(),
+            _ => {
+                let value = read();
+                *cache = Some((Instant::now(), value.clone()));
+                value
+            }
         }
     }
 }
@@ -368,6 +1375,7 @@ where
 mod tests {
     use super::*;
     use std::collections::HashMap;
+    use std::env;
     use std::iter::FromIterator;
 
     #[test]
@@ -390,6 +1398,60 @@ mod tests {
         assert_eq!(got, expected);
     }
 
+    #[test]
+    fn value_as_types_btreemap() {
+        let default = BTreeMap::from([("b".to_string(), 2), ("a".to_string(), 1)]);
+        assert_eq!(biodome("TABM", default.clone()), default);
+        env::set_var("TABM", "{ zz = 3, aa = 4 }");
+        let got = biodome("TABM", default);
+        assert_eq!(got.keys().collect::<Vec<_>>(), vec!["aa", "zz"]);
+        assert_eq!(got["zz"], 3);
+        env::remove_var("TABM");
+    }
+
+    #[test]
+    fn nested_collections() {
+        let routes: HashMap<String, Vec<String>> = biodome("ROUTES", HashMap::new());
+        assert!(routes.is_empty());
+        env::set_var(
+            "ROUTES",
+            r#"{ api = ["10.0.0.1", "10.0.0.2"], web = ["10.0.1.1"] }"#,
+        );
+        let routes: HashMap<String, Vec<String>> = biodome("ROUTES", HashMap::new());
+        assert_eq!(routes["api"], vec!["10.0.0.1", "10.0.0.2"]);
+        assert_eq!(routes["web"], vec!["10.0.1.1"]);
+        env::remove_var("ROUTES");
+
+        let default = BTreeMap::from([("api".to_string(), vec![1, 2])]);
+        assert_eq!(
+            <BTreeMap<String, Vec<i32>> as TryFromEnv<_>>::to_env(&default),
+            Some("{ api = [1, 2] }".to_string())
+        );
+    }
+
+    #[test]
+    fn array_of_tables() {
+        env::set_var(
+            "UPSTREAMS",
+            r#"[{name = "a", weight = 1}, {name = "b", weight = 2}]"#,
+        );
+        let upstreams: Vec<HashMap<String, toml::Value>> = biodome("UPSTREAMS", vec![]);
+        assert_eq!(upstreams.len(), 2);
+        assert_eq!(upstreams[0]["name"].as_str(), Some("a"));
+        assert_eq!(upstreams[1]["weight"].as_integer(), Some(2));
+        env::remove_var("UPSTREAMS");
+    }
+
+    #[test]
+    fn tuples() {
+        env::set_var("ORIGIN", r#"["host.example", 443]"#);
+        let origin: (String, u16) = biodome("ORIGIN", ("localhost".to_string(), 80));
+        assert_eq!(origin, ("host.example".to_string(), 443));
+        let centre = biodome("CENTRE", (51.5, -0.12));
+        assert_eq!(centre, (51.5, -0.12));
+        env::remove_var("ORIGIN");
+    }
+
     #[test]
     fn values_as_types() {
         assert_eq!(biodome("ABC", "123".to_string()), "123");
@@ -414,6 +1476,19 @@ mod tests {
         assert_eq!(v, vec![4, 5, 6]);
     }
 
+    #[test]
+    fn more_primitives() {
+        assert_eq!(biodome("ABC", 123_i128), 123_i128);
+        assert_eq!(biodome("ABC", 123_u128), 123_u128);
+        assert_eq!(biodome("ABC", 123_isize), 123_isize);
+        assert_eq!(biodome("ABC", 'x'), 'x');
+        env::set_var("PRIM_SEPARATOR", ";");
+        assert_eq!(biodome("PRIM_SEPARATOR", ','), ';');
+        env::set_var("PRIM_SEPARATOR", "-170141183460469231731687303715884105728");
+        assert_eq!(biodome("PRIM_SEPARATOR", 0_i128), i128::MIN);
+        env::remove_var("PRIM_SEPARATOR");
+    }
+
     #[test]
     fn basic() {
         struct Settings {
@@ -454,6 +1529,283 @@ mod tests {
         env::remove_var("NT");
     }
 
+    #[test]
+    fn sets() {
+        use std::collections::{BTreeSet, HashSet};
+
+        let default = HashSet::from([80]);
+        assert_eq!(biodome("SET_PORTS", default.clone()), default);
+        env::set_var("SET_PORTS", "[80, 443, 443]");
+        assert_eq!(biodome("SET_PORTS", default), HashSet::from([80, 443]));
+        assert_eq!(
+            biodome("SET_PORTS", BTreeSet::new()),
+            BTreeSet::from([80, 443])
+        );
+        env::remove_var("SET_PORTS");
+    }
+
+    #[test]
+    fn nonzero() {
+        let default = NonZeroUsize::new(4).unwrap();
+        assert_eq!(biodome("NZ_WORKERS", default), default);
+        env::set_var("NZ_WORKERS", "16");
+        assert_eq!(biodome("NZ_WORKERS", default).get(), 16);
+        assert_eq!(NonZeroU16::try_from_env("0"), Err("value must not be zero"));
+        assert_eq!(NonZeroI32::try_from_env("x"), Err("parse error"));
+        assert_eq!(NonZeroI8::try_from_env("-1").unwrap().get(), -1);
+        env::remove_var("NZ_WORKERS");
+    }
+
+    #[test]
+    fn datetimes() {
+        let default: Datetime = "2000-01-01T00:00:00Z".parse().unwrap();
+        assert_eq!(biodome("CUTOFF", default.clone()), default);
+        env::set_var("CUTOFF", "1979-05-27T07:32:00-08:00");
+        assert_eq!(
+            biodome("CUTOFF", default).to_string(),
+            "1979-05-27T07:32:00-08:00"
+        );
+        env::remove_var("CUTOFF");
+    }
+
+    #[cfg(feature = "uuid")]
+    #[test]
+    fn uuids() {
+        let default = uuid::Uuid::nil();
+        assert_eq!(biodome("TENANT_ID", default), default);
+        env::set_var("TENANT_ID", "67e55044-10b1-426f-9247-bb680e5fe0c8");
+        assert_eq!(
+            biodome("TENANT_ID", default).to_string(),
+            "67e55044-10b1-426f-9247-bb680e5fe0c8"
+        );
+        env::remove_var("TENANT_ID");
+    }
+
+    #[cfg(feature = "log")]
+    #[test]
+    fn log_levels() {
+        use log::{Level, LevelFilter};
+
+        assert_eq!(
+            biodome("LOG_LEVELS_TEST", LevelFilter::Info),
+            LevelFilter::Info
+        );
+        env::set_var("LOG_LEVELS_TEST", "Debug");
+        assert_eq!(
+            biodome("LOG_LEVELS_TEST", LevelFilter::Info),
+            LevelFilter::Debug
+        );
+        assert_eq!(biodome("LOG_LEVELS_TEST", Level::Info), Level::Debug);
+        assert!(<LevelFilter as TryFromEnv<_>>::try_from_env("verbose").is_err());
+        assert!(<Level as TryFromEnv<_>>::try_from_env("off").is_err());
+        assert_eq!(
+            LevelFilter::to_env(&LevelFilter::Warn),
+            Some("WARN".to_string())
+        );
+        env::remove_var("LOG_LEVELS_TEST");
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn tracing_levels() {
+        use tracing::level_filters::LevelFilter;
+        use tracing::Level;
+
+        assert_eq!(biodome("TRACE_LEVEL", Level::INFO), Level::INFO);
+        env::set_var("TRACE_LEVEL", "WARN");
+        assert_eq!(biodome("TRACE_LEVEL", Level::INFO), Level::WARN);
+        assert_eq!(biodome("TRACE_LEVEL", LevelFilter::OFF), LevelFilter::WARN);
+        assert!(<Level as TryFromEnv<_>>::try_from_env("off").is_err());
+        env::remove_var("TRACE_LEVEL");
+    }
+
+    #[test]
+    fn addresses() {
+        let default = SocketAddr::from(([0, 0, 0, 0], 8080));
+        assert_eq!(biodome("BIND_ADDR", default), default);
+        env::set_var("BIND_ADDR", "127.0.0.1:9000");
+        assert_eq!(
+            biodome("BIND_ADDR", default),
+            SocketAddr::from(([127, 0, 0, 1], 9000))
+        );
+        env::set_var("BIND_ADDR", "[::1]:9000");
+        assert_eq!(
+            biodome("BIND_ADDR", default),
+            SocketAddr::from((Ipv6Addr::LOCALHOST, 9000))
+        );
+        env::remove_var("BIND_ADDR");
+
+        env::set_var("BIND_IP", " 10.0.0.1 ");
+        assert_eq!(
+            biodome("BIND_IP", Ipv4Addr::UNSPECIFIED),
+            Ipv4Addr::new(10, 0, 0, 1)
+        );
+        assert_eq!(
+            biodome("BIND_IP", IpAddr::from(Ipv4Addr::UNSPECIFIED)),
+            IpAddr::from([10, 0, 0, 1])
+        );
+        env::remove_var("BIND_IP");
+    }
+
+    #[test]
+    fn paths() {
+        let default = PathBuf::from("/srv/data");
+        assert_eq!(biodome("DATA_DIR", default.clone()), default);
+        env::set_var("PATHS_APP_NAME", "demo");
+        env::set_var("DATA_DIR", "~/data/$PATHS_APP_NAME");
+        let home = env::var("HOME").unwrap();
+        assert_eq!(
+            biodome("DATA_DIR", default.clone()),
+            PathBuf::from(format!("{}/data/$PATHS_APP_NAME", home))
+        );
+        assert_eq!(
+            biodome("DATA_DIR", ExpandedPath::from(default)).0,
+            PathBuf::from(format!("{}/data/demo", home))
+        );
+        env::remove_var("DATA_DIR");
+        env::remove_var("PATHS_APP_NAME");
+    }
+
+    #[test]
+    fn one_of() {
+        let choices = ["json", "text", "yaml"];
+        assert_eq!(biodome_one_of("FORMAT", "text", &choices).unwrap(), "text");
+        env::set_var("FORMAT", "yaml");
+        assert_eq!(biodome_one_of("FORMAT", "text", &choices).unwrap(), "yaml");
+        let contract = contract::Contract::current();
+        assert_eq!(contract.keys["FORMAT"].choices, choices);
+        env::set_var("FORMAT", "jsn");
+        let err = biodome_one_of("FORMAT", "text", &choices).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Invalid value for env var FORMAT: got 'jsn', did you mean 'json'?"
+        );
+        env::set_var("FORMAT", "xml");
+        let err = biodome_one_of("FORMAT", "text", &choices).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Invalid value for env var FORMAT: got 'xml', expected one of: json, text, yaml"
+        );
+        env::remove_var("FORMAT");
+    }
+
+    #[test]
+    fn registry_entries() {
+        env::set_var("REGISTRY_SET", "3");
+        biodome("REGISTRY_SET", 1u8);
+        biodome("REGISTRY_UNSET", 1.5);
+        let entries = registry();
+        let set = entries.iter().find(|e| e.key == "REGISTRY_SET").unwrap();
+        let unset = entries.iter().find(|e| e.key == "REGISTRY_UNSET").unwrap();
+        assert!(set.from_env());
+        assert_eq!(set.type_name, "u8");
+        assert_eq!(set.default.as_deref(), Some("1"));
+        assert!(!unset.from_env());
+        assert_eq!(unset.type_name, "f64");
+    }
+
+    #[test]
+    fn secrets_unrecorded() {
+        env::set_var("REGISTRY_API_KEY", "hunter2hunter2");
+        let key = biodome("REGISTRY_API_KEY", Secret::new(String::new()));
+        drop(key);
+        let entry = registry()
+            .into_iter()
+            .find(|e| e.key == "REGISTRY_API_KEY")
+            .unwrap();
+        assert!(entry.from_env());
+        assert!(entry.value.is_none());
+        assert!(!format!("{:?}", registry()).contains("hunter2hunter2"));
+        env::remove_var("REGISTRY_API_KEY");
+    }
+
+    #[test]
+    fn weak_secrets() {
+        biodome(
+            "LINT_GOOD_TOKEN",
+            Secret::new("dcc8a1bd0e6f4b7f".to_string()),
+        );
+        env::set_var("LINT_WEAK_TOKEN", "password");
+        biodome(
+            "LINT_WEAK_TOKEN",
+            Secret::new("dcc8a1bd0e6f4b7f".to_string()),
+        );
+        biodome("LINT_UNSET_TOKEN", Secret::new(String::new()));
+        let found: Vec<String> = lints().into_iter().map(|l| l.key).collect();
+        assert!(found.contains(&"LINT_WEAK_TOKEN".to_string()));
+        assert!(!found.contains(&"LINT_GOOD_TOKEN".to_string()));
+        assert!(!found.contains(&"LINT_UNSET_TOKEN".to_string()));
+        env::remove_var("LINT_WEAK_TOKEN");
+    }
+
+    #[test]
+    fn crash_report() {
+        env::set_var("CRASH_TIMEOUT", "30");
+        env::set_var("CRASH_SIGNING", "k3y-material-0001");
+        biodome("CRASH_TIMEOUT", 10);
+        biodome("CRASH_RETRIES", 3);
+        biodome("CRASH_SIGNING", Secret::new(String::new()));
+        let report = crash_context();
+        assert!(report.contains("  CRASH_TIMEOUT = 30 [i32]\n"));
+        assert!(report.contains("  CRASH_RETRIES = 3 (default) [i32]\n"));
+        assert!(report.contains("  CRASH_SIGNING = [REDACTED]"));
+        assert!(!report.contains("k3y-material-0001"));
+        env::remove_var("CRASH_TIMEOUT");
+        env::remove_var("CRASH_SIGNING");
+    }
+
+    #[test]
+    fn export_resolved() {
+        use export::Format;
+
+        env::set_var("EXPORT_LOG_LEVEL", "debug");
+        env::set_var("EXPORT_API_TOKEN", "eb0ae0bf4f1c8d2b");
+        biodome("EXPORT_LOG_LEVEL", "info");
+        biodome("EXPORT_WORKERS", 4);
+        biodome("EXPORT_API_TOKEN", Secret::new(String::new()));
+        let script = export::resolved(Format::PowerShell);
+        assert!(script.contains("$env:EXPORT_LOG_LEVEL = 'debug'\n"));
+        assert!(!script.contains("EXPORT_WORKERS"));
+        assert!(script.contains("# EXPORT_API_TOKEN is a secret and was not exported\n"));
+        assert!(!script.contains("eb0ae0bf4f1c8d2b"));
+        let script = export::resolved(Format::Cmd);
+        assert!(script.contains("set \"EXPORT_LOG_LEVEL=debug\"\n"));
+        assert!(script.contains("rem EXPORT_API_TOKEN is a secret"));
+        env::remove_var("EXPORT_LOG_LEVEL");
+        env::remove_var("EXPORT_API_TOKEN");
+    }
+
+    #[test]
+    fn envrc() {
+        env::set_var("ENVRC_LOG_LEVEL", "debug");
+        biodome("ENVRC_LOG_LEVEL", "info");
+        biodome("ENVRC_WORKERS", 4);
+        biodome("ENVRC_DB_PASSWORD", "hunter2");
+        let envrc = export::Envrc::new().dotenv_if_exists(".env.local").render();
+        assert!(envrc.starts_with("# Generated by biodome\n"));
+        assert!(envrc.contains("export ENVRC_LOG_LEVEL='debug'\n"));
+        assert!(envrc.contains("export ENVRC_WORKERS='4'\n"));
+        assert!(envrc.contains("# export ENVRC_DB_PASSWORD=\n"));
+        assert!(envrc.ends_with("dotenv_if_exists '.env.local'\n"));
+        env::remove_var("ENVRC_LOG_LEVEL");
+    }
+
+    #[test]
+    fn compose() {
+        env::set_var("COMPOSE_GREETING", "costs $5");
+        biodome("COMPOSE_GREETING", "hi");
+        biodome("COMPOSE_WORKERS", 4);
+        biodome("COMPOSE_TOKEN", Secret::new(String::new()));
+        let block = export::compose_environment();
+        assert!(block.contains("      COMPOSE_GREETING: \"costs $$5\"\n"));
+        assert!(block.contains("      COMPOSE_WORKERS: \"4\"\n"));
+        assert!(block.contains("      COMPOSE_TOKEN: ${COMPOSE_TOKEN}\n"));
+        let stub = export::compose_env_file();
+        assert!(stub.contains("COMPOSE_TOKEN=\n"));
+        assert!(!stub.contains("COMPOSE_WORKERS"));
+        env::remove_var("COMPOSE_GREETING");
+    }
+
     #[test]
     fn callables() {
         let NUM_THREADS = biodome_callable("NUM_THREADS", 8);
@@ -461,5 +1813,260 @@ mod tests {
         env::set_var("NUM_THREADS", "16");
         assert_eq!(NUM_THREADS(), 16);
         env::remove_var("NUM_THREADS");
+
+        let RETRIES = biodome_callable_try("CALLABLE_RETRIES", 3u8);
+        env::set_var("CALLABLE_RETRIES", "300");
+        assert_eq!(
+            RETRIES().unwrap_err().to_string(),
+            "Failed to parse env var CALLABLE_RETRIES: \"parse error\""
+        );
+        env::set_var("CALLABLE_RETRIES", "5");
+        assert_eq!(RETRIES(), Ok(5));
+
+        let HOSTS = biodome_callable("CALLABLE_HOSTS", vec!["localhost".to_string()]);
+        assert_eq!(HOSTS(), vec!["localhost"]);
+        env::set_var("CALLABLE_HOSTS", r#"["a", "b"]"#);
+        assert_eq!(HOSTS(), vec!["a", "b"]);
+        env::remove_var("CALLABLE_HOSTS");
+    }
+
+    #[test]
+    fn cached() {
+        let WORKERS = biodome_cached("CACHED_WORKERS", 4, Duration::from_secs(3600));
+        assert_eq!(WORKERS(), 4);
+        env::set_var("CACHED_WORKERS", "16");
+        assert_eq!(WORKERS(), 4);
+
+        let WORKERS = biodome_cached("CACHED_WORKERS", 4, Duration::ZERO);
+        assert_eq!(WORKERS(), 16);
+        env::set_var("CACHED_WORKERS", "32");
+        assert_eq!(WORKERS(), 32);
+        env::remove_var("CACHED_WORKERS");
+    }
+
+    #[test]
+    fn required() {
+        assert_eq!(
+            biodome_required::<u16>("REQUIRED_PORT"),
+            Err(BiodomeError::Missing {
+                key: "REQUIRED_PORT".to_string()
+            })
+        );
+        env::set_var("REQUIRED_PORT", "http");
+        assert!(matches!(
+            biodome_required::<u16>("REQUIRED_PORT"),
+            Err(BiodomeError::Parse { .. })
+        ));
+        env::set_var("REQUIRED_PORT", "8443");
+        assert_eq!(biodome_required::<u16>("REQUIRED_PORT"), Ok(8443));
+        let entry = registry()
+            .into_iter()
+            .find(|e| e.key == "REQUIRED_PORT")
+            .unwrap();
+        assert!(entry.required);
+        assert_eq!(entry.default, None);
+        env::remove_var("REQUIRED_PORT");
+    }
+
+    #[test]
+    fn validated() {
+        let positive = |v: &i64| *v > 0;
+        assert_eq!(biodome_validated("VALIDATED_TIMEOUT", 30, positive), Ok(30));
+        env::set_var("VALIDATED_TIMEOUT", "-5");
+        assert_eq!(
+            biodome_validated("VALIDATED_TIMEOUT", 30, positive),
+            Err(BiodomeError::Invalid {
+                key: "VALIDATED_TIMEOUT".to_string(),
+                reason: "failed validation".to_string()
+            })
+        );
+        env::set_var("VALIDATED_TIMEOUT", "soon");
+        assert!(matches!(
+            biodome_validated("VALIDATED_TIMEOUT", 30, positive),
+            Err(BiodomeError::Parse { .. })
+        ));
+        env::remove_var("VALIDATED_TIMEOUT");
+    }
+
+    #[test]
+    fn redacted_errors() {
+        struct Dsn;
+
+        impl TryFromEnv<Self> for Dsn {
+            type Error = String;
+
+            fn try_from_env(value: &str) -> Result<Self, Self::Error> {
+                Err(format!("no scheme in {}", value))
+            }
+        }
+
+        env::set_var("REDACT_DB_PASSWORD", "hunter2");
+        let (err, _default) = biodome_or_error::<Dsn, Dsn>("REDACT_DB_PASSWORD", Dsn)
+            .err()
+            .unwrap();
+        assert_eq!(
+            err.to_string(),
+            "Failed to parse env var REDACT_DB_PASSWORD: \"no scheme in [REDACTED]\""
+        );
+
+        env::set_var("REDACT_DSN", "postgres:x");
+        mark_sensitive("REDACT_DSN");
+        let err = biodome_one_of("REDACT_DSN", "a", &["postgres:y"]).unwrap_err();
+        assert!(!err.to_string().contains("postgres:x"));
+        assert!(!err.to_string().contains("did you mean"));
+        let err = biodome_validated("REDACT_DSN", String::new(), |v: &String| v.is_empty());
+        assert_eq!(
+            err.unwrap_err().to_string(),
+            "Invalid value for env var REDACT_DSN: failed validation"
+        );
+        env::remove_var("REDACT_DB_PASSWORD");
+        env::remove_var("REDACT_DSN");
+    }
+
+    #[cfg(feature = "trace-reads")]
+    #[test]
+    fn trace_reads() {
+        use std::sync::{Arc, Mutex};
+        use tracing::field::{Field, Visit};
+        use tracing::span::{Attributes, Id, Record};
+        use tracing::{Event, Metadata};
+
+        #[derive(Default, Clone)]
+        struct Capture(Arc<Mutex<Vec<String>>>);
+
+        impl Visit for Capture {
+            fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+                let line = format!("{}={:?}", field.name(), value);
+                self.0.lock().unwrap().push(line);
+            }
+        }
+
+        impl tracing::Subscriber for Capture {
+            fn enabled(&self, _: &Metadata<'_>) -> bool {
+                true
+            }
+            fn new_span(&self, _: &Attributes<'_>) -> Id {
+                Id::from_u64(1)
+            }
+            fn record(&self, _: &Id, _: &Record<'_>) {}
+            fn record_follows_from(&self, _: &Id, _: &Id) {}
+            fn event(&self, event: &Event<'_>) {
+                event.record(&mut self.clone());
+            }
+            fn enter(&self, _: &Id) {}
+            fn exit(&self, _: &Id) {}
+        }
+
+        let capture = Capture::default();
+        env::set_var("TRACED_WORKERS", "many");
+        tracing::subscriber::with_default(capture.clone(), || {
+            biodome("TRACED_TIMEOUT", 30);
+            let _ = biodome_validated("TRACED_WORKERS", 4, |_: &i32| true);
+        });
+        env::remove_var("TRACED_WORKERS");
+        let lines = capture.0.lock().unwrap().join(" ");
+        assert!(lines.contains("key=\"TRACED_TIMEOUT\" source=\"default\""));
+        assert!(lines.contains("key=\"TRACED_WORKERS\" source=\"env\" error="));
+    }
+
+    #[test]
+    fn or_else() {
+        let calls = std::cell::Cell::new(0);
+        let default = || {
+            calls.set(calls.get() + 1);
+            8u16
+        };
+        env::set_var("OR_ELSE_WORKERS", "2");
+        assert_eq!(biodome_or_else("OR_ELSE_WORKERS", default), 2);
+        assert_eq!(calls.get(), 0);
+        env::remove_var("OR_ELSE_WORKERS");
+        assert_eq!(biodome_or_else("OR_ELSE_WORKERS", default), 8);
+        assert_eq!(calls.get(), 1);
+    }
+
+    #[test]
+    fn long_values() {
+        let cert = format!("-----BEGIN-----{}-----END-----", "A".repeat(8000));
+        env::set_var("LONG_CA_CERT", &cert);
+        assert_eq!(biodome("LONG_CA_CERT", String::new()), cert);
+        let entry = registry()
+            .into_iter()
+            .find(|e| e.key == "LONG_CA_CERT")
+            .unwrap();
+        assert!(entry.hashed);
+        assert_eq!(
+            entry.value,
+            Some("[8028 bytes, hash 2cd396eca502fc7b]".to_string())
+        );
+        assert!(!crash_context().contains("AAAA"));
+        let script = export::resolved(export::Format::Sh);
+        assert!(!script.contains("AAAA"));
+        assert!(!script.contains("LONG_CA_CERT='"));
+        assert!(script.contains("# LONG_CA_CERT is too long and was not exported\n"));
+        env::remove_var("LONG_CA_CERT");
+    }
+
+    #[test]
+    fn ranges() {
+        assert_eq!(biodome_in_range("RANGE_THREADS", 8u8, 1..=64), Ok(8));
+        env::set_var("RANGE_THREADS", "0");
+        assert_eq!(
+            biodome_in_range("RANGE_THREADS", 8u8, 1..=64),
+            Err(BiodomeError::Invalid {
+                key: "RANGE_THREADS".to_string(),
+                reason: "0 is not between 1 and 64".to_string()
+            })
+        );
+        assert_eq!(biodome_clamped("RANGE_THREADS", 8u8, 1..=64), Ok(1));
+        assert!(lints().contains(&Lint {
+            key: "RANGE_THREADS".to_string(),
+            message: "0 is not between 1 and 64, using 1".to_string()
+        }));
+        env::set_var("RANGE_THREADS", "2.5");
+        assert_eq!(biodome_clamped("RANGE_THREADS", 1.0, 0.0..=1.0), Ok(1.0));
+        env::remove_var("RANGE_THREADS");
+    }
+
+    #[test]
+    fn doctor_types() {
+        env::set_var("DOCTOR_NAME", "8080");
+        env::set_var("DOCTOR_RETRIES", "\"3\"");
+        env::set_var("DOCTOR_VERBOSE", "'true'");
+        env::set_var("DOCTOR_API_TOKEN", "12345678901234567890");
+        assert_eq!(biodome("DOCTOR_NAME", "app"), "8080");
+        assert!(biodome_or_error("DOCTOR_RETRIES", 5u8).is_err());
+        assert!(!biodome("DOCTOR_VERBOSE", true));
+        let _ = biodome("DOCTOR_API_TOKEN", "");
+
+        let found: Vec<(String, String)> = doctor()
+            .into_iter()
+            .filter(|l| l.key.starts_with("DOCTOR_"))
+            .map(|l| (l.key, l.message))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (
+                    "DOCTOR_RETRIES".to_string(),
+                    "value is in quotes, but u8 values are written without them".to_string()
+                ),
+                (
+                    "DOCTOR_VERBOSE".to_string(),
+                    "value is in quotes, but bool values are written without them".to_string()
+                ),
+                (
+                    "DOCTOR_NAME".to_string(),
+                    "value looks like a number, but the setting is a string".to_string()
+                ),
+            ]
+        );
+        for key in [
+            "DOCTOR_NAME",
+            "DOCTOR_RETRIES",
+            "DOCTOR_VERBOSE",
+            "DOCTOR_API_TOKEN",
+        ] {
+            env::remove_var(key);
+        }
     }
 }
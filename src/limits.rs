@@ -0,0 +1,151 @@
+use std::sync::RwLock;
+
+/// Upper bounds on structured env var values.
+///
+/// These are checked before and during parsing so that a huge or
+/// deeply nested value (injected by accident or on purpose) fails
+/// with an error instead of exhausting memory or the stack at startup.
+///
+/// ```rust
+/// use biodome::{set_limits, Limits};
+///
+/// set_limits(Limits {
+///     max_array_len: 100,
+///     ..Limits::default()
+/// });
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /// Maximum length of a value, in bytes.
+    pub max_value_len: usize,
+    /// Maximum number of elements in an array.
+    pub max_array_len: usize,
+    /// Maximum number of entries in a table.
+    pub max_map_len: usize,
+    /// Maximum nesting depth of arrays and tables.
+    pub max_depth: usize,
+    /// Values longer than this, in bytes, such as embedded
+    /// certificates, are kept in the [registry](crate::registry) and
+    /// in reports only as their length and hash.
+    pub max_recorded_len: usize,
+}
+
+impl Limits {
+    const DEFAULT: Limits = Limits {
+        max_value_len: 64 * 1024,
+        max_array_len: 10_000,
+        max_map_len: 10_000,
+        max_depth: 32,
+        max_recorded_len: 4 * 1024,
+    };
+
+    /// Check the raw text of a value against these limits.
+    pub(crate) fn check_value(&self, s: &str) -> Result<(), &'static str> {
+        if s.len() > self.max_value_len {
+            return Err("value too long");
+        }
+        if nesting_depth(s) > self.max_depth {
+            return Err("value nested too deeply");
+        }
+        Ok(())
+    }
+
+    /// Check the number of elements in every array and table in a
+    /// parsed value against these limits.
+    pub(crate) fn check_sizes(&self, value: &toml::Value) -> Result<(), &'static str> {
+        match value {
+            toml::Value::Array(items) => {
+                if items.len() > self.max_array_len {
+                    return Err("too many array elements");
+                }
+                items.iter().try_for_each(|v| self.check_sizes(v))
+            }
+            toml::Value::Table(table) => {
+                if table.len() > self.max_map_len {
+                    return Err("too many map entries");
+                }
+                table.values().try_for_each(|v| self.check_sizes(v))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits::DEFAULT
+    }
+}
+
+static LIMITS: RwLock<Limits> = RwLock::new(Limits::DEFAULT);
+
+/// Use "limits" for all parsing from now on.
+pub fn set_limits(limits: Limits) {
+    *LIMITS.write().unwrap() = limits;
+}
+
+/// The limits currently in effect.
+pub fn limits() -> Limits {
+    *LIMITS.read().unwrap()
+}
+
+/// The deepest nesting of `[` and `{` in "s", ignoring brackets
+/// inside TOML strings.
+fn nesting_depth(s: &str) -> usize {
+    let mut depth = 0usize;
+    let mut max = 0;
+    let mut quote = None;
+    let mut escaped = false;
+    for c in s.chars() {
+        match quote {
+            Some(q) => {
+                if escaped {
+                    escaped = false;
+                } else if c == '\\' && q == '"' {
+                    escaped = true;
+                } else if c == q {
+                    quote = None;
+                }
+            }
+            None => match c {
+                '"' | '\'' => quote = Some(c),
+                '[' | '{' => {
+                    depth += 1;
+                    max = max.max(depth);
+                }
+                ']' | '}' => depth = depth.saturating_sub(1),
+                _ => {}
+            },
+        }
+    }
+    max
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn depth() {
+        assert_eq!(nesting_depth("1"), 0);
+        assert_eq!(nesting_depth("[1, 2]"), 1);
+        assert_eq!(nesting_depth("{ a = [1, [2]] }"), 3);
+        assert_eq!(nesting_depth(r#"["[[[", '{{{']"#), 1);
+        assert_eq!(nesting_depth(r#"["\"[[", 1]"#), 1);
+    }
+
+    #[test]
+    fn check() {
+        let limits = Limits {
+            max_value_len: 10,
+            max_depth: 2,
+            ..Limits::default()
+        };
+        assert!(limits.check_value("[[1], [2]]").is_ok());
+        assert_eq!(limits.check_value("[1, 2, 3, 4]"), Err("value too long"));
+        assert_eq!(
+            limits.check_value("[[[1]]]"),
+            Err("value nested too deeply")
+        );
+    }
+}
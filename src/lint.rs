@@ -0,0 +1,199 @@
+use std::sync::Mutex;
+
+use crate::registry;
+
+/// A likely mistake in the configuration, found while reading it.
+///
+/// Lints never stop a value from being used; they are collected so
+/// that an application can log them at startup, see [`lints`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lint {
+    pub key: String,
+    pub message: String,
+}
+
+static LINTS: Mutex<Vec<Lint>> = Mutex::new(Vec::new());
+
+/// All lints found so far, in the order they were found.
+///
+/// ```rust
+/// use biodome::{biodome, lints, Secret};
+///
+/// std::env::set_var("DB_PASSWORD", "changeme");
+/// let DB_PASSWORD = biodome("DB_PASSWORD", Secret::new(String::new()));
+/// for lint in lints() {
+///     eprintln!("warning: {}: {}", lint.key, lint.message);
+/// }
+/// ```
+pub fn lints() -> Vec<Lint> {
+    LINTS.lock().unwrap().clone()
+}
+
+/// All lints found so far, like [`lints`], and also checks that are
+/// too likely to be wrong to run on every read, meant for a
+/// diagnostic command or a startup self-check.
+///
+/// The extra checks look for type mistakes: a `String` setting whose
+/// value from the environment is a number or a bool may have been
+/// declared with the wrong type. Sensitive values are not checked.
+///
+/// ```rust
+/// std::env::set_var("WORKER_COUNT", "8");
+/// let WORKER_COUNT = biodome::biodome("WORKER_COUNT", "4");
+/// for lint in biodome::doctor() {
+///     eprintln!("warning: {}: {}", lint.key, lint.message);
+/// }
+/// ```
+pub fn doctor() -> Vec<Lint> {
+    let mut found = lints();
+    for entry in registry::registry() {
+        if !entry.text || entry.hashed || entry.redacted() {
+            continue;
+        }
+        let Some(message) = entry.value.as_deref().and_then(looks_typed) else {
+            continue;
+        };
+        let lint = Lint {
+            key: entry.key,
+            message,
+        };
+        if !found.contains(&lint) {
+            found.push(lint);
+        }
+    }
+    found
+}
+
+/// Describe what "value", meant as a string, looks like instead, if
+/// it looks like a number or a bool.
+fn looks_typed(value: &str) -> Option<String> {
+    let value = value.trim();
+    if value.parse::<f64>().is_ok() && value.chars().any(|c| c.is_ascii_digit()) {
+        Some("value looks like a number, but the setting is a string".to_string())
+    } else if value.eq_ignore_ascii_case("true") || value.eq_ignore_ascii_case("false") {
+        Some("value looks like a bool, but the setting is a string".to_string())
+    } else {
+        None
+    }
+}
+
+/// Describe the mistake, if "value" for a setting of "type_name" is a
+/// number or a bool in quotes, which the type doesn't accept.
+pub(crate) fn quoted_scalar(type_name: &str, value: &str) -> Option<String> {
+    const NUMBERS: [&str; 14] = [
+        "i8", "i16", "i32", "i64", "i128", "isize", "u8", "u16", "u32", "u64", "u128", "usize",
+        "f32", "f64",
+    ];
+    let name = type_name.rsplit("::").next().unwrap_or(type_name);
+    let numeric = NUMBERS.contains(&name) || name.starts_with("NonZero");
+    if !numeric && name != "bool" {
+        return None;
+    }
+    let value = value.trim();
+    let inner = ['"', '\'']
+        .iter()
+        .find_map(|&q| value.strip_prefix(q)?.strip_suffix(q))?;
+    looks_typed(inner)?;
+    Some(format!(
+        "value is in quotes, but {} values are written without them",
+        name
+    ))
+}
+
+pub(crate) fn record(key: &str, message: String) {
+    let lint = Lint {
+        key: key.to_string(),
+        message,
+    };
+    let mut lints = LINTS.lock().unwrap();
+    if !lints.contains(&lint) {
+        lints.push(lint);
+    }
+}
+
+/// Describe why "value" would make a poor secret, if it would.
+pub(crate) fn weak_secret(value: &[u8]) -> Option<String> {
+    const PLACEHOLDERS: [&str; 14] = [
+        "changeme",
+        "change_me",
+        "change-me",
+        "password",
+        "passw0rd",
+        "secret",
+        "admin",
+        "default",
+        "example",
+        "letmein",
+        "qwerty",
+        "123456",
+        "12345678",
+        "todo",
+    ];
+    const MIN_LEN: usize = 8;
+    const MIN_ENTROPY_BITS: f64 = 32.0;
+
+    let text = String::from_utf8_lossy(value).trim().to_lowercase();
+    if PLACEHOLDERS.contains(&text.as_str()) {
+        return Some("secret looks like a placeholder value".to_string());
+    }
+    if value.len() < MIN_LEN {
+        return Some(format!("secret is shorter than {} characters", MIN_LEN));
+    }
+    if entropy_bits(value) < MIN_ENTROPY_BITS {
+        return Some("secret has very low entropy".to_string());
+    }
+    None
+}
+
+/// Shannon entropy of the byte distribution, times the length.
+fn entropy_bits(value: &[u8]) -> f64 {
+    let mut counts = [0usize; 256];
+    for &b in value {
+        counts[b as usize] += 1;
+    }
+    let len = value.len() as f64;
+    let per_byte: f64 = counts
+        .iter()
+        .filter(|&&n| n > 0)
+        .map(|&n| {
+            let p = n as f64 / len;
+            -p * p.log2()
+        })
+        .sum();
+    per_byte * len
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn weak() {
+        assert!(weak_secret(b"changeme").is_some());
+        assert!(weak_secret(b"Password").is_some());
+        assert!(weak_secret(b"").is_some());
+        assert!(weak_secret(b"abc12").is_some());
+        assert!(weak_secret(b"aaaaaaaaaaaaaaaaaaaa").is_some());
+        assert!(weak_secret(b"9f86d081884c7d659a2feaa0c55ad015").is_none());
+    }
+
+    #[test]
+    fn typed() {
+        assert!(looks_typed(" 8080 ").is_some());
+        assert!(looks_typed("1.5e3").is_some());
+        assert!(looks_typed("TRUE").is_some());
+        assert!(looks_typed("inf").is_none());
+        assert!(looks_typed("yes").is_none());
+        assert!(looks_typed("localhost").is_none());
+
+        assert_eq!(
+            quoted_scalar("u16", "\"8080\""),
+            Some("value is in quotes, but u16 values are written without them".to_string())
+        );
+        assert!(quoted_scalar("bool", "'true'").is_some());
+        assert!(quoted_scalar("core::num::NonZeroU8", "'1'").is_some());
+        assert!(quoted_scalar("u16", "8080").is_none());
+        assert!(quoted_scalar("u16", "\"80'").is_none());
+        assert!(quoted_scalar("alloc::string::String", "\"8080\"").is_none());
+    }
+}
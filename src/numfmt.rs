@@ -0,0 +1,139 @@
+use std::fmt::Display;
+use std::sync::RwLock;
+
+/// How numbers are written when they are exported, and read back.
+///
+/// Integers and floats are written with [`to_env`](crate::TryFromEnv::to_env)
+/// and [`TryIntoEnv`](crate::TryIntoEnv), which is how defaults show
+/// up in the registry and in generated files. Setting a format makes
+/// those files follow an organization's conventions, and diff cleanly
+/// between runs. Values in that format also parse, so they round-trip.
+///
+/// ```rust
+/// use biodome::{set_number_format, NumberFormat, TryIntoEnv};
+///
+/// set_number_format(NumberFormat {
+///     grouping: Some('_'),
+///     precision: Some(2),
+///     ..NumberFormat::default()
+/// });
+/// assert_eq!(1500000u32.try_into_env(), Ok("1_500_000".to_string()));
+/// assert_eq!(0.5f64.try_into_env(), Ok("0.50".to_string()));
+///
+/// std::env::set_var("NUMFMT_LIMIT", "2_000_000");
+/// assert_eq!(biodome::biodome("NUMFMT_LIMIT", 0u32), 2000000);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NumberFormat {
+    /// The character between the whole and the fractional part of a
+    /// float.
+    pub decimal: char,
+    /// The character between groups of three digits, if any.
+    pub grouping: Option<char>,
+    /// The number of digits after the decimal separator of a float.
+    /// `None` writes as many as it takes to read the value back
+    /// exactly.
+    pub precision: Option<usize>,
+}
+
+impl NumberFormat {
+    const DEFAULT: NumberFormat = NumberFormat {
+        decimal: '.',
+        grouping: None,
+        precision: None,
+    };
+
+    /// Write an integer in this format.
+    pub(crate) fn int<T: Display>(&self, value: T) -> String {
+        self.apply(&value.to_string())
+    }
+
+    /// Write a float in this format.
+    pub(crate) fn float<T: Display>(&self, value: T) -> String {
+        match self.precision {
+            Some(p) => self.apply(&format!("{:.*}", p, value)),
+            None => self.apply(&value.to_string()),
+        }
+    }
+
+    /// "number", as Rust writes it, with the separators of this format.
+    fn apply(&self, number: &str) -> String {
+        let (sign, digits) = match number.strip_prefix('-') {
+            Some(rest) => ("-", rest),
+            None => ("", number),
+        };
+        let (whole, fraction) = match digits.split_once('.') {
+            Some((whole, fraction)) => (whole, Some(fraction)),
+            None => (digits, None),
+        };
+        let mut out = sign.to_string();
+        match self.grouping {
+            Some(sep) if whole.bytes().all(|b| b.is_ascii_digit()) => {
+                for (i, c) in whole.chars().enumerate() {
+                    if i > 0 && (whole.len() - i) % 3 == 0 {
+                        out.push(sep);
+                    }
+                    out.push(c);
+                }
+            }
+            _ => out += whole,
+        }
+        if let Some(fraction) = fraction {
+            out.push(self.decimal);
+            out += fraction;
+        }
+        out
+    }
+
+    /// "value" with the separators of this format replaced by the ones
+    /// that Rust parses.
+    pub(crate) fn normalize(&self, value: &str) -> String {
+        value
+            .chars()
+            .filter(|&c| Some(c) != self.grouping)
+            .map(|c| if c == self.decimal { '.' } else { c })
+            .collect()
+    }
+}
+
+impl Default for NumberFormat {
+    fn default() -> Self {
+        NumberFormat::DEFAULT
+    }
+}
+
+static NUMBER_FORMAT: RwLock<NumberFormat> = RwLock::new(NumberFormat::DEFAULT);
+
+/// Use "format" for writing and reading numbers from now on.
+pub fn set_number_format(format: NumberFormat) {
+    *NUMBER_FORMAT.write().unwrap() = format;
+}
+
+/// The number format currently in effect.
+pub fn number_format() -> NumberFormat {
+    *NUMBER_FORMAT.read().unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats() {
+        let plain = NumberFormat::default();
+        assert_eq!(plain.int(-1234567), "-1234567");
+        assert_eq!(plain.float(0.1f32), "0.1");
+
+        let european = NumberFormat {
+            decimal: ',',
+            grouping: Some('.'),
+            precision: Some(3),
+        };
+        assert_eq!(european.int(999), "999");
+        assert_eq!(european.int(-1234567), "-1.234.567");
+        assert_eq!(european.float(1234.5), "1.234,500");
+        assert_eq!(european.float(f64::INFINITY), "inf");
+        assert_eq!(european.normalize("-1.234,500"), "-1234.500");
+        assert_eq!(plain.normalize("1234.5"), "1234.5");
+    }
+}
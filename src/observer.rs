@@ -0,0 +1,56 @@
+use std::sync::RwLock;
+
+use crate::BiodomeError;
+
+/// Callbacks for every env var read, so that an application can count
+/// reads in its own metrics, or keep an audit log of config access.
+///
+/// All methods do nothing by default; implement the ones you need.
+/// Values are not passed, since they may be sensitive.
+///
+/// ```rust
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+/// use biodome::{biodome, set_observer, BiodomeObserver};
+///
+/// static DEFAULTS_USED: AtomicUsize = AtomicUsize::new(0);
+///
+/// struct Metrics;
+///
+/// impl BiodomeObserver for Metrics {
+///     fn on_default_used(&self, key: &str) {
+///         DEFAULTS_USED.fetch_add(1, Ordering::Relaxed);
+///     }
+/// }
+///
+/// set_observer(Metrics);
+/// let RETRIES = biodome("RETRIES", 3);
+/// assert_eq!(DEFAULTS_USED.load(Ordering::Relaxed), 1);
+/// ```
+pub trait BiodomeObserver: Send + Sync {
+    /// "key" was set, and its value was used.
+    fn on_read(&self, _key: &str) {}
+
+    /// "key" was not set, so the default was used.
+    fn on_default_used(&self, _key: &str) {}
+
+    /// The value of "key" could not be parsed.
+    fn on_parse_error(&self, _key: &str, _error: &BiodomeError) {}
+}
+
+static OBSERVER: RwLock<Option<Box<dyn BiodomeObserver>>> = RwLock::new(None);
+
+/// Send every read from now on to "observer".
+pub fn set_observer(observer: impl BiodomeObserver + 'static) {
+    *OBSERVER.write().unwrap() = Some(Box::new(observer));
+}
+
+/// Stop sending reads to the observer.
+pub fn reset_observer() {
+    *OBSERVER.write().unwrap() = None;
+}
+
+pub(crate) fn notify(f: impl FnOnce(&dyn BiodomeObserver)) {
+    if let Some(observer) = OBSERVER.read().unwrap().as_ref() {
+        f(observer.as_ref());
+    }
+}
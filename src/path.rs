@@ -0,0 +1,77 @@
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+
+use crate::{rawconv, FromTomlValue, TryFromEnv, TryIntoEnv};
+
+/// A path whose `$VAR` and `${VAR}` references are expanded to the
+/// values of those env vars, as well as a leading `~`. On Windows,
+/// `%VAR%` references are expanded too.
+///
+/// A plain `PathBuf` only has its `~` expanded, so that paths which
+/// contain a `$`, like `C:\$Recycle.Bin`, are read as they are.
+///
+/// ```rust
+/// use biodome::{biodome, ExpandedPath};
+///
+/// std::env::set_var("EXPANDED_APP", "demo");
+/// std::env::set_var("EXPANDED_DIR", "/srv/$EXPANDED_APP/data");
+/// let dir = biodome("EXPANDED_DIR", ExpandedPath::new("/srv/data"));
+/// assert_eq!(dir.as_path(), std::path::Path::new("/srv/demo/data"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct ExpandedPath(pub PathBuf);
+
+impl ExpandedPath {
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        ExpandedPath(path.into())
+    }
+
+    pub fn as_path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Deref for ExpandedPath {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl From<PathBuf> for ExpandedPath {
+    fn from(path: PathBuf) -> Self {
+        ExpandedPath(path)
+    }
+}
+
+impl From<ExpandedPath> for PathBuf {
+    fn from(path: ExpandedPath) -> PathBuf {
+        path.0
+    }
+}
+
+impl TryFromEnv<Self> for ExpandedPath {
+    type Error = &'static str;
+
+    fn try_from_env(value: &str) -> Result<Self, Self::Error> {
+        Ok(ExpandedPath(rawconv::to_expanded_path(value)))
+    }
+
+    fn to_env(value: &Self) -> Option<String> {
+        value.0.try_into_env().ok()
+    }
+}
+
+impl FromTomlValue for ExpandedPath {
+    fn from_toml(value: &toml::Value) -> Result<Self, &'static str> {
+        value
+            .as_str()
+            .map(|s| ExpandedPath(rawconv::to_expanded_path(s)))
+            .ok_or("expected a string")
+    }
+
+    fn to_toml(&self) -> Option<toml::Value> {
+        self.0.to_toml()
+    }
+}
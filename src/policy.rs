@@ -0,0 +1,61 @@
+use std::collections::BTreeMap;
+use std::sync::RwLock;
+
+use crate::{profile, BiodomeError};
+
+static POLICY: RwLock<BTreeMap<String, Vec<String>>> = RwLock::new(BTreeMap::new());
+
+/// Only allow sources of the given "kinds" to be installed while
+/// "profile" is active, so that conveniences for local development
+/// can't leak into production.
+///
+/// The kinds are what [`EnvSource::kinds`](crate::EnvSource::kinds)
+/// returns: `"env"` for the process environment, `"dotenv"` for `.env`
+/// files, `"config-dir"` for a [`ConfigDir`](crate::ConfigDir),
+/// `"memory"` for a map of overrides, `"snapshot"` for the last known
+/// good snapshot, and `"custom"` for sources that don't say. A source
+/// with a kind that isn't allowed is refused by
+/// [`set_source`](crate::set_source) and [`Layers`](crate::Layers),
+/// and by [`dotenv::load`](crate::dotenv::load) with an error.
+/// Profiles without a policy allow everything.
+///
+/// ```rust
+/// use biodome::{allow_sources, set_profile, Layers, StdEnv};
+///
+/// allow_sources("prod", &["env", "vault"]);
+/// set_profile("prod");
+/// assert!(Layers::new().try_with(StdEnv).is_ok());
+///
+/// let overrides = std::collections::HashMap::<String, String>::new();
+/// let err = Layers::new().try_with(overrides).err().unwrap();
+/// assert_eq!(err.to_string(), "Source memory is not allowed in profile prod");
+/// # biodome::reset_profile();
+/// # biodome::reset_source_policy();
+/// ```
+pub fn allow_sources(profile: &str, kinds: &[&str]) {
+    let kinds = kinds.iter().map(|k| k.to_string()).collect();
+    POLICY.write().unwrap().insert(profile.to_string(), kinds);
+}
+
+/// Remove the policies of all profiles, so any source is allowed.
+pub fn reset_source_policy() {
+    POLICY.write().unwrap().clear();
+}
+
+/// Whether sources of all "kinds" are allowed in the active profile.
+pub(crate) fn check(kinds: &[&str]) -> Result<(), BiodomeError> {
+    let Some(profile) = profile() else {
+        return Ok(());
+    };
+    let policy = POLICY.read().unwrap();
+    let Some(allowed) = policy.get(&profile) else {
+        return Ok(());
+    };
+    match kinds.iter().find(|k| !allowed.iter().any(|a| a == *k)) {
+        Some(kind) => Err(BiodomeError::Forbidden {
+            kind: kind.to_string(),
+            profile,
+        }),
+        None => Ok(()),
+    }
+}
@@ -0,0 +1,120 @@
+use std::sync::RwLock;
+
+use crate::source;
+
+static PROFILE: RwLock<Option<String>> = RwLock::new(None);
+static PROFILES: RwLock<Vec<String>> = RwLock::new(Vec::new());
+
+/// Make "name" the active profile, for values that have a different
+/// setting per profile, like `TIMEOUT=10;prod:30`.
+///
+/// Without a call to this function, the profile is taken from the
+/// `BIODOME_PROFILE` env var, if it is set.
+///
+/// ```rust
+/// use biodome::{biodome, set_profile, set_profiles};
+///
+/// std::env::set_var("TIMEOUT", "10;staging:20;prod:30");
+/// set_profiles(&["dev", "staging", "prod"]);
+/// set_profile("prod");
+/// assert_eq!(biodome("TIMEOUT", 5), 30);
+/// set_profile("dev");
+/// assert_eq!(biodome("TIMEOUT", 5), 10);
+/// ```
+///
+/// A value is only split up like this when a profile is active and
+/// each part after the first `;` starts with the name of a known
+/// profile and a `:`. The first part applies to all other profiles.
+/// Any other value, like `host1:26379;host2:26379`, is used as it is.
+pub fn set_profile(name: &str) {
+    *PROFILE.write().unwrap() = Some(name.to_string());
+}
+
+/// Go back to taking the profile from `BIODOME_PROFILE`.
+pub fn reset_profile() {
+    *PROFILE.write().unwrap() = None;
+}
+
+/// Make "names" the known profiles, the ones that values can have a
+/// part for. The active profile is always known.
+pub fn set_profiles(names: &[&str]) {
+    *PROFILES.write().unwrap() = names.iter().map(|n| n.to_string()).collect();
+}
+
+/// The active profile, if there is one.
+pub fn profile() -> Option<String> {
+    let profile = PROFILE.read().unwrap().clone();
+    profile.or_else(|| source::get("BIODOME_PROFILE"))
+}
+
+/// The part of "raw" for the active profile. When no part is for it,
+/// and there is no part for all other profiles, that's "raw" itself.
+pub(crate) fn select(raw: String) -> String {
+    let Some(profile) = profile() else {
+        return raw;
+    };
+    let known = |name: &str| name == profile || PROFILES.read().unwrap().iter().any(|p| p == name);
+    let Some(parts) = split(&raw, known) else {
+        return raw;
+    };
+    let mut fallback = None;
+    for (name, value) in parts {
+        match name {
+            Some(name) if name == profile => return value.to_string(),
+            Some(_) => {}
+            None => fallback = Some(value),
+        }
+    }
+    fallback.map(str::to_string).unwrap_or(raw)
+}
+
+type Part<'a> = (Option<&'a str>, &'a str);
+
+/// Split "raw" into its per-profile parts, or `None` if it isn't
+/// written that way, with "known" telling which names are profiles.
+fn split(raw: &str, known: impl Fn(&str) -> bool) -> Option<Vec<Part<'_>>> {
+    if !raw.contains(';') {
+        return None;
+    }
+    let mut parts = vec![];
+    for (i, part) in raw.split(';').enumerate() {
+        match scoped(part).filter(|(name, _)| known(name)) {
+            Some((name, value)) => parts.push((Some(name), value)),
+            None if i == 0 => parts.push((None, part)),
+            None => return None,
+        }
+    }
+    Some(parts)
+}
+
+fn scoped(part: &str) -> Option<(&str, &str)> {
+    let (name, value) = part.split_once(':')?;
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    valid.then_some((name, value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parts() {
+        let known = |name: &str| ["dev", "prod"].contains(&name);
+        assert_eq!(split("10", known), None);
+        assert_eq!(
+            split("10;prod:30", known),
+            Some(vec![(None, "10"), (Some("prod"), "30")])
+        );
+        assert_eq!(
+            split("dev:1;prod:2", known),
+            Some(vec![(Some("dev"), "1"), (Some("prod"), "2")])
+        );
+        assert_eq!(split("Server=db;Database=app", known), None);
+        assert_eq!(split("a;b c:d", known), None);
+        assert_eq!(split("host1:26379;host2:26379", known), None);
+        assert_eq!(split("a;X-Foo:1", known), None);
+    }
+}
@@ -1,9 +1,14 @@
-use std::collections::HashMap;
-use std::fmt::Debug;
+use std::path::PathBuf;
 use std::str::FromStr;
+use std::time::Duration;
 use std::vec::Vec;
 use toml::value::Datetime;
 
+use crate::interpolate;
+use crate::limits::{self, Limits};
+use crate::source;
+use crate::tomlconv::{self, FromTomlValue};
+
 pub fn to_prim<T: FromStr>(s: &str) -> Result<T, &'static str> {
     s.parse().map_err(|_err| "parse error")
 }
@@ -16,37 +21,341 @@ pub fn to_bool(s: &str) -> bool {
     TRUTHY_VALUES.iter().any(|&v| v == cleaned)
 }
 
-pub fn to_vec<T: FromStr>(s: &str) -> Result<Vec<T>, &'static str>
-where
-    <T as FromStr>::Err: Debug,
-{
-    let s = format!("x = {}", s);
-    let out = s.parse::<toml::Value>().unwrap();
-    let out = out["x"].as_array().unwrap();
-    let out = out
+/// Parse "s" as a single TOML value, within "limits". Values that
+/// aren't TOML can be in one of the other syntaxes that are enabled.
+fn to_toml_limited(s: &str, limits: &Limits) -> Result<toml::Value, &'static str> {
+    limits.check_value(s)?;
+    let doc = format!("x = {}", s).parse::<toml::Value>();
+    let value = match doc.ok().and_then(|doc| doc.get("x").cloned()) {
+        Some(value) => value,
+        None => from_other_syntax(s)?,
+    };
+    limits.check_sizes(&value)?;
+    Ok(value)
+}
+
+/// Parse "s" as JSON, like `{"a": 1}`, with the `json` feature, as
+/// RON, like `(host: "db", port: 5432)`, with the `ron` feature, or as
+/// a YAML flow collection, like `{a: 1}` or `[a, b]`, with the `yaml`
+/// feature.
+#[cfg_attr(
+    not(any(feature = "json", feature = "ron", feature = "yaml")),
+    allow(unused_variables)
+)]
+fn from_other_syntax(s: &str) -> Result<toml::Value, &'static str> {
+    #[cfg(feature = "json")]
+    if let Ok(value) = serde_json::from_str(s) {
+        return from_json(value);
+    }
+    // Plain words are RON units and YAML strings, so only collections
+    // are tried. RON goes first since YAML reads `(a` as a string.
+    #[cfg(any(feature = "ron", feature = "yaml"))]
+    let collection = s.trim_start().starts_with(['{', '[', '(']);
+    #[cfg(feature = "ron")]
+    if collection {
+        if let Ok(Ok(value)) = ron::from_str(s).map(from_ron) {
+            return Ok(value);
+        }
+    }
+    #[cfg(feature = "yaml")]
+    if collection {
+        if let Ok(value) = serde_yaml::from_str(s) {
+            return from_yaml(value);
+        }
+    }
+    Err("parse error")
+}
+
+/// "value" as the TOML value it would be written as. TOML has no null.
+#[cfg(feature = "json")]
+fn from_json(value: serde_json::Value) -> Result<toml::Value, &'static str> {
+    use serde_json::Value;
+    Ok(match value {
+        Value::Null => return Err("null is not supported"),
+        Value::Bool(b) => toml::Value::Boolean(b),
+        Value::Number(n) => match n.as_i64() {
+            Some(i) => toml::Value::Integer(i),
+            None => toml::Value::Float(n.as_f64().ok_or("number out of range")?),
+        },
+        Value::String(s) => toml::Value::String(s),
+        Value::Array(items) => {
+            toml::Value::Array(items.into_iter().map(from_json).collect::<Result<_, _>>()?)
+        }
+        Value::Object(map) => toml::Value::Table(
+            map.into_iter()
+                .map(|(k, v)| Ok((k, from_json(v)?)))
+                .collect::<Result<_, &'static str>>()?,
+        ),
+    })
+}
+
+/// "value" as the TOML value it would be written as, or an error if
+/// TOML can't hold it. `Some` is left out, and named tuples and structs
+/// lose their names.
+#[cfg(feature = "ron")]
+fn from_ron(value: ron::Value) -> Result<toml::Value, &'static str> {
+    use ron::Value;
+    Ok(match value {
+        Value::Unit | Value::Option(None) => return Err("unit is not supported"),
+        Value::Option(Some(value)) => from_ron(*value)?,
+        Value::Bool(b) => toml::Value::Boolean(b),
+        Value::Char(c) => toml::Value::String(c.to_string()),
+        Value::Number(n) => match n.as_i64() {
+            Some(i) => toml::Value::Integer(i),
+            None => toml::Value::Float(n.as_f64().ok_or("number out of range")?),
+        },
+        Value::String(s) => toml::Value::String(s),
+        Value::Seq(items) => {
+            toml::Value::Array(items.into_iter().map(from_ron).collect::<Result<_, _>>()?)
+        }
+        Value::Map(map) => toml::Value::Table(
+            map.into_iter()
+                .map(|(k, v)| match k {
+                    Value::String(k) => Ok((k, from_ron(v)?)),
+                    _ => Err("keys must be strings"),
+                })
+                .collect::<Result<_, &'static str>>()?,
+        ),
+    })
+}
+
+/// "value" as the TOML value it would be written as. TOML has no null,
+/// and keys must be strings.
+#[cfg(feature = "yaml")]
+fn from_yaml(value: serde_yaml::Value) -> Result<toml::Value, &'static str> {
+    use serde_yaml::Value;
+    Ok(match value {
+        Value::Null => return Err("null is not supported"),
+        Value::Bool(b) => toml::Value::Boolean(b),
+        Value::Number(n) => match n.as_i64() {
+            Some(i) => toml::Value::Integer(i),
+            None => toml::Value::Float(n.as_f64().ok_or("number out of range")?),
+        },
+        Value::String(s) => toml::Value::String(s),
+        Value::Sequence(items) => {
+            toml::Value::Array(items.into_iter().map(from_yaml).collect::<Result<_, _>>()?)
+        }
+        Value::Mapping(map) => toml::Value::Table(
+            map.into_iter()
+                .map(|(k, v)| match k {
+                    Value::String(k) => Ok((k, from_yaml(v)?)),
+                    _ => Err("keys must be strings"),
+                })
+                .collect::<Result<_, &'static str>>()?,
+        ),
+        Value::Tagged(tagged) => from_yaml(tagged.value)?,
+    })
+}
+
+pub fn to_vec<T: FromTomlValue>(s: &str) -> Result<Vec<T>, &'static str> {
+    to_vec_limited(s, &limits::limits())
+}
+
+fn to_vec_limited<T: FromTomlValue>(s: &str, limits: &Limits) -> Result<Vec<T>, &'static str> {
+    let items = match to_list_limited(s, limits)? {
+        List::Array(value) => return Vec::from_toml(&value),
+        List::Items(items) => items,
+    };
+    // Each item is read as a TOML value if it is one, and as a string
+    // if not; when that doesn't give a list of "T", every item is a
+    // string.
+    let string = |item: &&str| toml::Value::String(item.to_string());
+    let typed = toml::Value::Array(
+        items
+            .iter()
+            .map(|item| to_toml_limited(item, limits).unwrap_or_else(|_| string(item)))
+            .collect(),
+    );
+    limits.check_sizes(&typed)?;
+    Vec::from_toml(&typed)
+        .or_else(|_| Vec::from_toml(&toml::Value::Array(items.iter().map(string).collect())))
+}
+
+/// A list in an env var: a TOML array, or the items of a list written
+/// without brackets, like `8081,8082,8083`.
+pub(crate) enum List<'a> {
+    Array(toml::Value),
+    Items(Vec<&'a str>),
+}
+
+/// Read "s" as a list. A value that isn't a TOML array is split on
+/// commas, unless it starts with `[`, which makes it a broken array.
+#[cfg(feature = "serde")]
+pub(crate) fn to_list(s: &str) -> Result<List<'_>, &'static str> {
+    to_list_limited(s, &limits::limits())
+}
+
+fn to_list_limited<'a>(s: &'a str, limits: &Limits) -> Result<List<'a>, &'static str> {
+    limits.check_value(s)?;
+    match to_toml_limited(s, limits) {
+        Ok(value @ toml::Value::Array(_)) => Ok(List::Array(value)),
+        Err(err) if s.trim_start().starts_with('[') => Err(err),
+        _ if s.trim().is_empty() => Ok(List::Items(vec![])),
+        _ => {
+            let items: Vec<&str> = s.split(',').map(str::trim).collect();
+            if items.len() > limits.max_array_len {
+                return Err("too many array elements");
+            }
+            Ok(List::Items(items))
+        }
+    }
+}
+
+/// Parse any single TOML value, such as an array read into a tuple.
+pub fn to_value<T: FromTomlValue>(s: &str) -> Result<T, &'static str> {
+    T::from_toml(&to_toml_limited(s, &limits::limits())?)
+}
+
+/// Parse "s" without knowing its type, for other config libraries:
+/// as TOML if it parses, and as a string if not. Words like `on` stay
+/// strings, so that string fields keep them, and the library reading
+/// the value decides whether it is a bool.
+#[cfg(any(feature = "figment", feature = "config"))]
+pub fn to_untyped(s: &str) -> toml::Value {
+    to_value::<toml::Value>(s).unwrap_or_else(|_| toml::Value::String(s.to_string()))
+}
+
+/// Parse a TOML inline table into any map with `String` keys.
+pub fn to_map<T: FromTomlValue, M: FromIterator<(String, T)>>(s: &str) -> Result<M, &'static str> {
+    to_map_limited(s, &limits::limits())
+}
+
+fn to_map_limited<T: FromTomlValue, M: FromIterator<(String, T)>>(
+    s: &str,
+    limits: &Limits,
+) -> Result<M, &'static str> {
+    tomlconv::from_table(&to_toml_limited(s, limits)?)
+}
+
+/// Parse a byte size like `512`, `10MB` or `8MiB` into a number of bytes.
+pub fn to_bytes(s: &str) -> Result<u64, &'static str> {
+    let s = s.trim();
+    let split = s
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+    let multiplier: u64 = match unit.trim().to_lowercase().as_str() {
+        "" | "b" => 1,
+        "kb" => 1_000,
+        "mb" => 1_000_000,
+        "gb" => 1_000_000_000,
+        "tb" => 1_000_000_000_000,
+        "pb" => 1_000_000_000_000_000,
+        "kib" => 1 << 10,
+        "mib" => 1 << 20,
+        "gib" => 1 << 30,
+        "tib" => 1 << 40,
+        "pib" => 1 << 50,
+        _ => return Err("unknown byte size unit"),
+    };
+    if let Ok(n) = number.parse::<u64>() {
+        return n.checked_mul(multiplier).ok_or("byte size too large");
+    }
+    let n: f64 = number.parse().map_err(|_err| "parse error")?;
+    let bytes = n * multiplier as f64;
+    if !bytes.is_finite() || bytes >= u64::MAX as f64 {
+        return Err("byte size too large");
+    }
+    Ok(bytes as u64)
+}
+
+/// Parse a duration like `500ms`, `2s`, `1.5m` or `1h`. A bare number
+/// is a number of seconds.
+pub fn to_duration(s: &str) -> Result<Duration, &'static str> {
+    let s = s.trim();
+    let split = s
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+    let seconds: f64 = match unit.trim() {
+        "ns" => 1e-9,
+        "us" | "µs" => 1e-6,
+        "ms" => 1e-3,
+        "" | "s" => 1.0,
+        "m" => 60.0,
+        "h" => 3600.0,
+        "d" => 86400.0,
+        _ => return Err("unknown duration unit"),
+    };
+    let n: f64 = number.parse().map_err(|_err| "parse error")?;
+    Duration::try_from_secs_f64(n * seconds).map_err(|_err| "duration too large")
+}
+
+/// Write "d" with the largest unit that it is a whole number of, so
+/// that [`to_duration`] reads it back unchanged.
+pub fn from_duration(d: Duration) -> String {
+    const UNITS: [(&str, u128); 6] = [
+        ("h", 3_600_000_000_000),
+        ("m", 60_000_000_000),
+        ("s", 1_000_000_000),
+        ("ms", 1_000_000),
+        ("us", 1_000),
+        ("ns", 1),
+    ];
+    let nanos = d.as_nanos();
+    if nanos == 0 {
+        return "0s".to_string();
+    }
+    let (unit, size) = UNITS
         .iter()
-        // .map(|v| v.as_integer().unwrap())
-        .map(|v| v.to_string().parse().unwrap())
-        .collect();
+        .find(|(_, size)| nanos.is_multiple_of(*size))
+        .unwrap_or(&UNITS[5]);
+    format!("{}{}", nanos / size, unit)
+}
+
+/// Convert to a path, expanding a leading `~` to the home directory.
+pub fn to_path(s: &str) -> PathBuf {
+    let home = source::get("HOME").or_else(|| source::get("USERPROFILE"));
+    PathBuf::from(interpolate::expand_tilde(s, home))
+}
 
-    Ok(out)
+/// Like [`to_path`], but also expanding `$VAR`/`${VAR}` references to
+/// the values of other env vars.
+pub fn to_expanded_path(s: &str) -> PathBuf {
+    let path = to_path(s);
+    let s = path.to_string_lossy();
+    PathBuf::from(interpolate::expand_vars(&s, source::get))
 }
 
-pub fn to_hashmap<T: FromStr>(s: &str) -> Result<HashMap<String, T>, &'static str>
-where
-    <T as FromStr>::Err: Debug,
-{
-    let s = format!("x = {}", s);
-    let out = s.parse::<toml::Value>().unwrap();
-    let out = out["x"]
-        .as_table()
-        .expect(format!("Failed to convert the input string to a hashmap: {}", s).as_str());
-    let out = out
+/// The edit distance between two strings.
+pub fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut prev = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let current = row[j + 1];
+            row[j + 1] = if ca == *cb {
+                prev
+            } else {
+                1 + prev.min(row[j]).min(row[j + 1])
+            };
+            prev = current;
+        }
+    }
+    row[b.len()]
+}
+
+/// The choices closest to `value`, best first. Choices that are too
+/// different from `value` to be a plausible typo are left out.
+pub fn suggest(value: &str, choices: &[&str]) -> Vec<String> {
+    let value = value.to_lowercase();
+    let threshold = (value.chars().count() / 3).max(1);
+    let mut scored: Vec<(usize, &str)> = choices
         .iter()
-        .map(|(k, v)| (k.clone(), v.to_string().parse().unwrap()))
+        .map(|&c| (levenshtein(&value, &c.to_lowercase()), c))
+        .filter(|&(d, _)| d <= threshold)
         .collect();
-
-    Ok(out)
+    scored.sort();
+    match scored.first() {
+        Some(&(best, _)) => scored
+            .iter()
+            .take_while(|&&(d, _)| d == best)
+            .map(|&(_, c)| c.to_string())
+            .collect(),
+        None => vec![],
+    }
 }
 
 pub fn to_datetime(s: &str) -> Result<Datetime, &'static str> {
@@ -56,6 +365,63 @@ pub fn to_datetime(s: &str) -> Result<Datetime, &'static str> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    #[cfg(feature = "json")]
+    fn json() {
+        let levels: HashMap<String, String> =
+            to_map(r#"{"root": "warn", "http": "info"}"#).unwrap();
+        assert_eq!(levels["root"], "warn");
+        let routes: HashMap<String, Vec<u16>> = to_map(r#"{"api": [80, 81], "web": []}"#).unwrap();
+        assert_eq!(routes["api"], [80, 81]);
+        let names: Vec<String> = to_vec(r#"["a","b"]"#).unwrap();
+        assert_eq!(names, ["a", "b"]);
+        assert!(to_map::<String, HashMap<String, String>>(r#"{"a": null}"#).is_err());
+        assert!(to_map::<String, HashMap<String, String>>(r#"{"a": "1""#).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "ron")]
+    fn ron() {
+        let db: HashMap<String, toml::Value> = to_map(r#"(host: "db", port: 5432)"#).unwrap();
+        assert_eq!(db["host"].as_str(), Some("db"));
+        assert_eq!(db["port"].as_integer(), Some(5432));
+        let origin: (String, u16) = to_value(r#"("host", Some(443))"#).unwrap();
+        assert_eq!(origin, ("host".to_string(), 443));
+        let pairs: Vec<(char, f64)> = to_vec("[('a', 1.5), ('b', 2.0)]").unwrap();
+        assert_eq!(pairs, [('a', 1.5), ('b', 2.0)]);
+        assert!(to_value::<(String, u16)>(r#"("host", None)"#).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "yaml")]
+    fn yaml() {
+        let levels: HashMap<String, String> = to_map("{root: warn, http: info}").unwrap();
+        assert_eq!(levels["root"], "warn");
+        let routes: HashMap<String, Vec<u16>> = to_map("{api: [80, 81], web: []}").unwrap();
+        assert_eq!(routes["api"], [80, 81]);
+        let names: Vec<String> = to_vec("[a, b]").unwrap();
+        assert_eq!(names, ["a", "b"]);
+        assert!(to_value::<Vec<String>>("a, b").is_err());
+        assert!(to_map::<String, HashMap<String, String>>("{a: ~}").is_err());
+        assert!(to_map::<String, HashMap<String, String>>("{1: a}").is_err());
+    }
+
+    #[test]
+    #[cfg(any(feature = "figment", feature = "config"))]
+    fn untyped() {
+        use toml::Value;
+        assert_eq!(to_untyped("8080"), Value::Integer(8080));
+        assert_eq!(to_untyped("true"), Value::Boolean(true));
+        assert_eq!(to_untyped("off"), Value::String("off".into()));
+        assert_eq!(to_untyped("localhost"), Value::String("localhost".into()));
+        assert_eq!(to_untyped(r#""1.0""#), Value::String("1.0".into()));
+        assert_eq!(
+            to_untyped("[1, 2]"),
+            Value::Array(vec![Value::Integer(1), Value::Integer(2)])
+        );
+    }
 
     #[test]
     fn prim() -> Result<(), &'static str> {
@@ -75,6 +441,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::bool_assert_comparison)]
     fn boo() -> Result<(), &'static str> {
         let x = to_bool("1");
         assert_eq!(x, true);
@@ -97,9 +464,88 @@ mod tests {
         assert_eq!(x, vec![1_f32, 2_f32, 3_f32]);
         let x: Vec<f64> = to_vec("[1, 2, 3]")?;
         assert_eq!(x, vec![1_f64, 2_f64, 3_f64]);
+        let x: Vec<String> = to_vec(r#"["a.proxy.com:8000", "b.proxy.com:8001"]"#)?;
+        assert_eq!(x, vec!["a.proxy.com:8000", "b.proxy.com:8001"]);
+        Ok(())
+    }
+
+    #[test]
+    fn comma_lists() -> Result<(), &'static str> {
+        let x: Vec<u16> = to_vec("8081,8082, 8083")?;
+        assert_eq!(x, vec![8081, 8082, 8083]);
+        let x: Vec<u16> = to_vec("8081")?;
+        assert_eq!(x, vec![8081]);
+        let x: Vec<String> = to_vec("a.proxy.com:8000,b.proxy.com:8001")?;
+        assert_eq!(x, vec!["a.proxy.com:8000", "b.proxy.com:8001"]);
+        let x: Vec<String> = to_vec("1, two")?;
+        assert_eq!(x, vec!["1", "two"]);
+        let x: Vec<i32> = to_vec("")?;
+        assert!(x.is_empty());
+        assert!(to_vec::<u16>("8081,http").is_err());
+        assert!(to_vec::<u16>("[8081, 8082").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn limited() {
+        let limits = Limits {
+            max_array_len: 3,
+            max_map_len: 1,
+            ..Limits::default()
+        };
+        let x: Result<Vec<i32>, _> = to_vec_limited("[1, 2, 3]", &limits);
+        assert_eq!(x, Ok(vec![1, 2, 3]));
+        let x: Result<Vec<i32>, _> = to_vec_limited("[1, 2, 3, 4]", &limits);
+        assert_eq!(x, Err("too many array elements"));
+        let x: Result<HashMap<String, i32>, _> = to_map_limited("{a = 1, b = 2}", &limits);
+        assert_eq!(x, Err("too many map entries"));
+        let x: Result<Vec<Vec<i32>>, _> = to_vec_limited("[[1], [1, 2, 3, 4]]", &limits);
+        assert_eq!(x, Err("too many array elements"));
+        let x: Result<Vec<i32>, _> = to_vec_limited("[1, 2", &limits);
+        assert_eq!(x, Err("parse error"));
+    }
+
+    #[test]
+    fn bytes() -> Result<(), &'static str> {
+        assert_eq!(to_bytes("512")?, 512);
+        assert_eq!(to_bytes("10MB")?, 10_000_000);
+        assert_eq!(to_bytes("512KiB")?, 512 * 1024);
+        assert_eq!(to_bytes("8 mib")?, 8 * 1024 * 1024);
+        assert_eq!(to_bytes("1.5GiB")?, 3 * 512 * 1024 * 1024);
+        assert!(to_bytes("10XB").is_err());
+        assert!(to_bytes("MB").is_err());
+        assert!(to_bytes("100000PiB").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn durations() -> Result<(), &'static str> {
+        assert_eq!(to_duration("2s")?, Duration::from_secs(2));
+        assert_eq!(to_duration("30")?, Duration::from_secs(30));
+        assert_eq!(to_duration("250ms")?, Duration::from_millis(250));
+        assert_eq!(to_duration("1.5m")?, Duration::from_secs(90));
+        assert_eq!(to_duration(" 1 h")?, Duration::from_secs(3600));
+        assert!(to_duration("2 fortnights").is_err());
+        assert!(to_duration("-1s").is_err());
+        assert!(to_duration("1e300d").is_err());
+        assert_eq!(from_duration(Duration::from_secs(90)), "90s");
+        assert_eq!(from_duration(Duration::from_secs(7200)), "2h");
+        assert_eq!(from_duration(Duration::from_millis(1500)), "1500ms");
+        assert_eq!(from_duration(Duration::ZERO), "0s");
         Ok(())
     }
 
+    #[test]
+    fn distance() {
+        assert_eq!(levenshtein("", ""), 0);
+        assert_eq!(levenshtein("json", "json"), 0);
+        assert_eq!(levenshtein("jsn", "json"), 1);
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+        assert_eq!(suggest("jsn", &["json", "text", "yaml"]), vec!["json"]);
+        assert_eq!(suggest("JSON", &["json", "text"]), vec!["json"]);
+        assert!(suggest("xml", &["json", "text", "yaml"]).is_empty());
+    }
+
     #[test]
     fn dt() -> Result<(), &'static str> {
         let x = to_datetime("1979-05-27T07:32:00-08:00")?;
@@ -0,0 +1,291 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Mutex;
+
+use crate::{digest, limits};
+
+/// What is known about one env var that has been read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegistryEntry {
+    pub key: String,
+    pub type_name: &'static str,
+    /// The raw value from the environment, or `None` if the default
+    /// was used. Sensitive values are never kept, and are `None` too.
+    pub value: Option<String>,
+    /// Whether the value came from the environment, rather than from
+    /// the default.
+    pub from_env: bool,
+    /// Whether the value was too long to keep, so that `value` only
+    /// describes it, by its length and a hash. See
+    /// [`Limits::max_recorded_len`](crate::Limits::max_recorded_len).
+    pub hashed: bool,
+    /// The default, written the way it would be in the environment,
+    /// if its type supports that.
+    pub default: Option<String>,
+    pub sensitive: bool,
+    /// Whether the type is free text, like `String`. See
+    /// [`TryFromEnv::TEXT`](crate::TryFromEnv::TEXT).
+    pub text: bool,
+    /// Whether the key has no default, and must be set.
+    pub required: bool,
+    /// The only values allowed, if the key was read with
+    /// [`biodome_one_of`](crate::biodome_one_of).
+    pub choices: Vec<String>,
+}
+
+impl RegistryEntry {
+    /// Whether the value came from the environment, rather than from
+    /// the default.
+    pub fn from_env(&self) -> bool {
+        self.from_env
+    }
+
+    /// Whether the value must be kept out of logs and reports, either
+    /// because of its type or because of its name.
+    pub fn redacted(&self) -> bool {
+        self.sensitive || sensitive_name(&self.key)
+    }
+}
+
+static REGISTRY: Mutex<Vec<RegistryEntry>> = Mutex::new(Vec::new());
+
+/// Remember that "key" was read. Reading the same key again replaces
+/// the earlier entry, so the registry always shows the latest value.
+pub(crate) fn record(entry: RegistryEntry) {
+    let mut registry = REGISTRY.lock().unwrap();
+    match registry.iter_mut().find(|e| e.key == entry.key) {
+        Some(existing) => *existing = entry,
+        None => registry.push(entry),
+    }
+}
+
+/// "value" as it should be kept in the registry, and whether it had
+/// to be replaced by its hash because it is too long.
+pub(crate) fn recordable(value: String) -> (String, bool) {
+    if value.len() <= limits::limits().max_recorded_len {
+        return (value, false);
+    }
+    let hash = digest::fnv1a(value.as_bytes());
+    (format!("[{} bytes, hash {:016x}]", value.len(), hash), true)
+}
+
+/// Record the values that "key" is restricted to.
+pub(crate) fn set_choices(key: &str, choices: &[&str]) {
+    let mut registry = REGISTRY.lock().unwrap();
+    if let Some(entry) = registry.iter_mut().find(|e| e.key == key) {
+        entry.choices = choices.iter().map(|c| c.to_string()).collect();
+    }
+}
+
+/// Every env var read so far, in the order they were first read.
+///
+/// This is what an app needs to print its effective configuration at
+/// startup, or to check it against documentation. Use
+/// [`redacted`](RegistryEntry::redacted) to keep sensitive values out
+/// of the output:
+///
+/// ```rust
+/// let PORT = biodome::biodome("PORT", 8080);
+/// for entry in biodome::registry() {
+///     let value = match &entry.value {
+///         _ if entry.redacted() => "[REDACTED]",
+///         Some(value) => value,
+///         None => "(default)",
+///     };
+///     println!("{} = {} [{}]", entry.key, value, entry.type_name);
+/// }
+/// ```
+pub fn registry() -> Vec<RegistryEntry> {
+    REGISTRY.lock().unwrap().clone()
+}
+
+/// The entry for "key", if it has been read.
+pub(crate) fn entry(key: &str) -> Option<RegistryEntry> {
+    REGISTRY
+        .lock()
+        .unwrap()
+        .iter()
+        .find(|e| e.key == key)
+        .cloned()
+}
+
+/// Whether the value of "key" must be kept out of logs: its entry is
+/// [redacted](RegistryEntry::redacted), or it hasn't been read and its
+/// name looks sensitive.
+pub(crate) fn redacted(key: &str) -> bool {
+    match entry(key) {
+        Some(entry) => entry.redacted(),
+        None => sensitive_name(key),
+    }
+}
+
+static DESCRIPTIONS: Mutex<BTreeMap<String, String>> = Mutex::new(BTreeMap::new());
+
+/// Attach a human-readable description to "key", for the files that
+/// [`export`](crate::export) generates. It can be called before or
+/// after the key is read.
+///
+/// ```rust
+/// biodome::describe("PORT", "Port the HTTP server listens on");
+/// let PORT = biodome::biodome("PORT", 8080);
+/// ```
+pub fn describe(key: &str, description: &str) {
+    DESCRIPTIONS
+        .lock()
+        .unwrap()
+        .insert(key.to_string(), description.to_string());
+}
+
+pub(crate) fn description(key: &str) -> Option<String> {
+    DESCRIPTIONS.lock().unwrap().get(key).cloned()
+}
+
+static MARKED: Mutex<BTreeSet<String>> = Mutex::new(BTreeSet::new());
+
+/// Treat the value of "key" as sensitive, whatever its type and name:
+/// keep it out of error messages, and redact it in reports.
+///
+/// ```rust
+/// biodome::mark_sensitive("DATABASE_URL");
+/// ```
+pub fn mark_sensitive(key: &str) {
+    MARKED.lock().unwrap().insert(key.to_string());
+}
+
+/// Replace "value" in "text", as it is and as `Debug` would quote it,
+/// so that an error message can't give it away.
+pub(crate) fn scrub(text: &str, value: &str) -> String {
+    if value.is_empty() {
+        return text.to_string();
+    }
+    let escaped = value.escape_debug().to_string();
+    text.replace(value, "[REDACTED]")
+        .replace(&escaped, "[REDACTED]")
+}
+
+/// Whether the name of "key" suggests that its value is sensitive, or
+/// it has been marked with [`mark_sensitive`].
+pub(crate) fn sensitive_name(key: &str) -> bool {
+    if MARKED.lock().unwrap().contains(key) {
+        return true;
+    }
+    const MARKERS: [&str; 6] = [
+        "PASSWORD",
+        "SECRET",
+        "TOKEN",
+        "CREDENTIAL",
+        "PRIVATE",
+        "API_KEY",
+    ];
+    let key = key.to_uppercase();
+    MARKERS.iter().any(|m| key.contains(m))
+}
+
+const MAX_ENTRIES: usize = 100;
+const MAX_VALUE_LEN: usize = 64;
+
+/// A short summary of the configuration that has been read so far,
+/// meant for attaching to crash reports.
+///
+/// Values of [`Secret`](crate::Secret)s, and of keys whose names look
+/// sensitive (`*PASSWORD*`, `*TOKEN*` and so on), are redacted. Long
+/// values are truncated, and only the first 100 keys are listed, so
+/// the summary stays small no matter what is in the environment.
+///
+/// A good place to use it is a panic hook:
+///
+/// ```rust
+/// let default_hook = std::panic::take_hook();
+/// std::panic::set_hook(Box::new(move |info| {
+///     eprintln!("{}", biodome::crash_context());
+///     default_hook(info);
+/// }));
+/// ```
+pub fn crash_context() -> String {
+    summarize(&registry())
+}
+
+fn summarize(entries: &[RegistryEntry]) -> String {
+    let mut out = format!("biodome configuration ({} keys):\n", entries.len());
+    for entry in entries.iter().take(MAX_ENTRIES) {
+        let value = match (&entry.value, &entry.default) {
+            _ if entry.redacted() => "[REDACTED]".to_string(),
+            (Some(v), _) => truncate(v, MAX_VALUE_LEN),
+            (None, Some(d)) => format!("{} (default)", truncate(d, MAX_VALUE_LEN)),
+            (None, None) => "(default)".to_string(),
+        };
+        out += &format!("  {} = {} [{}]\n", entry.key, value, entry.type_name);
+    }
+    if entries.len() > MAX_ENTRIES {
+        out += &format!("  ... and {} more\n", entries.len() - MAX_ENTRIES);
+    }
+    out
+}
+
+pub(crate) fn truncate(s: &str, max: usize) -> String {
+    match s.char_indices().nth(max) {
+        Some((i, _)) => format!("{}... ({} bytes)", &s[..i], s.len()),
+        None => s.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(key: &str, value: Option<&str>, sensitive: bool) -> RegistryEntry {
+        RegistryEntry {
+            key: key.to_string(),
+            type_name: "i32",
+            value: value.map(|v| v.to_string()),
+            from_env: value.is_some(),
+            hashed: false,
+            default: None,
+            sensitive,
+            text: false,
+            required: false,
+            choices: vec![],
+        }
+    }
+
+    #[test]
+    fn scrubbed() {
+        assert_eq!(
+            scrub("bad value \"p\\\"w\" here", "p\"w"),
+            "bad value \"[REDACTED]\" here"
+        );
+        assert_eq!(scrub("no value", ""), "no value");
+    }
+
+    #[test]
+    fn summary() {
+        let long = "x".repeat(100);
+        let entries = vec![
+            entry("TIMEOUT", Some("30"), false),
+            entry("PORT", None, false),
+            entry("DB_PASSWORD", Some("hunter2"), false),
+            entry("SIGNING", Some("abc"), true),
+            entry("CERT", Some(&long), false),
+        ];
+        let out = summarize(&entries);
+        assert!(out.starts_with("biodome configuration (5 keys):\n"));
+        assert!(out.contains("  TIMEOUT = 30 [i32]\n"));
+        assert!(out.contains("  PORT = (default) [i32]\n"));
+        assert!(out.contains("  DB_PASSWORD = [REDACTED] [i32]\n"));
+        assert!(out.contains("  SIGNING = [REDACTED] [i32]\n"));
+        assert!(out.contains(&format!(
+            "  CERT = {}... (100 bytes) [i32]\n",
+            "x".repeat(64)
+        )));
+        assert!(!out.contains("hunter2"));
+    }
+
+    #[test]
+    fn bounded() {
+        let entries: Vec<RegistryEntry> = (0..150)
+            .map(|i| entry(&format!("K{}", i), Some("1"), false))
+            .collect();
+        let out = summarize(&entries);
+        assert_eq!(out.lines().count(), 1 + MAX_ENTRIES + 1);
+        assert!(out.ends_with("  ... and 50 more\n"));
+    }
+}
@@ -0,0 +1,530 @@
+//! Settings that can be read again while the process runs.
+//!
+//! A [`Reloadable`] holds the current value of a [`Settings`] struct.
+//! [`reload`](Reloadable::reload) reads the settings again and swaps
+//! them in, unless they fail validation, in which case the previous
+//! settings stay in place.
+//!
+//! Components that depend on one key, such as a rate limiter, can
+//! [`subscribe`] to it, to hear when a reload changes its value.
+//!
+//! The settings are swapped with [`arc_swap`], so readers never wait
+//! for a reload, or for each other. [`load`](Reloadable::load) is
+//! cheap enough to call on every request of a long-running daemon.
+
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use arc_swap::ArcSwap;
+
+use crate::{profile, registry, source, validate_all, BiodomeError, Settings, TryFromEnv};
+
+/// A check run on newly read settings before they are kept, e.g. to
+/// reconfigure a component that may refuse the new values. An error
+/// rolls the settings back to the previous ones.
+pub trait ApplyReload<T>: Send + Sync {
+    fn apply(&self, new: &T, old: &T) -> Result<(), String>;
+}
+
+impl<T, F: Fn(&T, &T) -> Result<(), String> + Send + Sync> ApplyReload<T> for F {
+    fn apply(&self, new: &T, old: &T) -> Result<(), String> {
+        self(new, old)
+    }
+}
+
+/// Why a reload was rejected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReloadError {
+    /// The new settings could not be read.
+    Invalid(Vec<BiodomeError>),
+    /// The [`ApplyReload`] hook refused the new settings.
+    Rejected(String),
+}
+
+impl fmt::Display for ReloadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReloadError::Invalid(errors) => {
+                let errors: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+                write!(f, "reload rejected: {}", errors.join("; "))
+            }
+            ReloadError::Rejected(reason) => write!(f, "reload rejected: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for ReloadError {}
+
+/// What happened to a reload.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReloadEvent {
+    Applied,
+    Rejected(ReloadError),
+}
+
+type Listener = Box<dyn Fn(&ReloadEvent) + Send + Sync>;
+
+/// A cheap, temporary borrow of the current settings, from
+/// [`Reloadable::load`]. Hold it for a request, not for the life of a
+/// thread: a reload can't free the settings it borrows while it is
+/// around.
+pub type Guard<T> = arc_swap::Guard<Arc<T>>;
+
+/// Settings that can be reloaded.
+///
+/// ```rust
+/// use biodome::reload::{ReloadEvent, Reloadable};
+/// use biodome::{Scope, Settings};
+///
+/// struct Pool {
+///     size: u32,
+/// }
+///
+/// impl Settings for Pool {
+///     fn load(scope: &Scope) -> Self {
+///         Pool { size: scope.biodome("SIZE", 8u32) }
+///     }
+/// }
+///
+/// let pool = Reloadable::<Pool>::new("POOL_").unwrap().on_apply(|new: &Pool, _old: &Pool| {
+///     if new.size == 0 {
+///         return Err("the pool can't be empty".to_string());
+///     }
+///     Ok(())
+/// });
+/// pool.on_event(|event| {
+///     if let ReloadEvent::Rejected(err) = event {
+///         eprintln!("{}", err);
+///     }
+/// });
+///
+/// std::env::set_var("POOL_SIZE", "0");
+/// assert!(pool.reload().is_err());
+/// assert_eq!(pool.load().size, 8);
+/// ```
+pub struct Reloadable<T> {
+    prefix: String,
+    current: ArcSwap<T>,
+    hook: Option<Box<dyn ApplyReload<T>>>,
+    listeners: Mutex<Vec<Listener>>,
+    /// Held for the whole of a reload, so reloads from different
+    /// threads, like a signal and a file watch, happen one at a time.
+    reloading: Mutex<()>,
+}
+
+impl<T: Settings> Reloadable<T> {
+    /// Read the settings under "prefix", as [`validate_all`] does.
+    pub fn new(prefix: &str) -> Result<Self, Vec<BiodomeError>> {
+        Ok(Reloadable {
+            prefix: prefix.to_string(),
+            current: ArcSwap::from_pointee(validate_all(prefix)?),
+            hook: None,
+            listeners: Mutex::new(vec![]),
+            reloading: Mutex::new(()),
+        })
+    }
+
+    /// Run "hook" on the new settings at every reload.
+    pub fn on_apply(mut self, hook: impl ApplyReload<T> + 'static) -> Self {
+        self.hook = Some(Box::new(hook));
+        self
+    }
+
+    /// Call "listener" after every reload, with its outcome.
+    pub fn on_event(&self, listener: impl Fn(&ReloadEvent) + Send + Sync + 'static) {
+        self.listeners.lock().unwrap().push(Box::new(listener));
+    }
+
+    /// The current settings, to keep for as long as needed.
+    pub fn get(&self) -> Arc<T> {
+        self.current.load_full()
+    }
+
+    /// The current settings, to read right away. This is cheaper than
+    /// [`get`](Reloadable::get).
+    pub fn load(&self) -> Guard<T> {
+        self.current.load()
+    }
+
+    /// Read the settings again and swap them in. If they can't be
+    /// read, or the [`ApplyReload`] hook refuses them, the previous
+    /// settings stay in place and the error is returned. Readers never
+    /// see settings that the hook refuses.
+    pub fn reload(&self) -> Result<(), ReloadError> {
+        let result = self.try_reload();
+        self.notify(&result);
+        if result.is_ok() {
+            notify_subscribers();
+        }
+        result
+    }
+
+    /// [Refresh](crate::refresh_source) the installed source, then
+    /// reload. A source that can't be read again is reported to the
+    /// listeners as a rejected reload.
+    #[cfg(any(all(unix, feature = "sighup"), feature = "watch"))]
+    fn refresh_and_reload(&self) {
+        match crate::refresh_source() {
+            Ok(()) => {
+                let _ = self.reload();
+            }
+            Err(err) => self.notify(&Err(ReloadError::Rejected(format!(
+                "failed to read the configuration: {}",
+                err
+            )))),
+        }
+    }
+
+    fn notify(&self, result: &Result<(), ReloadError>) {
+        let event = match result {
+            Ok(()) => ReloadEvent::Applied,
+            Err(err) => ReloadEvent::Rejected(err.clone()),
+        };
+        for listener in self.listeners.lock().unwrap().iter() {
+            listener(&event);
+        }
+    }
+
+    fn try_reload(&self) -> Result<(), ReloadError> {
+        let _reloading = self.reloading.lock().unwrap_or_else(|err| err.into_inner());
+        let new = validate_all::<T>(&self.prefix).map_err(ReloadError::Invalid)?;
+        if let Some(hook) = &self.hook {
+            hook.apply(&new, &self.current.load())
+                .map_err(ReloadError::Rejected)?;
+        }
+        self.current.store(Arc::new(new));
+        Ok(())
+    }
+}
+
+type Subscriber = Box<dyn FnMut() + Send>;
+
+static SUBSCRIBERS: Mutex<Vec<Subscriber>> = Mutex::new(Vec::new());
+
+/// Call "f" with the old and the new value of "key", parsed as "T",
+/// whenever a [`Reloadable`] reloads and the value has changed.
+///
+/// The value is the one the reload read, from the environment or
+/// the default, so "key" must be one of the keys of the settings.
+/// Values too long to keep in the [registry](crate::registry) are not
+/// seen.
+///
+/// ```rust
+/// use biodome::reload::{subscribe, Reloadable};
+/// use biodome::{Scope, Settings};
+///
+/// struct Limits {
+///     rate: u32,
+/// }
+///
+/// impl Settings for Limits {
+///     fn load(scope: &Scope) -> Self {
+///         Limits { rate: scope.biodome("RATE_LIMIT", 100u32) }
+///     }
+/// }
+///
+/// let limits = Reloadable::<Limits>::new("").unwrap();
+/// subscribe::<u32>("RATE_LIMIT", |old, new| {
+///     println!("rate limit changed from {} to {}", old, new);
+/// });
+/// std::env::set_var("RATE_LIMIT", "200");
+/// limits.reload().unwrap();
+/// ```
+pub fn subscribe<T: TryFromEnv<T> + PartialEq + Send + 'static>(
+    key: &str,
+    mut f: impl FnMut(&T, &T) + Send + 'static,
+) {
+    let key = key.to_string();
+    let mut last = current::<T>(&key);
+    let subscriber = move || {
+        let Some(new) = current::<T>(&key) else {
+            return;
+        };
+        if let Some(old) = &last {
+            if *old != new {
+                f(old, &new);
+            }
+        }
+        last = Some(new);
+    };
+    SUBSCRIBERS.lock().unwrap().push(Box::new(subscriber));
+}
+
+/// The value of "key" from when it was last read, parsed as "T".
+/// Sensitive values are not kept in the registry, so those are read
+/// again.
+fn current<T: TryFromEnv<T>>(key: &str) -> Option<T> {
+    let entry = registry::entry(key).filter(|e| !e.hashed)?;
+    let raw = match (entry.value, entry.from_env) {
+        (Some(value), _) => value,
+        (None, true) => source::try_value(key).ok()?.map(profile::select)?,
+        (None, false) => entry.default?,
+    };
+    T::try_from_env(&raw).ok()
+}
+
+fn notify_subscribers() {
+    // The lock isn't held while the callbacks run, so that they can
+    // subscribe too.
+    let mut subscribers = std::mem::take(&mut *SUBSCRIBERS.lock().unwrap());
+    for subscriber in subscribers.iter_mut() {
+        subscriber();
+    }
+    SUBSCRIBERS.lock().unwrap().splice(0..0, subscribers);
+}
+
+/// Reload "reloadable" whenever the process receives SIGHUP, the
+/// usual way to tell a daemon that its configuration has changed.
+///
+/// The installed source is [refreshed](crate::refresh_source) first,
+/// so `.env` files are read again, and the settings are then reloaded
+/// as by [`Reloadable::reload`]. New settings that fail validation are
+/// not swapped in. Listeners added with
+/// [`on_event`](Reloadable::on_event) hear about every reload,
+/// including those where a file could not be read.
+///
+/// The signal handler is in place when this returns. Signals are
+/// handled on a thread of their own, for the life of the process.
+///
+/// ```rust,no_run
+/// use std::sync::Arc;
+/// use biodome::reload::{reload_on_sighup, Reloadable};
+/// use biodome::{Scope, Settings};
+///
+/// struct Pool {
+///     size: u32,
+/// }
+///
+/// impl Settings for Pool {
+///     fn load(scope: &Scope) -> Self {
+///         Pool { size: scope.biodome("SIZE", 8u32) }
+///     }
+/// }
+///
+/// biodome::dotenv::load(".env").unwrap();
+/// let pool = Arc::new(Reloadable::<Pool>::new("POOL_").unwrap());
+/// reload_on_sighup(&pool).unwrap();
+/// ```
+#[cfg(all(unix, feature = "sighup"))]
+pub fn reload_on_sighup<T: Settings + Send + Sync + 'static>(
+    reloadable: &Arc<Reloadable<T>>,
+) -> std::io::Result<()> {
+    use signal_hook::consts::SIGHUP;
+    use signal_hook::iterator::Signals;
+
+    let mut signals = Signals::new([SIGHUP])?;
+    let reloadable = Arc::clone(reloadable);
+    std::thread::spawn(move || {
+        for _ in signals.forever() {
+            reloadable.refresh_and_reload();
+        }
+    });
+    Ok(())
+}
+
+/// Watches the files of the installed source for
+/// [`reload_on_change`]. Dropping it stops watching.
+#[cfg(feature = "watch")]
+pub struct ReloadWatcher {
+    _watcher: notify::RecommendedWatcher,
+}
+
+/// Reload "reloadable" whenever the files of the installed source
+/// change, such as the `.env` file installed by
+/// [`dotenv::load`](crate::dotenv::load), or a [`ConfigDir`] mounted
+/// from a ConfigMap that Kubernetes updates in place.
+///
+/// Changes are debounced: the reload happens once no file has changed
+/// for "debounce", so an update that touches several files reloads
+/// once. It then goes as for [`reload_on_sighup`]: the source is
+/// [refreshed](crate::refresh_source), and new settings that fail
+/// validation are not swapped in.
+///
+/// The files to watch come from [`EnvSource::watch_paths`]; it is an
+/// error if the installed source has none. Files are watched through
+/// the directories they are in, so that files replaced by editors, or
+/// by Kubernetes swapping a link, are still seen.
+///
+/// ```rust,no_run
+/// use std::sync::Arc;
+/// use std::time::Duration;
+/// use biodome::reload::{reload_on_change, Reloadable};
+/// use biodome::{set_source, ConfigDir, Layers, Scope, Settings, StdEnv};
+///
+/// struct Pool {
+///     size: u32,
+/// }
+///
+/// impl Settings for Pool {
+///     fn load(scope: &Scope) -> Self {
+///         Pool { size: scope.biodome("SIZE", 8u32) }
+///     }
+/// }
+///
+/// set_source(Layers::new().with(StdEnv).with(ConfigDir::new("/etc/config")));
+/// let pool = Arc::new(Reloadable::<Pool>::new("POOL_").unwrap());
+/// let _watcher = reload_on_change(&pool, Duration::from_millis(500)).unwrap();
+/// ```
+///
+/// [`ConfigDir`]: crate::ConfigDir
+/// [`EnvSource::watch_paths`]: crate::EnvSource::watch_paths
+#[cfg(feature = "watch")]
+pub fn reload_on_change<T: Settings + Send + Sync + 'static>(
+    reloadable: &Arc<Reloadable<T>>,
+    debounce: std::time::Duration,
+) -> notify::Result<ReloadWatcher> {
+    use notify::{RecursiveMode, Watcher};
+    use std::collections::BTreeSet;
+    use std::sync::mpsc;
+
+    let dirs: BTreeSet<_> = crate::source::watch_paths()
+        .into_iter()
+        .filter_map(|path| {
+            if path.is_dir() {
+                Some(path)
+            } else {
+                path.parent().map(|p| p.to_path_buf())
+            }
+        })
+        // A bare file name is in the current directory.
+        .map(|dir| {
+            if dir.as_os_str().is_empty() {
+                std::path::PathBuf::from(".")
+            } else {
+                dir
+            }
+        })
+        .collect();
+    if dirs.is_empty() {
+        return Err(notify::Error::generic(
+            "the installed source has no files to watch",
+        ));
+    }
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        if event.is_ok_and(|e| !e.kind.is_access()) {
+            let _ = tx.send(());
+        }
+    })?;
+    for dir in &dirs {
+        watcher.watch(dir, RecursiveMode::NonRecursive)?;
+    }
+    let reloadable = Arc::clone(reloadable);
+    std::thread::spawn(move || {
+        // Ends when the watcher, and with it the sender, is dropped.
+        while rx.recv().is_ok() {
+            loop {
+                match rx.recv_timeout(debounce) {
+                    Ok(()) => continue,
+                    Err(mpsc::RecvTimeoutError::Timeout) => break,
+                    Err(mpsc::RecvTimeoutError::Disconnected) => return,
+                }
+            }
+            reloadable.refresh_and_reload();
+        }
+    });
+    Ok(ReloadWatcher { _watcher: watcher })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Scope;
+    use std::env;
+    use std::sync::{OnceLock, Weak};
+
+    struct Limits {
+        rate: u32,
+    }
+
+    impl Settings for Limits {
+        fn load(scope: &Scope) -> Self {
+            Limits {
+                rate: scope.biodome("RATE", 10u32),
+            }
+        }
+    }
+
+    #[test]
+    fn rollback() {
+        let limits =
+            Reloadable::<Limits>::new("RELOAD_")
+                .unwrap()
+                .on_apply(|new: &Limits, old: &Limits| {
+                    if new.rate > old.rate * 10 {
+                        Err(format!("rate jumped from {} to {}", old.rate, new.rate))
+                    } else {
+                        Ok(())
+                    }
+                });
+        let events = Arc::new(Mutex::new(vec![]));
+        let sink = events.clone();
+        limits.on_event(move |event| sink.lock().unwrap().push(event.clone()));
+
+        let before = limits.get();
+        env::set_var("RELOAD_RATE", "50");
+        assert_eq!(limits.reload(), Ok(()));
+        assert_eq!(limits.load().rate, 50);
+        assert_eq!(before.rate, 10);
+
+        env::set_var("RELOAD_RATE", "5000");
+        let err = limits.reload().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "reload rejected: rate jumped from 50 to 5000"
+        );
+        assert_eq!(limits.get().rate, 50);
+
+        env::set_var("RELOAD_RATE", "fast");
+        assert!(matches!(limits.reload(), Err(ReloadError::Invalid(_))));
+        assert_eq!(limits.get().rate, 50);
+
+        let events = events.lock().unwrap();
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[0], ReloadEvent::Applied);
+        assert_eq!(events[1], ReloadEvent::Rejected(err));
+    }
+
+    #[test]
+    fn hook_runs_before_swap() {
+        let this: Arc<OnceLock<Weak<Reloadable<Limits>>>> = Arc::new(OnceLock::new());
+        let inside = this.clone();
+        let limits = Arc::new(Reloadable::<Limits>::new("RELOAD_HOOK_").unwrap().on_apply(
+            move |new: &Limits, _old: &Limits| {
+                let limits = inside.get().unwrap().upgrade().unwrap();
+                assert_eq!(limits.load().rate, 10);
+                match new.rate {
+                    0 => Err("no rate".to_string()),
+                    _ => Ok(()),
+                }
+            },
+        ));
+        this.set(Arc::downgrade(&limits)).unwrap();
+
+        env::set_var("RELOAD_HOOK_RATE", "0");
+        assert!(limits.reload().is_err());
+        env::set_var("RELOAD_HOOK_RATE", "20");
+        assert_eq!(limits.reload(), Ok(()));
+        assert_eq!(limits.load().rate, 20);
+        env::remove_var("RELOAD_HOOK_RATE");
+    }
+
+    #[test]
+    fn subscriptions() {
+        let limits = Reloadable::<Limits>::new("SUBSCRIBE_").unwrap();
+        let changes = Arc::new(Mutex::new(vec![]));
+        let sink = changes.clone();
+        subscribe::<u32>("SUBSCRIBE_RATE", move |old, new| {
+            sink.lock().unwrap().push((*old, *new))
+        });
+
+        env::set_var("SUBSCRIBE_RATE", "50");
+        limits.reload().unwrap();
+        limits.reload().unwrap();
+        env::set_var("SUBSCRIBE_RATE", "fast");
+        assert!(limits.reload().is_err());
+        env::set_var("SUBSCRIBE_RATE", "60");
+        limits.reload().unwrap();
+        assert_eq!(*changes.lock().unwrap(), vec![(10, 50), (50, 60)]);
+    }
+}
@@ -0,0 +1,217 @@
+//! JSON Schema for settings structs.
+//!
+//! `#[derive(Settings)]` generates a `json_schema()` function from
+//! these pieces. The schema describes each env var, without its
+//! prefix, with the JSON type of its value, the bounds implied by its
+//! Rust type, its default and its description. Deployment tooling,
+//! like a Helm chart's `values.schema.json`, can use it to check
+//! values before the app starts.
+
+use serde_json::{json, Map, Value};
+
+use crate::{registry, TryFromEnv};
+
+/// The schema for the settings struct "title", with "properties".
+pub fn object(title: &str, properties: Map<String, Value>) -> Value {
+    json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": title,
+        "type": "object",
+        "properties": properties,
+    })
+}
+
+/// Add the properties of "schema", the schema of a flattened settings
+/// struct, to "properties", with their keys under "prefix".
+pub fn flatten(properties: &mut Map<String, Value>, prefix: &str, schema: Value) {
+    if let Value::Object(mut schema) = schema {
+        if let Some(Value::Object(inner)) = schema.remove("properties") {
+            for (key, value) in inner {
+                properties.insert(format!("{}{}", prefix, key), value);
+            }
+        }
+    }
+}
+
+/// The schema for the env var "key" that selects one of "choices",
+/// for settings that are an enum of variants.
+pub fn tag(key: &str, default: &str, choices: &[&str]) -> Value {
+    let mut schema = property::<String>(key, &default.to_string(), None);
+    schema["enum"] = json!(choices);
+    schema
+}
+
+/// The schema for the env var "key" of type "T". The default is left
+/// out for sensitive values, which are marked `writeOnly` instead.
+pub fn property<T: TryFromEnv<T>>(key: &str, default: &T, description: Option<&str>) -> Value {
+    let mut schema = type_schema(std::any::type_name::<T>());
+    if T::SENSITIVE || registry::sensitive_name(key) {
+        schema.insert("writeOnly".into(), Value::Bool(true));
+    } else if let Some(default) = T::to_env(default) {
+        let value = default_value(&default, schema.get("type").and_then(Value::as_str));
+        schema.insert("default".into(), value);
+    }
+    if let Some(description) = description {
+        schema.insert("description".into(), Value::String(description.into()));
+    }
+    Value::Object(schema)
+}
+
+/// The schema for values of "type_name", as given by
+/// `std::any::type_name`.
+fn type_schema(type_name: &str) -> Map<String, Value> {
+    let (path, args) = match type_name.find('<') {
+        Some(i) => (&type_name[..i], &type_name[i + 1..type_name.len() - 1]),
+        None => (type_name, ""),
+    };
+    let name = path.rsplit("::").next().unwrap_or(path);
+    let schema = match name {
+        "i8" => integer(i8::MIN as i64, i8::MAX as u64),
+        "i16" => integer(i16::MIN as i64, i16::MAX as u64),
+        "i32" => integer(i32::MIN as i64, i32::MAX as u64),
+        "i64" | "isize" => integer(i64::MIN, i64::MAX as u64),
+        "u8" => integer(0, u8::MAX as u64),
+        "u16" => integer(0, u16::MAX as u64),
+        "u32" => integer(0, u32::MAX as u64),
+        "u64" | "usize" => integer(0, u64::MAX),
+        "i128" | "u128" => json!({ "type": "integer" }),
+        "NonZero" => {
+            let mut inner = type_schema(args);
+            if args.starts_with('u') {
+                inner.insert("minimum".into(), json!(1));
+            }
+            return inner;
+        }
+        _ if name.starts_with("NonZeroU") => {
+            let mut inner = type_schema(&name["NonZero".len()..].to_lowercase());
+            inner.insert("minimum".into(), json!(1));
+            return inner;
+        }
+        _ if name.starts_with("NonZeroI") => {
+            return type_schema(&name["NonZero".len()..].to_lowercase());
+        }
+        "f32" | "f64" => json!({ "type": "number" }),
+        "bool" => json!({ "type": "boolean" }),
+        "Secret" => return type_schema(args),
+        "Vec" | "VecDeque" => json!({ "type": "array", "items": type_schema(args) }),
+        "HashSet" | "BTreeSet" => {
+            json!({ "type": "array", "items": type_schema(args), "uniqueItems": true })
+        }
+        "HashMap" | "BTreeMap" => {
+            let value = split_args(args).get(1).copied().unwrap_or("");
+            json!({ "type": "object", "additionalProperties": type_schema(value) })
+        }
+        "Ipv4Addr" => json!({ "type": "string", "format": "ipv4" }),
+        "Ipv6Addr" => json!({ "type": "string", "format": "ipv6" }),
+        "Uuid" => json!({ "type": "string", "format": "uuid" }),
+        "DateTime" | "OffsetDateTime" => json!({ "type": "string", "format": "date-time" }),
+        "NaiveDate" | "Date" => json!({ "type": "string", "format": "date" }),
+        _ => json!({ "type": "string" }),
+    };
+    match schema {
+        Value::Object(map) => map,
+        _ => unreachable!(),
+    }
+}
+
+fn integer(minimum: i64, maximum: u64) -> Value {
+    json!({ "type": "integer", "minimum": minimum, "maximum": maximum })
+}
+
+/// The type arguments in "args", split at the top-level commas.
+fn split_args(args: &str) -> Vec<&str> {
+    let mut parts = vec![];
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in args.char_indices() {
+        match c {
+            '<' | '(' => depth += 1,
+            '>' | ')' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(args[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(args[start..].trim());
+    parts
+}
+
+/// A default written as an env var value, as a JSON value of
+/// "json_type".
+fn default_value(value: &str, json_type: Option<&str>) -> Value {
+    let parsed = match json_type {
+        Some("integer") => value.parse::<i64>().map(Value::from).ok(),
+        Some("number") => value.parse::<f64>().ok().map(Value::from),
+        Some("boolean") => value.parse::<bool>().ok().map(Value::Bool),
+        Some("array" | "object") => format!("x = {}", value)
+            .parse::<toml::Value>()
+            .ok()
+            .and_then(|doc| doc.get("x").map(toml_to_json)),
+        _ => None,
+    };
+    parsed.unwrap_or_else(|| Value::String(value.to_string()))
+}
+
+fn toml_to_json(value: &toml::Value) -> Value {
+    match value {
+        toml::Value::String(s) => Value::String(s.clone()),
+        toml::Value::Integer(i) => Value::from(*i),
+        toml::Value::Float(f) => Value::from(*f),
+        toml::Value::Boolean(b) => Value::Bool(*b),
+        toml::Value::Datetime(d) => Value::String(d.to_string()),
+        toml::Value::Array(items) => Value::Array(items.iter().map(toml_to_json).collect()),
+        toml::Value::Table(table) => Value::Object(
+            table
+                .iter()
+                .map(|(k, v)| (k.clone(), toml_to_json(v)))
+                .collect(),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Secret;
+    use std::collections::HashMap;
+
+    #[test]
+    fn properties() {
+        assert_eq!(
+            property("PORT", &8080u16, Some("Port to listen on")),
+            json!({
+                "type": "integer",
+                "minimum": 0,
+                "maximum": 65535,
+                "default": 8080,
+                "description": "Port to listen on",
+            })
+        );
+        assert_eq!(
+            property("HOSTS", &vec!["a".to_string()], None),
+            json!({ "type": "array", "items": { "type": "string" }, "default": ["a"] })
+        );
+        assert_eq!(
+            property("LIMITS", &HashMap::from([("x".to_string(), 1.5)]), None),
+            json!({
+                "type": "object",
+                "additionalProperties": { "type": "number" },
+                "default": { "x": 1.5 },
+            })
+        );
+        assert_eq!(
+            property("SIGNING", &Secret::new("dev".to_string()), None),
+            json!({ "type": "string", "writeOnly": true })
+        );
+        assert_eq!(
+            property("DB_PASSWORD", &"dev".to_string(), None),
+            json!({ "type": "string", "writeOnly": true })
+        );
+        assert_eq!(
+            property("WORKERS", &std::num::NonZeroU8::new(4).unwrap(), None)["minimum"],
+            json!(1)
+        );
+    }
+}
@@ -0,0 +1,162 @@
+use std::fmt;
+use std::hint::black_box;
+
+use crate::{lint, TryFromEnv};
+
+/// A value, like a password or an API token, that needs more care
+/// than ordinary settings.
+///
+/// The wrapped value is only reachable through [`Secret::expose`],
+/// and comparisons go through [`Secret::verify`], which takes the same
+/// time no matter where the first mismatching byte is. `Debug` and
+/// `Display` print `[REDACTED]`, so a secret inside a settings struct
+/// stays out of debug logs. With the `zeroize` feature, the memory of
+/// the value is wiped when the secret is dropped.
+///
+/// ```rust
+/// use biodome::{biodome, Secret};
+///
+/// std::env::set_var("API_TOKEN", "s3cr3t");
+/// let API_TOKEN = biodome("API_TOKEN", Secret::new(String::new()));
+/// assert!(API_TOKEN.verify("s3cr3t"));
+/// assert!(!API_TOKEN.verify("guess"));
+/// assert_eq!(format!("{:?}", API_TOKEN), "[REDACTED]");
+/// ```
+#[derive(Clone, Default)]
+pub struct Secret<T: Wipe>(T);
+
+impl<T: Wipe> Secret<T> {
+    pub fn new(value: T) -> Self {
+        Secret(value)
+    }
+
+    /// Access the secret value.
+    pub fn expose(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: Wipe + AsRef<[u8]>> Secret<T> {
+    /// Compare "candidate" to the secret in constant time. Only the
+    /// length of the secret can be learned from timing.
+    pub fn verify(&self, candidate: impl AsRef<[u8]>) -> bool {
+        constant_time_eq(self.0.as_ref(), candidate.as_ref())
+    }
+}
+
+/// Secrets are checked for obviously weak values, like `changeme` or
+/// very short strings, and any found are reported through [`lints()`].
+///
+/// [`lints()`]: crate::lints
+impl<T: TryFromEnv<T> + AsRef<[u8]> + Wipe> TryFromEnv<Self> for Secret<T> {
+    type Error = T::Error;
+
+    const SENSITIVE: bool = true;
+
+    fn try_from_env(value: &str) -> Result<Self, Self::Error> {
+        T::try_from_env(value).map(Secret)
+    }
+
+    fn lint(value: &Self) -> Option<String> {
+        lint::weak_secret(value.0.as_ref()).or_else(|| T::lint(&value.0))
+    }
+}
+
+impl<T: Wipe> fmt::Debug for Secret<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("[REDACTED]")
+    }
+}
+
+impl<T: Wipe> fmt::Display for Secret<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("[REDACTED]")
+    }
+}
+
+impl<T: Wipe> Drop for Secret<T> {
+    fn drop(&mut self) {
+        self.0.wipe();
+    }
+}
+
+/// Values that a [`Secret`] can hold, which can be overwritten in
+/// place when the secret is dropped.
+///
+/// With the `zeroize` feature, the bytes are wiped with the
+/// [zeroize](https://docs.rs/zeroize) crate, which makes sure the
+/// compiler doesn't optimize the writes away. Without it, nothing is
+/// wiped. Implement this for your own types to keep them in secrets.
+pub trait Wipe {
+    fn wipe(&mut self);
+}
+
+#[cfg(feature = "zeroize")]
+impl Wipe for String {
+    fn wipe(&mut self) {
+        zeroize::Zeroize::zeroize(self);
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl Wipe for Vec<u8> {
+    fn wipe(&mut self) {
+        zeroize::Zeroize::zeroize(self);
+    }
+}
+
+#[cfg(not(feature = "zeroize"))]
+impl Wipe for String {
+    fn wipe(&mut self) {}
+}
+
+#[cfg(not(feature = "zeroize"))]
+impl Wipe for Vec<u8> {
+    fn wipe(&mut self) {}
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    let diff = a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y));
+    black_box(diff) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn eq() {
+        assert!(constant_time_eq(b"", b""));
+        assert!(constant_time_eq(b"token", b"token"));
+        assert!(!constant_time_eq(b"token", b"tokem"));
+        assert!(!constant_time_eq(b"token", b"toke"));
+    }
+
+    #[test]
+    fn verify() {
+        let secret = Secret::new("hunter2".to_string());
+        assert!(secret.verify("hunter2"));
+        assert!(secret.verify(b"hunter2"));
+        assert!(!secret.verify("hunter3"));
+        assert_eq!(secret.expose(), "hunter2");
+    }
+
+    #[test]
+    fn redacted() {
+        let secret = Secret::new("hunter2".to_string());
+        assert_eq!(format!("{:?}", secret), "[REDACTED]");
+        assert_eq!(secret.to_string(), "[REDACTED]");
+        assert_eq!(format!("{:?}", Some(secret)), "Some([REDACTED])");
+    }
+
+    #[cfg(feature = "zeroize")]
+    #[test]
+    fn wipe() {
+        let mut value = "hunter2".to_string();
+        value.wipe();
+        assert!(value.is_empty());
+    }
+}
@@ -0,0 +1,92 @@
+use std::collections::{BTreeSet, HashSet};
+use std::hash::Hash;
+use std::sync::RwLock;
+
+use crate::tomlconv::{self, FromTomlValue};
+use crate::{rawconv, TryFromEnv};
+
+/// What to do when an env var parsed into a set repeats an element.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Duplicates {
+    /// Keep one copy, silently.
+    #[default]
+    Dedup,
+    /// Fail to parse.
+    Reject,
+}
+
+static DUPLICATES: RwLock<Duplicates> = RwLock::new(Duplicates::Dedup);
+
+/// Use "policy" for all sets parsed from now on.
+///
+/// ```rust
+/// use biodome::{set_duplicates, Duplicates};
+///
+/// set_duplicates(Duplicates::Reject);
+/// ```
+pub fn set_duplicates(policy: Duplicates) {
+    *DUPLICATES.write().unwrap() = policy;
+}
+
+fn check_duplicates(parsed: usize, distinct: usize) -> Result<(), &'static str> {
+    let policy = *DUPLICATES.read().unwrap();
+    if policy == Duplicates::Reject && parsed != distinct {
+        return Err("duplicate elements");
+    }
+    Ok(())
+}
+
+fn to_env<'a, T: FromTomlValue + 'a>(items: impl Iterator<Item = &'a T>) -> Option<String> {
+    items
+        .map(T::to_toml)
+        .collect::<Option<Vec<_>>>()
+        .map(|items| tomlconv::to_inline(&toml::Value::Array(items)))
+}
+
+/// Parsed from a TOML array, like `Vec<T>`.
+impl<T: FromTomlValue + Eq + Hash> TryFromEnv<Self> for HashSet<T> {
+    type Error = &'static str;
+
+    fn try_from_env(value: &str) -> Result<Self, Self::Error> {
+        let items: Vec<T> = rawconv::to_vec(value)?;
+        let parsed = items.len();
+        let set: Self = items.into_iter().collect();
+        check_duplicates(parsed, set.len())?;
+        Ok(set)
+    }
+
+    fn to_env(value: &Self) -> Option<String> {
+        to_env(value.iter())
+    }
+}
+
+/// Parsed from a TOML array, like `Vec<T>`.
+impl<T: FromTomlValue + Ord> TryFromEnv<Self> for BTreeSet<T> {
+    type Error = &'static str;
+
+    fn try_from_env(value: &str) -> Result<Self, Self::Error> {
+        let items: Vec<T> = rawconv::to_vec(value)?;
+        let parsed = items.len();
+        let set: Self = items.into_iter().collect();
+        check_duplicates(parsed, set.len())?;
+        Ok(set)
+    }
+
+    fn to_env(value: &Self) -> Option<String> {
+        to_env(value.iter())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sets() {
+        let s = HashSet::<i32>::try_from_env("[1, 2, 2, 3]").unwrap();
+        assert_eq!(s, HashSet::from([1, 2, 3]));
+        let s = BTreeSet::<i32>::try_from_env("[3, 1, 2]").unwrap();
+        assert_eq!(s.into_iter().collect::<Vec<_>>(), vec![1, 2, 3]);
+        assert_eq!(check_duplicates(3, 3), Ok(()));
+    }
+}
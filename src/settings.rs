@@ -0,0 +1,332 @@
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet};
+use std::rc::Rc;
+
+use crate::{
+    biodome, biodome_one_of, biodome_one_of_ignore_case, biodome_or_error, BiodomeError, TryFromEnv,
+};
+
+/// A group of settings that are read together.
+///
+/// In a monorepo, the settings every service needs can live in one
+/// `CommonSettings`, which each service's own settings load next to
+/// theirs:
+///
+/// ```rust
+/// use biodome::{load_settings, Scope, Settings};
+///
+/// struct CommonSettings {
+///     log_level: String,
+///     db_url: String,
+/// }
+///
+/// impl Settings for CommonSettings {
+///     fn load(scope: &Scope) -> Self {
+///         CommonSettings {
+///             log_level: scope.biodome("LOG_LEVEL", "info"),
+///             db_url: scope.nested("DB_").biodome("URL", "postgres://localhost"),
+///         }
+///     }
+/// }
+///
+/// struct BillingSettings {
+///     common: CommonSettings,
+///     currency: String,
+/// }
+///
+/// impl Settings for BillingSettings {
+///     fn load(scope: &Scope) -> Self {
+///         BillingSettings {
+///             common: scope.load(),
+///             currency: scope.biodome("CURRENCY", "EUR"),
+///         }
+///     }
+/// }
+///
+/// std::env::set_var("BILLING_DB_URL", "postgres://billing-db");
+/// let settings: BillingSettings = load_settings("BILLING_").unwrap();
+/// assert_eq!(settings.common.db_url, "postgres://billing-db");
+/// assert_eq!(settings.common.log_level, "info");
+/// ```
+pub trait Settings: Sized {
+    /// Read the settings, with keys relative to "scope".
+    fn load(scope: &Scope) -> Self;
+}
+
+/// A key prefix that [`Settings`] are read under.
+///
+/// Prefixes compose: [`nested`](Scope::nested) adds to the prefix, so
+/// `DB_` inside `BILLING_` reads `BILLING_DB_URL`. A scope remembers
+/// which settings read each key, and [`collisions`](Scope::collisions)
+/// reports keys read by more than one of them, such as a service
+/// defining a `LOG_LEVEL` of its own next to the common one.
+#[derive(Debug, Clone, Default)]
+pub struct Scope {
+    prefix: String,
+    owner: &'static str,
+    keys: Rc<RefCell<BTreeMap<String, BTreeSet<&'static str>>>>,
+    /// Where errors are collected, instead of panicking, for
+    /// [`validate_all`].
+    errors: Option<Rc<RefCell<Vec<BiodomeError>>>>,
+}
+
+impl Scope {
+    pub fn new(prefix: &str) -> Self {
+        Scope {
+            prefix: prefix.to_string(),
+            ..Scope::default()
+        }
+    }
+
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
+
+    /// The full name of the env var for "name".
+    pub fn key(&self, name: &str) -> String {
+        format!("{}{}", self.prefix, name)
+    }
+
+    /// A scope for keys under "prefix", inside this one.
+    pub fn nested(&self, prefix: &str) -> Scope {
+        Scope {
+            prefix: self.key(prefix),
+            ..self.clone()
+        }
+    }
+
+    /// Attach a description to "name" in this scope, like
+    /// [`describe`](crate::describe).
+    pub fn describe(&self, name: &str, description: &str) {
+        crate::describe(&self.key(name), description);
+    }
+
+    /// Load the settings "S" in this scope.
+    pub fn load<S: Settings>(&self) -> S {
+        S::load(&Scope {
+            owner: std::any::type_name::<S>(),
+            ..self.clone()
+        })
+    }
+
+    /// Like [`biodome`](crate::biodome), but for "name" in this scope.
+    pub fn biodome<U: From<T>, T: TryFromEnv<U>>(&self, name: &str, default: T) -> U
+    where
+        <T as TryFromEnv<U>>::Error: std::fmt::Debug,
+    {
+        let key = self.claim(name);
+        match &self.errors {
+            None => biodome(&key, default),
+            Some(errors) => biodome_or_error(&key, default).unwrap_or_else(|(err, default)| {
+                errors.borrow_mut().push(err);
+                default
+            }),
+        }
+    }
+
+    /// Like [`biodome_one_of`](crate::biodome_one_of), but for "name"
+    /// in this scope. A value that is not one of "choices" panics, or
+    /// gives "default" when the settings are being validated.
+    pub fn one_of(&self, name: &str, default: &str, choices: &[&str]) -> String {
+        self.choose(name, default, choices, biodome_one_of)
+    }
+
+    /// Like [`one_of`](Scope::one_of), but ignoring case, as
+    /// [`biodome_one_of_ignore_case`] does.
+    pub fn one_of_ignore_case(&self, name: &str, default: &str, choices: &[&str]) -> String {
+        self.choose(name, default, choices, biodome_one_of_ignore_case)
+    }
+
+    fn choose(
+        &self,
+        name: &str,
+        default: &str,
+        choices: &[&str],
+        read: fn(&str, &str, &[&str]) -> Result<String, BiodomeError>,
+    ) -> String {
+        let key = self.claim(name);
+        read(&key, default, choices).unwrap_or_else(|err| match &self.errors {
+            None => panic!("{}", err),
+            Some(errors) => {
+                errors.borrow_mut().push(err);
+                default.to_string()
+            }
+        })
+    }
+
+    /// The full name of the env var for "name", which is now read by
+    /// the settings of this scope.
+    fn claim(&self, name: &str) -> String {
+        let key = self.key(name);
+        self.keys
+            .borrow_mut()
+            .entry(key.clone())
+            .or_default()
+            .insert(self.owner);
+        key
+    }
+
+    /// The keys read by more than one group of settings so far.
+    pub fn collisions(&self) -> Vec<BiodomeError> {
+        self.keys
+            .borrow()
+            .iter()
+            .filter(|(_, owners)| owners.len() > 1)
+            .map(|(key, owners)| BiodomeError::Collision {
+                key: key.clone(),
+                owners: owners.iter().map(|o| o.to_string()).collect(),
+            })
+            .collect()
+    }
+}
+
+/// Load the settings "S" under "prefix", failing on the first key
+/// that can't be read, or that is read by more than one group of
+/// settings. Use [`validate_all`] to get every error.
+pub fn load_settings<S: Settings>(prefix: &str) -> Result<S, BiodomeError> {
+    validate_all(prefix).map_err(|errors| errors.into_iter().next().unwrap())
+}
+
+/// Load the settings "S" under "prefix", reading every key even after
+/// one has failed, so that all the problems can be reported at once
+/// rather than only the first.
+///
+/// ```rust
+/// use biodome::{validate_all, Scope, Settings};
+///
+/// struct Limits {
+///     workers: u8,
+///     queue: u32,
+/// }
+///
+/// impl Settings for Limits {
+///     fn load(scope: &Scope) -> Self {
+///         Limits {
+///             workers: scope.biodome("WORKERS", 4u8),
+///             queue: scope.biodome("QUEUE", 100u32),
+///         }
+///     }
+/// }
+///
+/// std::env::set_var("LIMITS_WORKERS", "300");
+/// std::env::set_var("LIMITS_QUEUE", "lots");
+/// let errors = validate_all::<Limits>("LIMITS_").err().unwrap();
+/// for err in &errors {
+///     eprintln!("{}", err);
+/// }
+/// assert_eq!(errors.len(), 2);
+/// ```
+///
+/// Keys read by more than one group of settings are reported too.
+pub fn validate_all<S: Settings>(prefix: &str) -> Result<S, Vec<BiodomeError>> {
+    let errors = Rc::new(RefCell::new(vec![]));
+    let scope = Scope {
+        errors: Some(errors.clone()),
+        ..Scope::new(prefix)
+    };
+    let settings = scope.load();
+    let mut errors = errors.take();
+    errors.extend(scope.collisions());
+    if errors.is_empty() {
+        Ok(settings)
+    } else {
+        Err(errors)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Common {
+        port: u16,
+    }
+
+    impl Settings for Common {
+        fn load(scope: &Scope) -> Self {
+            Common {
+                port: scope.biodome("PORT", 80),
+            }
+        }
+    }
+
+    struct Clashing {
+        common: Common,
+        port: u16,
+    }
+
+    impl Settings for Clashing {
+        fn load(scope: &Scope) -> Self {
+            Clashing {
+                common: scope.load(),
+                port: scope.biodome("PORT", 8080),
+            }
+        }
+    }
+
+    struct Nested {
+        common: Common,
+        admin: Common,
+    }
+
+    impl Settings for Nested {
+        fn load(scope: &Scope) -> Self {
+            Nested {
+                common: scope.load(),
+                admin: scope.nested("ADMIN_").load(),
+            }
+        }
+    }
+
+    #[test]
+    fn prefixes() {
+        let scope = Scope::new("SVC_A_");
+        assert_eq!(scope.nested("DB_").key("URL"), "SVC_A_DB_URL");
+        let nested: Nested = scope.load();
+        assert_eq!((nested.common.port, nested.admin.port), (80, 80));
+        assert!(scope.collisions().is_empty());
+        assert_eq!(
+            scope.keys.borrow().keys().collect::<Vec<_>>(),
+            ["SVC_A_ADMIN_PORT", "SVC_A_PORT"]
+        );
+    }
+
+    #[test]
+    fn collisions() {
+        let err = load_settings::<Clashing>("SVC_B_").err().unwrap();
+        match err {
+            BiodomeError::Collision { key, owners } => {
+                assert_eq!(key, "SVC_B_PORT");
+                assert_eq!(owners.len(), 2);
+            }
+            _ => panic!("expected a collision"),
+        }
+        let clashing: Clashing = Scope::new("SVC_C_").load();
+        assert_eq!((clashing.common.port, clashing.port), (80, 8080));
+    }
+
+    #[test]
+    fn load_errors() {
+        std::env::set_var("SVC_G_PORT", "http");
+        let err = load_settings::<Common>("SVC_G_").err().unwrap();
+        assert!(matches!(err, BiodomeError::Parse { key, .. } if key == "SVC_G_PORT"));
+        std::env::remove_var("SVC_G_PORT");
+    }
+
+    #[test]
+    fn validation() {
+        std::env::set_var("SVC_D_PORT", "http");
+        std::env::set_var("SVC_D_ADMIN_PORT", "-1");
+        let errors = validate_all::<Nested>("SVC_D_").err().unwrap();
+        let keys: Vec<String> = errors
+            .into_iter()
+            .map(|err| match err {
+                BiodomeError::Parse { key, .. } => key,
+                _ => panic!("expected a parse error"),
+            })
+            .collect();
+        assert_eq!(keys, ["SVC_D_PORT", "SVC_D_ADMIN_PORT"]);
+        assert_eq!(validate_all::<Clashing>("SVC_E_").err().unwrap().len(), 1);
+        assert!(validate_all::<Nested>("SVC_F_").is_ok());
+    }
+}
@@ -0,0 +1,177 @@
+//! Keep the last settings that validated, to boot from when a deploy
+//! pushes bad config.
+//!
+//! [`boot_from_last_known_good`] loads a [`Settings`] struct from the
+//! environment as usual. When that works, the values are saved to a
+//! snapshot file; when it fails, the values in the snapshot are used
+//! in place of the environment, so the process can still start with
+//! the config it last ran with.
+//!
+//! Sensitive values are never written to the snapshot. They are
+//! always read from the live environment.
+
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::dotenv::Dotenv;
+use crate::source::{self, EnvSource};
+use crate::{export, policy, registry, validate_all, BiodomeError, Settings};
+
+/// Write the values of every key under "prefix" that has been read so
+/// far to the snapshot file at "path", in `.env` format. Keys that
+/// were not set are saved with their defaults, so that a later boot
+/// from the snapshot doesn't pick up a bad value added since.
+///
+/// The file is written with [`export::write`], so a crash while
+/// saving never leaves half a snapshot behind, and processes that
+/// share the snapshot take turns.
+pub fn save<P: AsRef<Path>>(path: P, prefix: &str) -> io::Result<()> {
+    let mut out = String::from("# Last known good settings, written by biodome.\n");
+    for entry in registry::registry() {
+        if !entry.key.starts_with(prefix) {
+            continue;
+        }
+        if entry.redacted() {
+            out += &format!("# {} is sensitive, and not saved\n", entry.key);
+            continue;
+        }
+        if entry.hashed {
+            out += &format!("# {} is too long, and not saved\n", entry.key);
+            continue;
+        }
+        if let Some(value) = entry.value.or(entry.default) {
+            out += &format!("{}=\"{}\"\n", entry.key, escape(&value));
+        }
+    }
+    export::write(path, &out)
+}
+
+/// Load the settings "S" under "prefix" from the environment, falling
+/// back to the snapshot at "path" if they fail validation.
+///
+/// If the settings are valid, they are saved as the new snapshot. A
+/// snapshot that can't be written doesn't stop the boot, since the
+/// settings themselves are fine.
+///
+/// If they are not, the snapshot is installed as a source above the
+/// current one, for all lookups from then on, and the settings are
+/// read again. That is unless the [policy](crate::allow_sources) of
+/// the active profile doesn't allow a `"snapshot"` source, in which
+/// case that is one of the errors. The errors returned are those from the live
+/// environment, if the snapshot is missing or doesn't fix them.
+///
+/// ```rust,no_run
+/// use biodome::snapshot::boot_from_last_known_good;
+/// use biodome::{Scope, Settings};
+///
+/// struct Server {
+///     port: u16,
+/// }
+///
+/// impl Settings for Server {
+///     fn load(scope: &Scope) -> Self {
+///         Server {
+///             port: scope.biodome("PORT", 8080),
+///         }
+///     }
+/// }
+///
+/// let server: Server = boot_from_last_known_good("SERVER_", "/var/lib/app/config.env")
+///     .unwrap_or_else(|errors| panic!("{:?}", errors));
+/// ```
+pub fn boot_from_last_known_good<S: Settings, P: AsRef<Path>>(
+    prefix: &str,
+    path: P,
+) -> Result<S, Vec<BiodomeError>> {
+    let errors = match validate_all::<S>(prefix) {
+        Ok(settings) => {
+            let _ = save(path, prefix);
+            return Ok(settings);
+        }
+        Err(errors) => errors,
+    };
+    let Ok(snapshot) = Dotenv::from_path(path) else {
+        return Err(errors);
+    };
+    if let Err(err) = policy::check(&["snapshot"]) {
+        let mut errors = errors;
+        errors.push(err);
+        return Err(errors);
+    }
+    source::set_source(Fallback {
+        snapshot,
+        live: source::take_source(),
+    });
+    validate_all::<S>(prefix).map_err(|_| errors)
+}
+
+/// The snapshot, over the source that was installed before it.
+struct Fallback {
+    snapshot: Dotenv,
+    live: Box<dyn EnvSource>,
+}
+
+impl EnvSource for Fallback {
+    fn get(&self, key: &str) -> Option<String> {
+        self.snapshot.get(key).or_else(|| self.live.get(key))
+    }
+
+    fn names(&self) -> Vec<String> {
+        let mut names = self.snapshot.names();
+        names.extend(self.live.names());
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    fn provenance(&self, key: &str) -> Option<String> {
+        match self.snapshot.get(key) {
+            Some(_) => Some("last known good snapshot".to_string()),
+            None => self.live.provenance(key),
+        }
+    }
+
+    fn refresh(&self) -> io::Result<()> {
+        self.live.refresh()
+    }
+
+    fn watch_paths(&self) -> Vec<PathBuf> {
+        self.live.watch_paths()
+    }
+
+    fn kinds(&self) -> Vec<&'static str> {
+        let mut kinds = self.live.kinds();
+        kinds.push("snapshot");
+        kinds
+    }
+}
+
+/// Escape "value" for a double-quoted `.env` value.
+fn escape(value: &str) -> String {
+    let mut out = String::new();
+    for c in value.chars() {
+        match c {
+            '\n' => out += "\\n",
+            '\r' => out += "\\r",
+            '\t' => out += "\\t",
+            '"' | '\\' | '$' => {
+                out.push('\\');
+                out.push(c);
+            }
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes() {
+        let value = "a \"b\"\n\t$HOME \\";
+        let text = format!("K=\"{}\"", escape(value));
+        assert_eq!(Dotenv::parse(&text).unwrap().vars["K"], value);
+    }
+}
@@ -0,0 +1,906 @@
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+use crate::watch::{Change, ChangeSet};
+use crate::{debug, interpolate, policy, registry, testing, BiodomeError, TryFromEnv};
+
+/// Where env vars are looked up.
+///
+/// The process environment is used unless another source is installed
+/// with [`set_source`]. Other sources make it easy to test with a fake
+/// environment, or to read settings from somewhere else entirely:
+///
+/// ```rust
+/// use std::collections::HashMap;
+/// use biodome::biodome_from;
+///
+/// let env = HashMap::from([("WORKERS".to_string(), "8".to_string())]);
+/// assert_eq!(biodome_from(&env, "WORKERS", 4), 8);
+/// assert_eq!(biodome_from(&env, "TIMEOUT", 30), 30);
+/// ```
+pub trait EnvSource: Send + Sync {
+    /// The value of "key", or `None` if it is not set.
+    fn get(&self, key: &str) -> Option<String>;
+
+    /// The names of all the keys that are set, for sources that can
+    /// list them. Those that can't return none, which is the default.
+    fn names(&self) -> Vec<String> {
+        vec![]
+    }
+
+    /// Where the value of "key" comes from, such as the file it is in,
+    /// for sources that can tell.
+    fn provenance(&self, _key: &str) -> Option<String> {
+        None
+    }
+
+    /// Read the values again, for sources that keep a copy of them,
+    /// such as the `.env` files installed by
+    /// [`dotenv::load`](crate::dotenv::load). If that fails, the
+    /// source keeps the values it had. The default does nothing.
+    fn refresh(&self) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// The files and directories that hold the values, for sources
+    /// that read them, so that they can be watched for changes. The
+    /// default is none.
+    fn watch_paths(&self) -> Vec<PathBuf> {
+        vec![]
+    }
+
+    /// The kinds of source this is made of, such as `"env"` or
+    /// `"dotenv"`, for the policies set with
+    /// [`allow_sources`](crate::allow_sources). Sources that don't say
+    /// are `"custom"`; a source that reads from Vault might say
+    /// `"vault"`.
+    fn kinds(&self) -> Vec<&'static str> {
+        vec!["custom"]
+    }
+}
+
+/// The environment of the current process, from `std::env`. Values
+/// that are not valid unicode are treated as unset.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StdEnv;
+
+impl EnvSource for StdEnv {
+    fn get(&self, key: &str) -> Option<String> {
+        env::var(key).ok()
+    }
+
+    fn names(&self) -> Vec<String> {
+        env::vars_os()
+            .filter_map(|(key, _)| key.into_string().ok())
+            .collect()
+    }
+
+    fn provenance(&self, key: &str) -> Option<String> {
+        self.get(key).map(|_| "process environment".to_string())
+    }
+
+    fn kinds(&self) -> Vec<&'static str> {
+        vec!["env"]
+    }
+}
+
+impl EnvSource for HashMap<String, String> {
+    fn get(&self, key: &str) -> Option<String> {
+        HashMap::get(self, key).cloned()
+    }
+
+    fn names(&self) -> Vec<String> {
+        HashMap::keys(self).cloned().collect()
+    }
+
+    fn kinds(&self) -> Vec<&'static str> {
+        vec!["memory"]
+    }
+}
+
+impl EnvSource for BTreeMap<String, String> {
+    fn get(&self, key: &str) -> Option<String> {
+        BTreeMap::get(self, key).cloned()
+    }
+
+    fn names(&self) -> Vec<String> {
+        BTreeMap::keys(self).cloned().collect()
+    }
+
+    fn kinds(&self) -> Vec<&'static str> {
+        vec!["memory"]
+    }
+}
+
+impl<S: EnvSource + ?Sized> EnvSource for &S {
+    fn get(&self, key: &str) -> Option<String> {
+        (**self).get(key)
+    }
+
+    fn names(&self) -> Vec<String> {
+        (**self).names()
+    }
+
+    fn provenance(&self, key: &str) -> Option<String> {
+        (**self).provenance(key)
+    }
+
+    fn refresh(&self) -> io::Result<()> {
+        (**self).refresh()
+    }
+
+    fn watch_paths(&self) -> Vec<PathBuf> {
+        (**self).watch_paths()
+    }
+
+    fn kinds(&self) -> Vec<&'static str> {
+        (**self).kinds()
+    }
+}
+
+impl<S: EnvSource + ?Sized> EnvSource for Box<S> {
+    fn get(&self, key: &str) -> Option<String> {
+        (**self).get(key)
+    }
+
+    fn names(&self) -> Vec<String> {
+        (**self).names()
+    }
+
+    fn provenance(&self, key: &str) -> Option<String> {
+        (**self).provenance(key)
+    }
+
+    fn refresh(&self) -> io::Result<()> {
+        (**self).refresh()
+    }
+
+    fn watch_paths(&self) -> Vec<PathBuf> {
+        (**self).watch_paths()
+    }
+
+    fn kinds(&self) -> Vec<&'static str> {
+        (**self).kinds()
+    }
+}
+
+/// Sources in priority order: a key resolves to its value in the
+/// first source that has it.
+///
+/// This is the 12-factor pattern of the environment overriding a
+/// file, which overrides built-in defaults:
+///
+/// ```rust
+/// use std::collections::HashMap;
+/// use biodome::{Layers, StdEnv};
+///
+/// let file = HashMap::from([("WORKERS".to_string(), "8".to_string())]);
+/// let defaults = HashMap::from([
+///     ("WORKERS".to_string(), "2".to_string()),
+///     ("TIMEOUT".to_string(), "30".to_string()),
+/// ]);
+/// let layers = Layers::new().with(StdEnv).with(file).with(defaults);
+/// assert_eq!(layers.biodome("WORKERS", 1), 8);
+/// assert_eq!(layers.biodome("TIMEOUT", 10), 30);
+/// assert_eq!(layers.biodome("RETRIES", 3), 3);
+/// ```
+///
+/// `Layers` is an [`EnvSource`] too, so it can be installed with
+/// [`set_source`] to make the plain `biodome()` use it.
+#[derive(Default)]
+pub struct Layers {
+    sources: Vec<Box<dyn EnvSource>>,
+}
+
+impl Layers {
+    pub fn new() -> Self {
+        Layers::default()
+    }
+
+    /// Add "source" below the sources added so far.
+    ///
+    /// Panics if the [policy](crate::allow_sources) of the active
+    /// profile doesn't allow the source.
+    pub fn with(self, source: impl EnvSource + 'static) -> Self {
+        self.try_with(source)
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Like [`with`](Layers::with), but return an error instead of
+    /// panicking.
+    pub fn try_with(mut self, source: impl EnvSource + 'static) -> Result<Self, BiodomeError> {
+        policy::check(&source.kinds())?;
+        self.sources.push(Box::new(source));
+        Ok(self)
+    }
+
+    /// Like [`biodome`](crate::biodome), but resolve "key" through
+    /// the layers.
+    pub fn biodome<U: From<T>, T: TryFromEnv<U>>(&self, key: &str, default: T) -> U
+    where
+        <T as TryFromEnv<U>>::Error: std::fmt::Debug,
+    {
+        crate::biodome_from(self, key, default)
+    }
+}
+
+impl EnvSource for Layers {
+    fn get(&self, key: &str) -> Option<String> {
+        let found = self
+            .sources
+            .iter()
+            .enumerate()
+            .find_map(|(i, source)| source.get(key).map(|value| (i, value)));
+        debug::log(key, || match &found {
+            Some((i, _)) => format!("found in layer {} of {}", i + 1, self.sources.len()),
+            None => format!("not in any of {} layers", self.sources.len()),
+        });
+        found.map(|(_, value)| value)
+    }
+
+    fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.sources.iter().flat_map(|s| s.names()).collect();
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    fn provenance(&self, key: &str) -> Option<String> {
+        let (i, source) = self
+            .sources
+            .iter()
+            .enumerate()
+            .find(|(_, source)| source.get(key).is_some())?;
+        Some(match source.provenance(key) {
+            Some(inner) => format!("layer {} ({})", i + 1, inner),
+            None => format!("layer {}", i + 1),
+        })
+    }
+
+    fn refresh(&self) -> io::Result<()> {
+        self.sources.iter().try_for_each(|source| source.refresh())
+    }
+
+    fn watch_paths(&self) -> Vec<PathBuf> {
+        self.sources.iter().flat_map(|s| s.watch_paths()).collect()
+    }
+
+    fn kinds(&self) -> Vec<&'static str> {
+        let mut kinds: Vec<&'static str> = self.sources.iter().flat_map(|s| s.kinds()).collect();
+        kinds.sort();
+        kinds.dedup();
+        kinds
+    }
+}
+
+/// A directory where each file is a key and its contents the value,
+/// like a Kubernetes ConfigMap mounted at `/etc/config`.
+///
+/// Files are read when a key is looked up, so updates to the mounted
+/// volume are seen. Values keep their contents as is, apart from a
+/// trailing newline. Kubernetes keeps the real files in hidden
+/// directories and links to them, so names starting with `.` are not
+/// keys.
+///
+/// ```rust,no_run
+/// use biodome::{set_source, ConfigDir, Layers, StdEnv};
+///
+/// set_source(Layers::new().with(StdEnv).with(ConfigDir::new("/etc/config")));
+/// ```
+#[derive(Debug, Clone)]
+pub struct ConfigDir {
+    dir: PathBuf,
+}
+
+impl ConfigDir {
+    pub fn new<P: AsRef<Path>>(dir: P) -> Self {
+        ConfigDir {
+            dir: dir.as_ref().to_path_buf(),
+        }
+    }
+
+    /// The keys of all the files in the directory, sorted.
+    pub fn keys(&self) -> io::Result<Vec<String>> {
+        let mut keys = vec![];
+        for entry in fs::read_dir(&self.dir)? {
+            let entry = entry?;
+            let Ok(name) = entry.file_name().into_string() else {
+                continue;
+            };
+            if !name.starts_with('.') && entry.path().is_file() {
+                keys.push(name);
+            }
+        }
+        keys.sort();
+        Ok(keys)
+    }
+}
+
+impl EnvSource for ConfigDir {
+    fn get(&self, key: &str) -> Option<String> {
+        if key.is_empty() || key.starts_with('.') || key.contains(['/', '\\']) {
+            return None;
+        }
+        let contents = fs::read_to_string(self.dir.join(key)).ok()?;
+        let value = contents.strip_suffix('\n').unwrap_or(&contents);
+        Some(value.strip_suffix('\r').unwrap_or(value).to_string())
+    }
+
+    fn names(&self) -> Vec<String> {
+        self.keys().unwrap_or_default()
+    }
+
+    fn provenance(&self, key: &str) -> Option<String> {
+        self.get(key)
+            .map(|_| self.dir.join(key).display().to_string())
+    }
+
+    fn watch_paths(&self) -> Vec<PathBuf> {
+        vec![self.dir.clone()]
+    }
+
+    fn kinds(&self) -> Vec<&'static str> {
+        vec!["config-dir"]
+    }
+}
+
+/// A copy of the whole process environment, taken once.
+///
+/// Lookups in a snapshot don't go back to the process environment, so
+/// they are cheap, all see the same values even if something calls
+/// `set_var` in the meantime, and can't race with it. This suits
+/// startup, when many settings are read in one go.
+///
+/// ```rust
+/// use biodome::EnvSnapshot;
+///
+/// std::env::set_var("SNAPSHOT_WORKERS", "8");
+/// let env = EnvSnapshot::capture();
+/// std::env::set_var("SNAPSHOT_WORKERS", "16");
+/// assert_eq!(env.biodome("SNAPSHOT_WORKERS", 4), 8);
+/// assert_eq!(env.biodome("SNAPSHOT_TIMEOUT", 30), 30);
+/// ```
+///
+/// Env vars whose name or value is not valid unicode are left out.
+#[derive(Debug, Clone, Default)]
+pub struct EnvSnapshot {
+    vars: BTreeMap<String, String>,
+}
+
+impl EnvSnapshot {
+    /// Copy all env vars of the process.
+    pub fn capture() -> Self {
+        let vars = env::vars_os()
+            .filter_map(|(k, v)| Some((k.into_string().ok()?, v.into_string().ok()?)))
+            .collect();
+        EnvSnapshot { vars }
+    }
+
+    /// Like [`biodome`](crate::biodome), but look up "key" in the
+    /// snapshot.
+    pub fn biodome<U: From<T>, T: TryFromEnv<U>>(&self, key: &str, default: T) -> U
+    where
+        <T as TryFromEnv<U>>::Error: std::fmt::Debug,
+    {
+        crate::biodome_from(self, key, default)
+    }
+
+    /// The keys that were added, removed or changed in "newer", such
+    /// as a snapshot taken after a reload. Values of keys that look
+    /// sensitive, or were read as a [`Secret`](crate::Secret), are
+    /// `[REDACTED]`, so the changes can be logged.
+    ///
+    /// ```rust
+    /// use biodome::EnvSnapshot;
+    ///
+    /// std::env::set_var("DIFF_WORKERS", "8");
+    /// let before = EnvSnapshot::capture();
+    /// std::env::set_var("DIFF_WORKERS", "16");
+    /// let changes = before.diff(&EnvSnapshot::capture());
+    /// assert_eq!(changes.to_string(), "DIFF_WORKERS: '8' -> '16'\n");
+    /// ```
+    pub fn diff(&self, newer: &EnvSnapshot) -> ChangeSet {
+        let keys: BTreeSet<&String> = self.vars.keys().chain(newer.vars.keys()).collect();
+        let changes = keys
+            .into_iter()
+            .filter_map(|key| {
+                let (old, new) = (self.vars.get(key), newer.vars.get(key));
+                if old == new {
+                    return None;
+                }
+                let shown = |value: Option<&String>| match registry::redacted(key) {
+                    true => value.map(|_| "[REDACTED]".to_string()),
+                    false => value.cloned(),
+                };
+                Some(Change {
+                    key: key.clone(),
+                    old: shown(old),
+                    new: shown(new),
+                })
+            })
+            .collect();
+        ChangeSet { changes }
+    }
+}
+
+impl EnvSource for EnvSnapshot {
+    fn get(&self, key: &str) -> Option<String> {
+        self.vars.get(key).cloned()
+    }
+
+    fn names(&self) -> Vec<String> {
+        self.vars.keys().cloned().collect()
+    }
+
+    fn provenance(&self, key: &str) -> Option<String> {
+        self.get(key)
+            .map(|_| "process environment, at capture".to_string())
+    }
+
+    fn kinds(&self) -> Vec<&'static str> {
+        vec!["env"]
+    }
+}
+
+/// How to treat env vars that are set to an empty string.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EmptyValues {
+    /// An empty string is a value like any other.
+    #[default]
+    Value,
+    /// An empty string counts as not set, so the default is used, as
+    /// with `${VAR:-default}` in the shell.
+    Unset,
+}
+
+static EMPTY_VALUES: RwLock<EmptyValues> = RwLock::new(EmptyValues::Value);
+
+/// Use "policy" for empty env vars from now on.
+///
+/// ```rust
+/// use biodome::{biodome, set_empty_values, EmptyValues};
+///
+/// std::env::set_var("EMPTY_WORKERS", "");
+/// set_empty_values(EmptyValues::Unset);
+/// assert_eq!(biodome("EMPTY_WORKERS", 4), 4);
+/// ```
+pub fn set_empty_values(policy: EmptyValues) {
+    *EMPTY_VALUES.write().unwrap() = policy;
+}
+
+/// "value", unless it is empty and empty values count as not set.
+fn non_empty(value: Option<String>) -> Option<String> {
+    let policy = *EMPTY_VALUES.read().unwrap();
+    value.filter(|v| !(policy == EmptyValues::Unset && v.is_empty()))
+}
+
+/// Whether "key" is set in the installed source, either directly or
+/// through `{key}_FILE`. The value is not parsed, and nothing is
+/// recorded, so this is for code that only needs to branch on
+/// whether a variable is there.
+///
+/// Empty values count as set unless [`set_empty_values`] says
+/// otherwise.
+///
+/// ```rust
+/// std::env::set_var("SENTRY_DSN", "https://key@sentry.example/1");
+/// if biodome::is_set("SENTRY_DSN") {
+///     // set up error reporting
+/// }
+/// ```
+pub fn is_set(key: &str) -> bool {
+    non_empty(get(key)).is_some() || non_empty(get(&format!("{}_FILE", key))).is_some()
+}
+
+/// Whether any key starting with "prefix" is set in the installed
+/// source, as for [`is_set`]. Only sources that can list their keys
+/// with [`EnvSource::names`] are searched.
+///
+/// ```rust
+/// std::env::set_var("OTEL_EXPORTER_OTLP_ENDPOINT", "http://collector:4317");
+/// assert!(biodome::any_set("OTEL_"));
+/// ```
+pub fn any_set(prefix: &str) -> bool {
+    installed(|source| source.names())
+        .iter()
+        .any(|name| name.starts_with(prefix) && non_empty(get(name)).is_some())
+}
+
+/// The unparsed value of an env var, and where it came from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawValue {
+    /// The value exactly as set: without `${VAR}` references expanded,
+    /// or a per-profile part picked out.
+    pub value: String,
+    /// Where the value came from, if the source can tell, e.g.
+    /// `layer 2 (/etc/config/PORT)`.
+    pub provenance: Option<String>,
+    /// The file the value was read from, if it was set with
+    /// `{key}_FILE`.
+    pub file: Option<PathBuf>,
+    /// Whether the value is sensitive, and should be redacted in logs
+    /// and reports.
+    pub redacted: bool,
+}
+
+/// The value of "key" as it is set, with no parsing at all, for
+/// callers that pass values on verbatim, e.g. to a child process.
+/// Lookups go through the installed source and `{key}_FILE` as usual,
+/// and are reported to the [observer](crate::set_observer). A
+/// `{key}_FILE` that can't be read is an error.
+///
+/// ```rust
+/// use biodome::biodome_raw;
+///
+/// std::env::set_var("UPSTREAM_URL", "http://${HOST}:8080");
+/// let raw = biodome_raw("UPSTREAM_URL").unwrap().unwrap();
+/// assert_eq!(raw.value, "http://${HOST}:8080");
+/// assert_eq!(raw.provenance.as_deref(), Some("process environment"));
+/// assert!(!raw.redacted);
+/// ```
+pub fn biodome_raw(key: &str) -> Result<Option<RawValue>, BiodomeError> {
+    let found = installed(|source| {
+        if let Some(value) = non_empty(source.get(key)) {
+            return Ok(Some((value, source.provenance(key), None)));
+        }
+        let file_key = format!("{}_FILE", key);
+        let Some(path) = non_empty(source.get(&file_key)) else {
+            return Ok(None);
+        };
+        Ok(Some((
+            read_file(key, &path)?,
+            source.provenance(&file_key),
+            Some(PathBuf::from(path)),
+        )))
+    })?;
+    let Some((value, provenance, file)) = found else {
+        return Ok(None);
+    };
+    debug::log(key, || match &provenance {
+        Some(provenance) => format!("read raw from {}", provenance),
+        None => "read raw".to_string(),
+    });
+    crate::observer::notify(|o| o.on_read(key));
+    Ok(Some(RawValue {
+        value,
+        provenance,
+        file,
+        redacted: registry::redacted(key),
+    }))
+}
+
+static SOURCE: RwLock<Option<Box<dyn EnvSource>>> = RwLock::new(None);
+
+/// Look up all env vars in "source" from now on, instead of in the
+/// process environment.
+///
+/// Panics if the [policy](crate::allow_sources) of the active profile
+/// doesn't allow the source.
+pub fn set_source(source: impl EnvSource + 'static) {
+    policy::check(&source.kinds()).unwrap_or_else(|err| panic!("{}", err));
+    *SOURCE.write().unwrap() = Some(Box::new(source));
+}
+
+/// Go back to looking up env vars in the process environment.
+pub fn reset_source() {
+    *SOURCE.write().unwrap() = None;
+}
+
+/// Read the values of the installed source again, with
+/// [`EnvSource::refresh`].
+pub fn refresh_source() -> io::Result<()> {
+    match SOURCE.read().unwrap().as_ref() {
+        Some(source) => source.refresh(),
+        None => Ok(()),
+    }
+}
+
+/// The paths to watch for changes to the installed source, with
+/// [`EnvSource::watch_paths`].
+#[cfg(feature = "watch")]
+pub(crate) fn watch_paths() -> Vec<PathBuf> {
+    match SOURCE.read().unwrap().as_ref() {
+        Some(source) => source.watch_paths(),
+        None => vec![],
+    }
+}
+
+/// Take the installed source out, leaving the process environment in
+/// its place.
+pub(crate) fn take_source() -> Box<dyn EnvSource> {
+    SOURCE
+        .write()
+        .unwrap()
+        .take()
+        .unwrap_or_else(|| Box::new(StdEnv))
+}
+
+/// Call "f" with the installed source, under the overrides of the
+/// current thread. A thread in a [jail](testing::jail) sees only the jail.
+fn installed<R>(f: impl FnOnce(&dyn EnvSource) -> R) -> R {
+    if let Some(jail) = testing::jailed() {
+        return f(&testing::Overridden(&jail));
+    }
+    let source = SOURCE.read().unwrap();
+    let source: &dyn EnvSource = match source.as_ref() {
+        Some(source) => source.as_ref(),
+        None => &StdEnv,
+    };
+    f(&testing::Overridden(source))
+}
+
+/// The values of the env vars that start with "prefix", for the
+/// active profile, keyed by the rest of their names in lower case.
+///
+/// A `{key}_FILE` var also gives the value of "key", read from the
+/// file, when "key" isn't set itself. If the file can't be read, only
+/// the `{key}_FILE` var is there, as it may not name a secret at all.
+#[cfg(any(feature = "figment", feature = "config"))]
+pub(crate) fn prefixed_values(prefix: &str) -> Result<Vec<(String, String)>, BiodomeError> {
+    let mut values = BTreeMap::new();
+    for name in installed(|source| source.names()) {
+        let Some(key) = name.strip_prefix(prefix).filter(|k| !k.is_empty()) else {
+            continue;
+        };
+        if let Some(raw) = try_value(&name)?.map(crate::profile::select) {
+            values.insert(key.to_lowercase(), raw);
+        }
+        let Some(key) = key.strip_suffix("_FILE").filter(|k| !k.is_empty()) else {
+            continue;
+        };
+        let base = format!("{}{}", prefix, key);
+        if let Ok(Some(raw)) = try_value(&base) {
+            values.insert(key.to_lowercase(), crate::profile::select(raw));
+        }
+    }
+    Ok(values.into_iter().collect())
+}
+
+/// Look up "key" in the installed source.
+pub(crate) fn get(key: &str) -> Option<String> {
+    installed(|source| source.get(key))
+}
+
+/// Look up the value of the setting "key" in the installed source,
+/// as [`lookup`] does.
+pub(crate) fn value(key: &str) -> Option<String> {
+    try_value(key).unwrap_or_else(|err| panic!("{}", err))
+}
+
+/// Like [`value`], but return an error instead of panicking.
+pub(crate) fn try_value(key: &str) -> Result<Option<String>, BiodomeError> {
+    lookup_with(key, &get)
+}
+
+/// Look up the value of the setting "key" in "source". If "key" is not
+/// set but `{key}_FILE` is, the value is read from the file it names,
+/// without surrounding whitespace. This is the convention for Docker
+/// and Kubernetes secrets, e.g. `DB_PASSWORD_FILE=/run/secrets/db`.
+/// `${VAR}` references in the value are then expanded with
+/// [`interpolate`](interpolate::interpolate).
+///
+/// Panics if the file can't be read, or if references are circular.
+pub(crate) fn lookup<S: EnvSource + ?Sized>(source: &S, key: &str) -> Option<String> {
+    lookup_with(key, &|k| source.get(k)).unwrap_or_else(|err| panic!("{}", err))
+}
+
+type Get<'a> = &'a dyn Fn(&str) -> Option<String>;
+
+fn lookup_with(key: &str, get: Get) -> Result<Option<String>, BiodomeError> {
+    let get: Get = &|k| non_empty(get(k));
+    let Some(raw) = with_file(key, get)? else {
+        return Ok(None);
+    };
+    if !raw.contains('$') {
+        return Ok(Some(raw));
+    }
+    // A file named by a referenced variable can fail to read too.
+    let failed = RefCell::new(None);
+    let expanded = interpolate::interpolate(key, &raw, &|name| {
+        with_file(name, get).unwrap_or_else(|err| {
+            failed.borrow_mut().get_or_insert(err);
+            None
+        })
+    });
+    if let Some(err) = failed.into_inner() {
+        return Err(err);
+    }
+    debug::log(key, || "expanded ${...} references".to_string());
+    expanded.map(Some).map_err(|reason| BiodomeError::Parse {
+        key: key.to_string(),
+        reason,
+    })
+}
+
+fn with_file(key: &str, get: Get) -> Result<Option<String>, BiodomeError> {
+    if let Some(value) = get(key) {
+        return Ok(Some(value));
+    }
+    let Some(path) = get(&format!("{}_FILE", key)) else {
+        debug::log(key, || "not set".to_string());
+        return Ok(None);
+    };
+    debug::log(key, || format!("reading the value from {}", path));
+    read_file(key, &path).map(Some)
+}
+
+/// The contents of "path", named by `{key}_FILE`, without surrounding
+/// whitespace.
+fn read_file(key: &str, path: &str) -> Result<String, BiodomeError> {
+    match fs::read_to_string(path) {
+        Ok(contents) => Ok(contents.trim().to_string()),
+        Err(err) => Err(BiodomeError::Parse {
+            key: format!("{}_FILE", key),
+            reason: format!("can't read {}: {}", path, err),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps() {
+        let map = BTreeMap::from([("A".to_string(), "1".to_string())]);
+        assert_eq!(EnvSource::get(&map, "A"), Some("1".to_string()));
+        assert_eq!(EnvSource::get(&map, "B"), None);
+        let source: &dyn EnvSource = &map;
+        assert_eq!(source.get("A"), Some("1".to_string()));
+    }
+
+    #[test]
+    fn env_snapshot() {
+        env::set_var("ENV_SNAPSHOT_A", "1");
+        let snapshot = EnvSnapshot::capture();
+        env::remove_var("ENV_SNAPSHOT_A");
+        assert_eq!(snapshot.get("ENV_SNAPSHOT_A"), Some("1".to_string()));
+        assert!(snapshot.names().contains(&"ENV_SNAPSHOT_A".to_string()));
+        assert_eq!(snapshot.biodome("ENV_SNAPSHOT_A", 0), 1);
+        assert_eq!(snapshot.biodome("ENV_SNAPSHOT_B", 2), 2);
+    }
+
+    #[test]
+    fn env_snapshot_diff() {
+        let before = EnvSnapshot {
+            vars: BTreeMap::from([
+                ("A".to_string(), "1".to_string()),
+                ("B".to_string(), "2".to_string()),
+                ("API_TOKEN".to_string(), "old".to_string()),
+            ]),
+        };
+        let after = EnvSnapshot {
+            vars: BTreeMap::from([
+                ("B".to_string(), "3".to_string()),
+                ("C".to_string(), "4".to_string()),
+                ("API_TOKEN".to_string(), "new".to_string()),
+            ]),
+        };
+        let changes = before.diff(&after);
+        let redacted = Some("[REDACTED]".to_string());
+        assert_eq!(changes.get("API_TOKEN").unwrap().old, redacted);
+        let _ = crate::biodome("DIFF_DB_URL", crate::Secret::new("u:pass".to_string()));
+        let before = EnvSnapshot {
+            vars: BTreeMap::from([("DIFF_DB_URL".to_string(), "u:old".to_string())]),
+        };
+        let after = EnvSnapshot {
+            vars: BTreeMap::from([("DIFF_DB_URL".to_string(), "u:new".to_string())]),
+        };
+        assert_eq!(
+            before.diff(&after).get("DIFF_DB_URL").unwrap().new,
+            redacted
+        );
+        assert_eq!(changes.get("A").unwrap().new, None);
+        assert_eq!(changes.get("C").unwrap().old, None);
+        assert_eq!(
+            changes.to_string(),
+            "A: removed '1'\nAPI_TOKEN: '[REDACTED]' -> '[REDACTED]'\nB: '2' -> '3'\nC: added '4'\n"
+        );
+        assert!(before.diff(&before).is_empty());
+    }
+
+    #[test]
+    fn layers() {
+        let top = BTreeMap::from([("A".to_string(), "top".to_string())]);
+        let bottom = BTreeMap::from([
+            ("A".to_string(), "bottom".to_string()),
+            ("B".to_string(), "bottom".to_string()),
+        ]);
+        let layers = Layers::new().with(top).with(bottom);
+        assert_eq!(layers.get("A"), Some("top".to_string()));
+        assert_eq!(layers.get("B"), Some("bottom".to_string()));
+        assert_eq!(layers.get("C"), None);
+        assert_eq!(layers.names(), vec!["A", "B"]);
+        assert_eq!(layers.provenance("B"), Some("layer 2".to_string()));
+        assert_eq!(layers.provenance("C"), None);
+        assert_eq!(Layers::new().get("A"), None);
+    }
+
+    #[test]
+    fn config_dir() {
+        let dir = env::temp_dir().join(format!("biodome-configmap-{}", std::process::id()));
+        fs::create_dir_all(dir.join("..data")).unwrap();
+        fs::write(dir.join("LOG_LEVEL"), "debug\n").unwrap();
+        fs::write(dir.join("MOTD"), "two\nlines\n").unwrap();
+        fs::write(dir.join(".hidden"), "x").unwrap();
+        let source = ConfigDir::new(&dir);
+        let keys = source.keys().unwrap();
+        let values: Vec<_> = ["LOG_LEVEL", "MOTD", ".hidden", "MISSING", "../x", ""]
+            .iter()
+            .map(|key| source.get(key))
+            .collect();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(keys, ["LOG_LEVEL", "MOTD"]);
+        assert_eq!(
+            values,
+            [
+                Some("debug".to_string()),
+                Some("two\nlines".to_string()),
+                None,
+                None,
+                None,
+                None
+            ]
+        );
+    }
+
+    #[test]
+    fn files() {
+        let path = env::temp_dir().join(format!("biodome-secret-{}", std::process::id()));
+        fs::write(&path, "hunter2\n").unwrap();
+        let source = BTreeMap::from([
+            ("A".to_string(), "1".to_string()),
+            ("A_FILE".to_string(), "/nonexistent".to_string()),
+            ("B_FILE".to_string(), path.display().to_string()),
+            ("C_FILE".to_string(), "/nonexistent".to_string()),
+        ]);
+        assert_eq!(lookup(&source, "B"), Some("hunter2".to_string()));
+        assert_eq!(lookup(&source, "A"), Some("1".to_string()));
+        assert_eq!(lookup(&source, "D"), None);
+        let refs = BTreeMap::from([
+            ("URL".to_string(), "http://${HOST}:${PORT:-80}/".to_string()),
+            ("HOST".to_string(), "${B}".to_string()),
+            ("B_FILE".to_string(), path.display().to_string()),
+        ]);
+        let url = lookup(&refs, "URL");
+        fs::remove_file(&path).unwrap();
+        assert_eq!(url, Some("http://hunter2:80/".to_string()));
+        let err = std::panic::catch_unwind(|| lookup(&source, "C")).unwrap_err();
+        let msg = err.downcast_ref::<String>().unwrap();
+        assert!(msg.starts_with("Failed to parse env var C_FILE: can't read /nonexistent"));
+    }
+
+    #[test]
+    fn raw() {
+        let path = env::temp_dir().join(format!("biodome-raw-{}", std::process::id()));
+        fs::write(&path, "${NOT_EXPANDED}\n").unwrap();
+        env::set_var("RAW_API_TOKEN_FILE", &path);
+        let raw = biodome_raw("RAW_API_TOKEN");
+        fs::remove_file(&path).unwrap();
+        assert_eq!(
+            raw,
+            Ok(Some(RawValue {
+                value: "${NOT_EXPANDED}".to_string(),
+                provenance: Some("process environment".to_string()),
+                file: Some(path),
+                redacted: true,
+            }))
+        );
+        assert_eq!(biodome_raw("RAW_MISSING"), Ok(None));
+        env::set_var("RAW_CERT_FILE", "/nonexistent/cert.pem");
+        let err = biodome_raw("RAW_CERT").unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("RAW_CERT_FILE: can't read /nonexistent"));
+        env::remove_var("RAW_CERT_FILE");
+        env::set_var("RAW_SIGNING", "abc");
+        crate::biodome("RAW_SIGNING", crate::Secret::new(String::new()));
+        assert!(biodome_raw("RAW_SIGNING").unwrap().unwrap().redacted);
+        env::remove_var("RAW_SIGNING");
+    }
+}
@@ -0,0 +1,304 @@
+//! Hermetic configuration tests.
+//!
+//! [`jail`] runs a closure with its own environment, in place of the
+//! process environment, and its own temporary directory for `.env`
+//! files and config dirs. Everything is undone when the closure
+//! returns or panics, and a jail only applies to the thread that runs
+//! it, so tests can't see each other's settings even when they run in
+//! parallel, whether they use jails or not.
+//!
+//! ```rust
+//! use biodome::{biodome, testing::jail};
+//!
+//! jail(|env| {
+//!     env.set("WORKERS", "8");
+//!     env.dotenv(".env", "TIMEOUT=5\n").unwrap();
+//!     assert_eq!(biodome("WORKERS", 4), 8);
+//!     assert_eq!(biodome("TIMEOUT", 30), 5);
+//!     assert_eq!(biodome("RETRIES", 3), 3);
+//! });
+//! ```
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::env;
+use std::ffi::OsString;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+
+use crate::dotenv::Dotenv;
+use crate::source::EnvSource;
+use crate::{policy, ConfigDir};
+
+static TURN: Mutex<()> = Mutex::new(());
+static COUNT: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    static JAIL: RefCell<Option<Arc<RwLock<State>>>> = const { RefCell::new(None) };
+}
+
+/// Run "f" in a jail, and return what it returns.
+///
+/// Inside the jail, env vars are looked up only in what "f" sets up
+/// through the [`Jail`]: the process environment and the installed
+/// source are hidden, and left untouched. Only the current thread is
+/// in the jail; other threads, including those "f" spawns, see the
+/// environment as usual. When "f" is done, the jail's directory is
+/// deleted.
+///
+/// Panics if the [policy](crate::allow_sources) of the active profile
+/// doesn't allow the `test` kind of source.
+pub fn jail<R>(f: impl FnOnce(&mut Jail) -> R) -> R {
+    policy::check(&Cell::KINDS).unwrap_or_else(|err| panic!("{}", err));
+    let dir = env::temp_dir().join(format!(
+        "biodome-jail-{}-{}",
+        std::process::id(),
+        COUNT.fetch_add(1, Ordering::Relaxed)
+    ));
+    fs::create_dir_all(&dir).expect("failed to create the jail directory");
+    let state = Arc::new(RwLock::new(State::default()));
+    let mut jail = Jail {
+        dir,
+        state: state.clone(),
+        outside: JAIL.with(|j| j.replace(Some(state))),
+    };
+    f(&mut jail)
+}
+
+/// The jail of the current thread, if it is in one.
+pub(crate) fn jailed() -> Option<Cell> {
+    JAIL.with(|j| j.borrow().clone()).map(Cell)
+}
+
+/// Run "f" with the process env vars in "vars" set, and return what
+/// it returns.
+///
+/// Unlike a [`jail`], this changes the real process environment, so
+/// the values are seen by everything, including child processes and
+/// code that reads `std::env` directly. The vars get their old values
+/// back, or are unset again, when "f" returns or panics. Calls take
+/// turns with each other, so they can't race, but they can't be
+/// nested either.
+///
+/// ```rust
+/// use biodome::{biodome, testing::with_vars};
+///
+/// let workers = with_vars(&[("VARS_WORKERS", "8")], || biodome("VARS_WORKERS", 4));
+/// assert_eq!(workers, 8);
+/// assert!(std::env::var("VARS_WORKERS").is_err());
+/// ```
+pub fn with_vars<R>(vars: &[(&str, &str)], f: impl FnOnce() -> R) -> R {
+    let _turn = TURN.lock().unwrap_or_else(|err| err.into_inner());
+    let mut restore = Restore(vec![]);
+    for (key, value) in vars {
+        restore.0.push((key.to_string(), env::var_os(key)));
+        env::set_var(key, value);
+    }
+    f()
+}
+
+/// The values to put back when [`with_vars`] is done, in the order
+/// they were replaced.
+struct Restore(Vec<(String, Option<OsString>)>);
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        for (key, value) in self.0.drain(..).rev() {
+            match value {
+                Some(value) => env::set_var(key, value),
+                None => env::remove_var(key),
+            }
+        }
+    }
+}
+
+thread_local! {
+    static OVERRIDES: RefCell<BTreeMap<String, String>> = const { RefCell::new(BTreeMap::new()) };
+}
+
+/// Set the env var "key" to "value", for lookups on the current thread
+/// only.
+///
+/// Overrides are looked up before the installed source, and the
+/// process environment is never touched, so tests that use them can
+/// run in parallel without seeing each other's values. The test
+/// runner gives each test a thread of its own; where threads are
+/// reused, clear the overrides with [`clear_overrides`].
+///
+/// ```rust
+/// use biodome::{biodome, testing::override_var};
+///
+/// override_var("OVERRIDE_WORKERS", "8");
+/// assert_eq!(biodome("OVERRIDE_WORKERS", 4), 8);
+/// let other = std::thread::spawn(|| biodome("OVERRIDE_WORKERS", 4));
+/// assert_eq!(other.join().unwrap(), 4);
+/// ```
+pub fn override_var(key: &str, value: &str) {
+    OVERRIDES.with(|o| o.borrow_mut().insert(key.to_string(), value.to_string()));
+}
+
+/// Remove the override of "key" on the current thread, if there is one.
+pub fn remove_override(key: &str) {
+    OVERRIDES.with(|o| o.borrow_mut().remove(key));
+}
+
+/// Remove all overrides on the current thread.
+pub fn clear_overrides() {
+    OVERRIDES.with(|o| o.borrow_mut().clear());
+}
+
+/// An installed source under the overrides of the current thread.
+pub(crate) struct Overridden<'a>(pub(crate) &'a dyn EnvSource);
+
+impl Overridden<'_> {
+    fn get_override(&self, key: &str) -> Option<String> {
+        OVERRIDES.with(|o| o.borrow().get(key).cloned())
+    }
+}
+
+impl EnvSource for Overridden<'_> {
+    fn get(&self, key: &str) -> Option<String> {
+        self.get_override(key).or_else(|| self.0.get(key))
+    }
+
+    fn names(&self) -> Vec<String> {
+        let mut names = self.0.names();
+        OVERRIDES.with(|o| names.extend(o.borrow().keys().cloned()));
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    fn provenance(&self, key: &str) -> Option<String> {
+        match self.get_override(key) {
+            Some(_) => Some("thread-local override".to_string()),
+            None => self.0.provenance(key),
+        }
+    }
+}
+
+/// The environment of a [`jail`].
+pub struct Jail {
+    dir: PathBuf,
+    state: Arc<RwLock<State>>,
+    /// The jail the thread was in before, if any.
+    outside: Option<Arc<RwLock<State>>>,
+}
+
+impl Jail {
+    /// The jail's temporary directory. Relative paths given to the
+    /// other methods are inside it.
+    pub fn directory(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Set the env var "key" to "value".
+    pub fn set(&mut self, key: &str, value: &str) {
+        let mut state = self.state.write().unwrap();
+        state.vars.insert(key.to_string(), value.to_string());
+    }
+
+    /// Unset the env var "key", if it was set with [`set`](Jail::set).
+    pub fn remove(&mut self, key: &str) {
+        self.state.write().unwrap().vars.remove(key);
+    }
+
+    /// Write a file at "path" with "contents", and return its full
+    /// path, e.g. for a `{key}_FILE` var.
+    pub fn create_file<P: AsRef<Path>>(&mut self, path: P, contents: &str) -> io::Result<PathBuf> {
+        let path = self.dir.join(path);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, contents)?;
+        Ok(path)
+    }
+
+    /// Write a `.env` file at "path" with "contents", and look up env
+    /// vars in it too. Vars set with [`set`](Jail::set) win over
+    /// files, and files added earlier win over later ones.
+    pub fn dotenv<P: AsRef<Path>>(&mut self, path: P, contents: &str) -> io::Result<PathBuf> {
+        let path = self.create_file(path, contents)?;
+        let dotenv = Dotenv::from_path(&path)?;
+        self.state.write().unwrap().files.push(Box::new(dotenv));
+        Ok(path)
+    }
+
+    /// Create a [`ConfigDir`] at "path", with a file for each of
+    /// "vars", and look up env vars in it too, as for
+    /// [`dotenv`](Jail::dotenv).
+    pub fn config_dir<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        vars: &[(&str, &str)],
+    ) -> io::Result<PathBuf> {
+        let dir = self.dir.join(path);
+        fs::create_dir_all(&dir)?;
+        for (key, value) in vars {
+            fs::write(dir.join(key), value)?;
+        }
+        self.state
+            .write()
+            .unwrap()
+            .files
+            .push(Box::new(ConfigDir::new(&dir)));
+        Ok(dir)
+    }
+}
+
+impl Drop for Jail {
+    fn drop(&mut self) {
+        let outside = self.outside.take();
+        JAIL.with(|j| *j.borrow_mut() = outside);
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+#[derive(Default)]
+struct State {
+    vars: BTreeMap<String, String>,
+    files: Vec<Box<dyn EnvSource>>,
+}
+
+/// The source of a thread that is in a jail.
+pub(crate) struct Cell(Arc<RwLock<State>>);
+
+impl Cell {
+    const KINDS: [&'static str; 1] = ["test"];
+}
+
+impl EnvSource for Cell {
+    fn get(&self, key: &str) -> Option<String> {
+        let state = self.0.read().unwrap();
+        state
+            .vars
+            .get(key)
+            .cloned()
+            .or_else(|| state.files.iter().find_map(|f| f.get(key)))
+    }
+
+    fn names(&self) -> Vec<String> {
+        let state = self.0.read().unwrap();
+        let mut names: Vec<String> = state.vars.keys().cloned().collect();
+        names.extend(state.files.iter().flat_map(|f| f.names()));
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    fn provenance(&self, key: &str) -> Option<String> {
+        let state = self.0.read().unwrap();
+        if state.vars.contains_key(key) {
+            return Some("jail".to_string());
+        }
+        let file = state.files.iter().find(|f| f.get(key).is_some())?;
+        file.provenance(key)
+    }
+
+    fn kinds(&self) -> Vec<&'static str> {
+        Cell::KINDS.to_vec()
+    }
+}
@@ -0,0 +1,456 @@
+use std::collections::{BTreeMap, HashMap};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::ops::Deref;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+
+use toml::value::Datetime;
+use toml::Value;
+
+use crate::{rawconv, ByteSize};
+
+/// Types that can be part of a structured env var: the items of a
+/// `Vec<T>`, the values of a `HashMap<String, T>`, and so on.
+///
+/// Structured values are written as TOML, so this converts from one
+/// TOML value. Collections implement it too, which is what makes
+/// nested values like `HashMap<String, Vec<String>>` work.
+///
+/// To use your own type inside collections, implement this trait:
+///
+/// ```rust
+/// use biodome::{biodome, FromTomlValue};
+///
+/// #[derive(Debug, PartialEq)]
+/// struct Port(u16);
+///
+/// impl FromTomlValue for Port {
+///     fn from_toml(value: &biodome::toml::Value) -> Result<Self, &'static str> {
+///         u16::from_toml(value).map(Port)
+///     }
+/// }
+///
+/// std::env::set_var("PORTS", "[80, 443]");
+/// let PORTS: Vec<Port> = biodome("PORTS", vec![]);
+/// assert_eq!(PORTS, vec![Port(80), Port(443)]);
+/// ```
+pub trait FromTomlValue: Sized {
+    fn from_toml(value: &Value) -> Result<Self, &'static str>;
+
+    /// The value as TOML, so that defaults can be written back out.
+    fn to_toml(&self) -> Option<Value> {
+        None
+    }
+}
+
+macro_rules! impl_from_toml_int {
+    ($($t:ty),*) => {
+        $(
+            impl FromTomlValue for $t {
+                fn from_toml(value: &Value) -> Result<Self, &'static str> {
+                    match value {
+                        Value::Integer(i) => <$t>::try_from(*i).map_err(|_err| "integer out of range"),
+                        _ => Err("expected an integer"),
+                    }
+                }
+
+                fn to_toml(&self) -> Option<Value> {
+                    i64::try_from(*self).ok().map(Value::Integer)
+                }
+            }
+        )*
+    };
+}
+
+impl_from_toml_int!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
+
+macro_rules! impl_from_toml_float {
+    ($($t:ty),*) => {
+        $(
+            impl FromTomlValue for $t {
+                fn from_toml(value: &Value) -> Result<Self, &'static str> {
+                    match value {
+                        Value::Float(f) => Ok(*f as $t),
+                        Value::Integer(i) => Ok(*i as $t),
+                        _ => Err("expected a number"),
+                    }
+                }
+
+                fn to_toml(&self) -> Option<Value> {
+                    Some(Value::Float(*self as f64))
+                }
+            }
+        )*
+    };
+}
+
+impl_from_toml_float!(f32, f64);
+
+impl FromTomlValue for bool {
+    fn from_toml(value: &Value) -> Result<Self, &'static str> {
+        value.as_bool().ok_or("expected a boolean")
+    }
+
+    fn to_toml(&self) -> Option<Value> {
+        Some(Value::Boolean(*self))
+    }
+}
+
+/// Other scalars are accepted too, so `[8080, "x"]` can be read as a
+/// `Vec<String>`.
+impl FromTomlValue for String {
+    fn from_toml(value: &Value) -> Result<Self, &'static str> {
+        match value {
+            Value::String(s) => Ok(s.clone()),
+            Value::Integer(_) | Value::Float(_) | Value::Boolean(_) | Value::Datetime(_) => {
+                Ok(value.to_string())
+            }
+            _ => Err("expected a string"),
+        }
+    }
+
+    fn to_toml(&self) -> Option<Value> {
+        Some(Value::String(self.clone()))
+    }
+}
+
+impl FromTomlValue for char {
+    fn from_toml(value: &Value) -> Result<Self, &'static str> {
+        let s = value.as_str().ok_or("expected a string")?;
+        rawconv::to_prim(s)
+    }
+
+    fn to_toml(&self) -> Option<Value> {
+        Some(Value::String(self.to_string()))
+    }
+}
+
+impl FromTomlValue for Datetime {
+    fn from_toml(value: &Value) -> Result<Self, &'static str> {
+        value.as_datetime().cloned().ok_or("expected a datetime")
+    }
+
+    fn to_toml(&self) -> Option<Value> {
+        Some(Value::Datetime(self.clone()))
+    }
+}
+
+/// Implement `FromTomlValue` for types written as TOML strings that
+/// parse with `FromStr`.
+macro_rules! impl_from_toml_str {
+    ($($t:ty),*) => {
+        $(
+            impl FromTomlValue for $t {
+                fn from_toml(value: &Value) -> Result<Self, &'static str> {
+                    let s = value.as_str().ok_or("expected a string")?;
+                    rawconv::to_prim(s.trim())
+                }
+
+                fn to_toml(&self) -> Option<Value> {
+                    Some(Value::String(self.to_string()))
+                }
+            }
+        )*
+    };
+}
+
+impl_from_toml_str!(IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr);
+
+impl FromTomlValue for PathBuf {
+    fn from_toml(value: &Value) -> Result<Self, &'static str> {
+        value
+            .as_str()
+            .map(rawconv::to_path)
+            .ok_or("expected a string")
+    }
+
+    fn to_toml(&self) -> Option<Value> {
+        self.to_str().map(|s| Value::String(s.to_string()))
+    }
+}
+
+/// Either a number of bytes, or a string like `"8MiB"`.
+impl FromTomlValue for ByteSize {
+    fn from_toml(value: &Value) -> Result<Self, &'static str> {
+        match value {
+            Value::Integer(i) => u64::try_from(*i)
+                .map(ByteSize)
+                .map_err(|_err| "integer out of range"),
+            Value::String(s) => rawconv::to_bytes(s).map(ByteSize),
+            _ => Err("expected a byte size"),
+        }
+    }
+
+    fn to_toml(&self) -> Option<Value> {
+        i64::try_from(self.0).ok().map(Value::Integer)
+    }
+}
+
+/// Either a number of seconds, or a string like `"500ms"`.
+impl FromTomlValue for Duration {
+    fn from_toml(value: &Value) -> Result<Self, &'static str> {
+        match value {
+            Value::Integer(i) => u64::try_from(*i)
+                .map(Duration::from_secs)
+                .map_err(|_err| "integer out of range"),
+            Value::String(s) => rawconv::to_duration(s),
+            _ => Err("expected a duration"),
+        }
+    }
+
+    fn to_toml(&self) -> Option<Value> {
+        Some(Value::String(rawconv::from_duration(*self)))
+    }
+}
+
+/// Any TOML value, as is. Useful for tables whose entries have
+/// different types, like `{ name = "a", weight = 1 }`.
+impl FromTomlValue for Value {
+    fn from_toml(value: &Value) -> Result<Self, &'static str> {
+        Ok(value.clone())
+    }
+
+    fn to_toml(&self) -> Option<Value> {
+        Some(self.clone())
+    }
+}
+
+/// An item of a structured env var that is read with its `FromStr`
+/// impl, for types that don't implement [`FromTomlValue`]. Before
+/// 0.3, items of a `Vec<T>` or `HashMap<String, T>` were read this
+/// way; wrapping them keeps that working.
+///
+/// ```rust
+/// use biodome::{biodome, FromStrValue};
+/// use std::net::SocketAddrV4;
+///
+/// std::env::set_var("FROMSTR_HOSTS", r#"["10.0.0.1:80", "10.0.0.2:80"]"#);
+/// let hosts: Vec<FromStrValue<SocketAddrV4>> = biodome("FROMSTR_HOSTS", vec![]);
+/// assert_eq!(hosts[1].port(), 80);
+/// ```
+///
+/// Scalars that aren't strings, like the `1` in `[1, 2]`, are parsed
+/// from how they are written in TOML.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub struct FromStrValue<T>(pub T);
+
+impl<T> Deref for FromStrValue<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: FromStr> FromTomlValue for FromStrValue<T> {
+    fn from_toml(value: &Value) -> Result<Self, &'static str> {
+        let s = match value {
+            Value::String(s) => s.clone(),
+            Value::Array(_) | Value::Table(_) => return Err("expected a string"),
+            other => other.to_string(),
+        };
+        s.parse().map(FromStrValue).map_err(|_err| "parse error")
+    }
+}
+
+impl<T: FromTomlValue> FromTomlValue for Vec<T> {
+    fn from_toml(value: &Value) -> Result<Self, &'static str> {
+        let array = value.as_array().ok_or("expected an array")?;
+        array.iter().map(T::from_toml).collect()
+    }
+
+    fn to_toml(&self) -> Option<Value> {
+        self.iter()
+            .map(T::to_toml)
+            .collect::<Option<Vec<_>>>()
+            .map(Value::Array)
+    }
+}
+
+/// Implement `FromTomlValue` for tuples, read from arrays with one
+/// element per field, e.g. `["host.example", 443]`.
+macro_rules! impl_from_toml_tuple {
+    ($($len:literal => ($($t:ident $i:tt),+)),*) => {
+        $(
+            impl<$($t: FromTomlValue),+> FromTomlValue for ($($t,)+) {
+                fn from_toml(value: &Value) -> Result<Self, &'static str> {
+                    let array = value.as_array().ok_or("expected an array")?;
+                    if array.len() != $len {
+                        return Err(concat!("expected an array of ", $len, " elements"));
+                    }
+                    Ok(($($t::from_toml(&array[$i])?,)+))
+                }
+
+                fn to_toml(&self) -> Option<Value> {
+                    Some(Value::Array(vec![$(self.$i.to_toml()?),+]))
+                }
+            }
+        )*
+    };
+}
+
+impl_from_toml_tuple!(
+    2 => (A 0, B 1),
+    3 => (A 0, B 1, C 2),
+    4 => (A 0, B 1, C 2, D 3)
+);
+
+impl<T: FromTomlValue> FromTomlValue for HashMap<String, T> {
+    fn from_toml(value: &Value) -> Result<Self, &'static str> {
+        from_table(value)
+    }
+
+    fn to_toml(&self) -> Option<Value> {
+        to_table(self.iter())
+    }
+}
+
+impl<T: FromTomlValue> FromTomlValue for BTreeMap<String, T> {
+    fn from_toml(value: &Value) -> Result<Self, &'static str> {
+        from_table(value)
+    }
+
+    fn to_toml(&self) -> Option<Value> {
+        to_table(self.iter())
+    }
+}
+
+pub(crate) fn from_table<T: FromTomlValue, M: FromIterator<(String, T)>>(
+    value: &Value,
+) -> Result<M, &'static str> {
+    let table = value.as_table().ok_or("expected a table")?;
+    table
+        .iter()
+        .map(|(k, v)| T::from_toml(v).map(|v| (k.clone(), v)))
+        .collect()
+}
+
+fn to_table<'a, T: FromTomlValue + 'a>(
+    entries: impl Iterator<Item = (&'a String, &'a T)>,
+) -> Option<Value> {
+    entries
+        .map(|(k, v)| v.to_toml().map(|v| (k.clone(), v)))
+        .collect::<Option<toml::value::Table>>()
+        .map(Value::Table)
+}
+
+/// Write "value" on a single line, the way it would be given in an
+/// env var: tables are written as inline tables.
+pub(crate) fn to_inline(value: &Value) -> String {
+    match value {
+        Value::Array(items) => {
+            let items: Vec<String> = items.iter().map(to_inline).collect();
+            format!("[{}]", items.join(", "))
+        }
+        Value::Table(table) if table.is_empty() => "{}".to_string(),
+        Value::Table(table) => {
+            let entries: Vec<String> = table
+                .iter()
+                .map(|(k, v)| format!("{} = {}", inline_key(k), to_inline(v)))
+                .collect();
+            format!("{{ {} }}", entries.join(", "))
+        }
+        other => other.to_string(),
+    }
+}
+
+pub(crate) fn inline_key(key: &str) -> String {
+    let bare = !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    if bare {
+        key.to_string()
+    } else {
+        Value::String(key.to_string()).to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(s: &str) -> Value {
+        format!("x = {}", s).parse::<Value>().unwrap()["x"].clone()
+    }
+
+    #[test]
+    fn from_str_values() {
+        let x = FromStrValue::<u8>::from_toml(&parse("\"7\""));
+        assert_eq!(x, Ok(FromStrValue(7)));
+        let x = FromStrValue::<String>::from_toml(&parse("8080"));
+        assert_eq!(x, Ok(FromStrValue("8080".to_string())));
+        let x = FromStrValue::<u8>::from_toml(&parse("\"x\""));
+        assert_eq!(x, Err("parse error"));
+        let x = FromStrValue::<String>::from_toml(&parse("[1]"));
+        assert_eq!(x, Err("expected a string"));
+    }
+
+    #[test]
+    fn scalars() {
+        assert_eq!(i32::from_toml(&parse("5")), Ok(5));
+        assert_eq!(u8::from_toml(&parse("300")), Err("integer out of range"));
+        assert_eq!(f64::from_toml(&parse("5")), Ok(5.0));
+        assert_eq!(String::from_toml(&parse("\"a\"")), Ok("a".to_string()));
+        assert_eq!(String::from_toml(&parse("8080")), Ok("8080".to_string()));
+        assert_eq!(bool::from_toml(&parse("1")), Err("expected a boolean"));
+        assert_eq!(
+            SocketAddr::from_toml(&parse("\"127.0.0.1:80\"")),
+            Ok(SocketAddr::from(([127, 0, 0, 1], 80)))
+        );
+        assert_eq!(ByteSize::from_toml(&parse("\"1KiB\"")), Ok(ByteSize(1024)));
+    }
+
+    #[test]
+    fn nested() {
+        let routes: HashMap<String, Vec<String>> = HashMap::from_toml(&parse(
+            r#"{ api = ["10.0.0.1", "10.0.0.2"], web = ["10.0.1.1"] }"#,
+        ))
+        .unwrap();
+        assert_eq!(routes["api"], vec!["10.0.0.1", "10.0.0.2"]);
+        assert_eq!(routes["web"], vec!["10.0.1.1"]);
+        let bad: Result<HashMap<String, Vec<i32>>, _> = HashMap::from_toml(&parse("{ a = 1 }"));
+        assert_eq!(bad, Err("expected an array"));
+    }
+
+    #[test]
+    fn array_of_tables() {
+        let value = parse(r#"[{name = "a", weight = 1}, {name = "b", weight = 2}]"#);
+        let backends: Vec<HashMap<String, String>> = Vec::from_toml(&value).unwrap();
+        assert_eq!(backends[1]["name"], "b");
+        assert_eq!(backends[1]["weight"], "2");
+        let backends: Vec<BTreeMap<String, Value>> = Vec::from_toml(&value).unwrap();
+        assert_eq!(backends[0]["weight"].as_integer(), Some(1));
+        let bad: Result<Vec<HashMap<String, i32>>, _> = Vec::from_toml(&value);
+        assert_eq!(bad, Err("expected an integer"));
+    }
+
+    #[test]
+    fn tuples() {
+        let origin: (String, u16) =
+            FromTomlValue::from_toml(&parse(r#"["host.example", 443]"#)).unwrap();
+        assert_eq!(origin, ("host.example".to_string(), 443));
+        let point: (f64, f64, f64) = FromTomlValue::from_toml(&parse("[1.5, 2, -3]")).unwrap();
+        assert_eq!(point, (1.5, 2.0, -3.0));
+        let short: Result<(f64, f64), _> = FromTomlValue::from_toml(&parse("[1.5]"));
+        assert_eq!(short, Err("expected an array of 2 elements"));
+        let wrong: Result<(String, u16), _> = FromTomlValue::from_toml(&parse(r#"["a", "b"]"#));
+        assert_eq!(wrong, Err("expected an integer"));
+        assert_eq!(
+            to_inline(&("a".to_string(), 1).to_toml().unwrap()),
+            r#"["a", 1]"#
+        );
+    }
+
+    #[test]
+    fn inline() {
+        let value = parse(r#"{ a = [1, 2], "b c" = { d = "e" }, f = {} }"#);
+        assert_eq!(
+            to_inline(&value),
+            r#"{ a = [1, 2], "b c" = { d = "e" }, f = {} }"#
+        );
+        let map = BTreeMap::from([("k".to_string(), vec![1.5])]);
+        assert_eq!(to_inline(&map.to_toml().unwrap()), "{ k = [1.5] }");
+    }
+}
@@ -0,0 +1,60 @@
+use std::fmt::Display;
+use std::ops::RangeInclusive;
+
+/// A check on a value after it has been parsed, for values that parse
+/// fine but make no sense, like a port of 0 or a negative timeout.
+///
+/// Closures that return `bool` are validators. Implement the trait
+/// directly to give a reason for the failure:
+///
+/// ```rust
+/// use biodome::{biodome_validated, Validator};
+///
+/// struct Between(u32, u32);
+///
+/// impl Validator<u32> for Between {
+///     fn validate(&self, value: &u32) -> Result<(), String> {
+///         if (self.0..=self.1).contains(value) {
+///             Ok(())
+///         } else {
+///             Err(format!("must be between {} and {}", self.0, self.1))
+///         }
+///     }
+/// }
+///
+/// std::env::set_var("POOL_SIZE", "500");
+/// let err = biodome_validated("POOL_SIZE", 10u32, Between(1, 100)).unwrap_err();
+/// assert_eq!(
+///     err.to_string(),
+///     "Invalid value for env var POOL_SIZE: must be between 1 and 100"
+/// );
+/// ```
+pub trait Validator<T> {
+    fn validate(&self, value: &T) -> Result<(), String>;
+}
+
+impl<T, F: Fn(&T) -> bool> Validator<T> for F {
+    fn validate(&self, value: &T) -> Result<(), String> {
+        if self(value) {
+            Ok(())
+        } else {
+            Err("failed validation".to_string())
+        }
+    }
+}
+
+/// A range validates values within it.
+impl<T: PartialOrd + Display> Validator<T> for RangeInclusive<T> {
+    fn validate(&self, value: &T) -> Result<(), String> {
+        if self.contains(value) {
+            Ok(())
+        } else {
+            Err(format!(
+                "{} is not between {} and {}",
+                value,
+                self.start(),
+                self.end()
+            ))
+        }
+    }
+}
@@ -0,0 +1,382 @@
+//! Notice when env vars change.
+//!
+//! A [`Watcher`] compares the current values of a set of keys with the
+//! values it saw last, each time it is polled. This suits sources
+//! whose values change while the process runs, like a [`ConfigDir`]
+//! mounted from a ConfigMap, or an installed source that is replaced.
+//!
+//! Changes are coalesced: when a reload touches many keys, possibly
+//! over several polls, subscribers get one [`ChangeSet`] once the
+//! values have been stable for the debounce window, rather than one
+//! call per key.
+//!
+//! [`ConfigDir`]: crate::ConfigDir
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::{registry, source};
+
+/// A key whose value changed. `None` means the key is not set.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Change {
+    pub key: String,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+/// The changes delivered together to subscribers, in key order.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ChangeSet {
+    pub changes: Vec<Change>,
+}
+
+impl ChangeSet {
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// The change to "key", if it changed.
+    pub fn get(&self, key: &str) -> Option<&Change> {
+        self.changes.iter().find(|c| c.key == key)
+    }
+
+    /// The changes to keys that match "pattern", as for
+    /// [`Watcher::subscribe_to`].
+    pub fn matching(&self, pattern: &str) -> ChangeSet {
+        ChangeSet {
+            changes: self
+                .changes
+                .iter()
+                .filter(|c| glob_match(pattern, &c.key))
+                .cloned()
+                .collect(),
+        }
+    }
+}
+
+/// One line per change, for logs: `KEY: 'old' -> 'new'`, or
+/// `KEY: added 'new'` and `KEY: removed 'old'`. Values that must be
+/// kept out of logs, like those of [`Secret`](crate::Secret)s, are
+/// shown as `[REDACTED]`.
+impl fmt::Display for ChangeSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for change in &self.changes {
+            let redacted = registry::redacted(&change.key);
+            let shown = |value: &Option<String>| match redacted {
+                true => value.as_ref().map(|_| "[REDACTED]".to_string()),
+                false => value.clone(),
+            };
+            match (shown(&change.old), shown(&change.new)) {
+                (None, Some(new)) => writeln!(f, "{}: added '{}'", change.key, new)?,
+                (Some(old), None) => writeln!(f, "{}: removed '{}'", change.key, old)?,
+                (Some(old), Some(new)) => writeln!(f, "{}: '{}' -> '{}'", change.key, old, new)?,
+                (None, None) => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+struct Subscriber {
+    /// Only changes to keys matching this are delivered.
+    pattern: Option<String>,
+    callback: Box<dyn FnMut(&ChangeSet) + Send>,
+}
+
+/// Polls env vars for changes and tells subscribers about them.
+///
+/// ```rust
+/// use biodome::watch::Watcher;
+///
+/// std::env::set_var("RATE_LIMIT", "100");
+/// let mut watcher = Watcher::new().keys(&["RATE_LIMIT"]);
+/// watcher.subscribe(|changes| {
+///     for change in &changes.changes {
+///         println!("{} changed to {:?}", change.key, change.new);
+///     }
+/// });
+/// std::env::set_var("RATE_LIMIT", "200");
+/// let changes = watcher.poll().unwrap();
+/// assert_eq!(changes.get("RATE_LIMIT").unwrap().new.as_deref(), Some("200"));
+/// ```
+pub struct Watcher {
+    last: BTreeMap<String, Option<String>>,
+    window: Duration,
+    /// Changes not yet delivered, by key, with the value before the
+    /// first of them.
+    pending: BTreeMap<String, Option<String>>,
+    last_change: Option<Instant>,
+    subscribers: Vec<Subscriber>,
+}
+
+impl Default for Watcher {
+    fn default() -> Self {
+        Watcher::new()
+    }
+}
+
+impl Watcher {
+    /// Watch every key that has been read through biodome so far,
+    /// starting from their current values. Changes are delivered as
+    /// soon as they are seen.
+    pub fn new() -> Self {
+        let keys: Vec<String> = registry::registry().into_iter().map(|e| e.key).collect();
+        Watcher {
+            last: snapshot(keys),
+            window: Duration::ZERO,
+            pending: BTreeMap::new(),
+            last_change: None,
+            subscribers: vec![],
+        }
+    }
+
+    /// Watch "keys" instead.
+    pub fn keys(mut self, keys: &[&str]) -> Self {
+        self.last = snapshot(keys.iter().map(|k| k.to_string()).collect());
+        self
+    }
+
+    /// Deliver changes only after no further changes have been seen
+    /// for "window", so a burst of changes is delivered together.
+    pub fn debounce(mut self, window: Duration) -> Self {
+        self.window = window;
+        self
+    }
+
+    /// Call "f" with each set of changes that is delivered.
+    pub fn subscribe(&mut self, f: impl FnMut(&ChangeSet) + Send + 'static) {
+        self.subscribers.push(Subscriber {
+            pattern: None,
+            callback: Box::new(f),
+        });
+    }
+
+    /// Call "f" with the changes to keys that match "pattern", when
+    /// there are any. The pattern is a key, or a glob where `*` matches
+    /// any run of characters and `?` any one character, so that
+    /// `METRICS_*` covers every key with that prefix.
+    ///
+    /// ```rust
+    /// use biodome::watch::Watcher;
+    ///
+    /// let mut watcher = Watcher::new().keys(&["METRICS_PORT", "HTTP_PORT"]);
+    /// watcher.subscribe_to("METRICS_*", |changes| {
+    ///     assert!(changes.changes.iter().all(|c| c.key.starts_with("METRICS_")));
+    /// });
+    /// ```
+    pub fn subscribe_to(&mut self, pattern: &str, f: impl FnMut(&ChangeSet) + Send + 'static) {
+        self.subscribers.push(Subscriber {
+            pattern: Some(pattern.to_string()),
+            callback: Box::new(f),
+        });
+    }
+
+    /// Check the keys for changes. Returns the changes delivered to
+    /// subscribers by this poll, if any were.
+    pub fn poll(&mut self) -> Option<ChangeSet> {
+        self.poll_at(Instant::now())
+    }
+
+    fn poll_at(&mut self, now: Instant) -> Option<ChangeSet> {
+        let current = snapshot(self.last.keys().cloned().collect());
+        for (key, value) in current {
+            let old = self.last.insert(key.clone(), value.clone()).flatten();
+            if old != value {
+                self.pending.entry(key).or_insert(old);
+                self.last_change = Some(now);
+            }
+        }
+        let quiet = match self.last_change {
+            Some(at) => now.duration_since(at) >= self.window,
+            None => false,
+        };
+        if !quiet {
+            return None;
+        }
+        self.last_change = None;
+        let changes = std::mem::take(&mut self.pending)
+            .into_iter()
+            .filter_map(|(key, old)| {
+                let new = self.last[&key].clone();
+                // A key that changed and changed back is left out.
+                (old != new).then_some(Change { key, old, new })
+            })
+            .collect();
+        let changes = ChangeSet { changes };
+        if changes.is_empty() {
+            return None;
+        }
+        for subscriber in &mut self.subscribers {
+            match &subscriber.pattern {
+                None => (subscriber.callback)(&changes),
+                Some(pattern) => {
+                    let matching = changes.matching(pattern);
+                    if !matching.is_empty() {
+                        (subscriber.callback)(&matching);
+                    }
+                }
+            }
+        }
+        Some(changes)
+    }
+
+    /// Poll every "interval" on a background thread, for as long as
+    /// the process runs.
+    pub fn spawn(mut self, interval: Duration) -> thread::JoinHandle<()> {
+        thread::spawn(move || loop {
+            thread::sleep(interval);
+            self.poll();
+        })
+    }
+}
+
+/// Whether "key" matches the glob "pattern", where `*` matches any run
+/// of characters and `?` any one character.
+fn glob_match(pattern: &str, key: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let key: Vec<char> = key.chars().collect();
+    let (mut p, mut k) = (0, 0);
+    // Where to resume after the last `*`, if matching fails.
+    let mut star: Option<(usize, usize)> = None;
+    while k < key.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p + 1, k));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == key[k] => {
+                p += 1;
+                k += 1;
+            }
+            _ => match star {
+                Some((sp, sk)) => {
+                    p = sp;
+                    k = sk + 1;
+                    star = Some((sp, sk + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// The current values of "keys". A key whose value can't be read, like
+/// one whose `_FILE` is missing, counts as unset.
+fn snapshot(keys: Vec<String>) -> BTreeMap<String, Option<String>> {
+    keys.into_iter()
+        .map(|key| {
+            let value = source::try_value(&key).ok().flatten();
+            (key, value)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn globs() {
+        assert!(glob_match("HTTP_PORT", "HTTP_PORT"));
+        assert!(!glob_match("HTTP_PORT", "HTTP_PORTS"));
+        assert!(glob_match("METRICS_*", "METRICS_PORT"));
+        assert!(glob_match("METRICS_*", "METRICS_"));
+        assert!(!glob_match("METRICS_*", "HTTP_METRICS_PORT"));
+        assert!(glob_match("*_PORT", "HTTP_PORT"));
+        assert!(glob_match("*_TIMEOUT_*S", "DB_TIMEOUT_MS"));
+        assert!(glob_match("DB?_URL", "DB2_URL"));
+        assert!(!glob_match("DB?_URL", "DB_URL"));
+        assert!(glob_match("*", ""));
+    }
+
+    #[test]
+    fn display() {
+        let change = |key: &str, old: Option<&str>, new: Option<&str>| Change {
+            key: key.to_string(),
+            old: old.map(String::from),
+            new: new.map(String::from),
+        };
+        let changes = ChangeSet {
+            changes: vec![
+                change("A", None, Some("1")),
+                change("B", Some("2"), None),
+                change("C", Some("3"), Some("4")),
+                change("D_PASSWORD", Some("5"), Some("6")),
+            ],
+        };
+        assert_eq!(
+            changes.to_string(),
+            "A: added '1'\nB: removed '2'\nC: '3' -> '4'\nD_PASSWORD: '[REDACTED]' -> '[REDACTED]'\n"
+        );
+    }
+
+    #[test]
+    fn filtering() {
+        env::set_var("FILTER_METRICS_PORT", "9100");
+        env::set_var("FILTER_HTTP_PORT", "80");
+        let mut watcher = Watcher::new().keys(&["FILTER_METRICS_PORT", "FILTER_HTTP_PORT"]);
+        let seen = Arc::new(Mutex::new(vec![]));
+        let (metrics, http) = (seen.clone(), seen.clone());
+        watcher.subscribe_to("FILTER_METRICS_*", move |changes| {
+            metrics
+                .lock()
+                .unwrap()
+                .push(("metrics", changes.changes.len()));
+        });
+        watcher.subscribe_to("FILTER_HTTP_*", move |changes| {
+            http.lock().unwrap().push(("http", changes.changes.len()));
+        });
+        env::set_var("FILTER_METRICS_PORT", "9101");
+        assert!(watcher.poll().is_some());
+        assert_eq!(*seen.lock().unwrap(), [("metrics", 1)]);
+    }
+
+    #[test]
+    fn coalescing() {
+        env::set_var("WATCH_A", "1");
+        env::remove_var("WATCH_B");
+        env::set_var("WATCH_C", "x");
+        let mut watcher = Watcher::new()
+            .keys(&["WATCH_A", "WATCH_B", "WATCH_C"])
+            .debounce(Duration::from_millis(100));
+        let delivered = Arc::new(Mutex::new(vec![]));
+        let sink = delivered.clone();
+        watcher.subscribe(move |changes| sink.lock().unwrap().push(changes.clone()));
+
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        assert_eq!(watcher.poll_at(at(0)), None);
+        env::set_var("WATCH_A", "2");
+        env::set_var("WATCH_C", "y");
+        assert_eq!(watcher.poll_at(at(10)), None);
+        env::set_var("WATCH_A", "3");
+        env::set_var("WATCH_B", "new");
+        env::set_var("WATCH_C", "x");
+        assert_eq!(watcher.poll_at(at(50)), None);
+        assert_eq!(watcher.poll_at(at(120)), None);
+        let changes = watcher.poll_at(at(150)).unwrap();
+        assert_eq!(
+            changes.changes,
+            vec![
+                Change {
+                    key: "WATCH_A".to_string(),
+                    old: Some("1".to_string()),
+                    new: Some("3".to_string()),
+                },
+                Change {
+                    key: "WATCH_B".to_string(),
+                    old: None,
+                    new: Some("new".to_string()),
+                },
+            ]
+        );
+        assert_eq!(*delivered.lock().unwrap(), vec![changes]);
+        assert_eq!(watcher.poll_at(at(300)), None);
+    }
+}
@@ -0,0 +1,18 @@
+use biodome::biodome;
+use std::env;
+
+// The duplicates policy is global, so this is the only test in this
+// binary.
+
+#[test]
+fn test_duplicates() {
+    use std::collections::BTreeSet;
+
+    let varname = "DUPLICATES_PORTS";
+    env::set_var(varname, "[1, 2, 2]");
+    assert_eq!(biodome(varname, BTreeSet::new()), BTreeSet::from([1, 2]));
+    biodome::set_duplicates(biodome::Duplicates::Reject);
+    let result = std::panic::catch_unwind(|| biodome(varname, BTreeSet::<i32>::new()));
+    biodome::set_duplicates(biodome::Duplicates::Dedup);
+    assert!(result.is_err());
+}
@@ -0,0 +1,102 @@
+use biodome::testing::{clear_overrides, jail, override_var, remove_override, with_vars};
+use biodome::{biodome, biodome_raw};
+use std::env;
+
+#[test]
+fn test_jail() {
+    env::set_var("JAIL_WORKERS", "2");
+    let dir = jail(|env| {
+        assert_eq!(biodome("JAIL_WORKERS", 4), 4);
+        env.set("JAIL_WORKERS", "8");
+        assert_eq!(biodome("JAIL_WORKERS", 4), 8);
+        let outside = std::thread::spawn(|| biodome("JAIL_WORKERS", 4));
+        assert_eq!(outside.join().unwrap(), 2);
+        env.remove("JAIL_WORKERS");
+        assert_eq!(biodome("JAIL_WORKERS", 4), 4);
+
+        env.dotenv(".env", "JAIL_TIMEOUT=5\nJAIL_RETRIES=1\n")
+            .unwrap();
+        let config = env
+            .config_dir("config", &[("JAIL_RETRIES", "2"), ("JAIL_HOST", "db")])
+            .unwrap();
+        env.set("JAIL_TIMEOUT", "10");
+        assert_eq!(biodome("JAIL_TIMEOUT", 30), 10);
+        assert_eq!(biodome("JAIL_RETRIES", 3), 1);
+        assert_eq!(biodome("JAIL_HOST", "localhost"), "db");
+        assert_eq!(
+            biodome_raw("JAIL_HOST").unwrap().unwrap().provenance,
+            Some(config.join("JAIL_HOST").display().to_string())
+        );
+
+        let token = env.create_file("secrets/token", "hunter2\n").unwrap();
+        env.set("JAIL_TOKEN_FILE", token.to_str().unwrap());
+        assert_eq!(biodome("JAIL_TOKEN", ""), "hunter2");
+        env.directory().to_path_buf()
+    });
+    assert!(!dir.exists());
+    assert_eq!(biodome("JAIL_WORKERS", 4), 2);
+    assert_eq!(env::var("JAIL_TIMEOUT"), Err(env::VarError::NotPresent));
+
+    // A failing test is cleaned up after too.
+    let result = std::panic::catch_unwind(|| {
+        jail(|env| {
+            env.set("JAIL_WORKERS", "8");
+            panic!("failed");
+        })
+    });
+    assert!(result.is_err());
+    assert_eq!(biodome("JAIL_WORKERS", 4), 2);
+    env::remove_var("JAIL_WORKERS");
+}
+
+#[test]
+fn test_with_vars() {
+    env::set_var("VARS_TIMEOUT", "30");
+    env::remove_var("VARS_RETRIES");
+    let (timeout, retries) = with_vars(&[("VARS_TIMEOUT", "5"), ("VARS_RETRIES", "2")], || {
+        assert_eq!(env::var("VARS_RETRIES").unwrap(), "2");
+        (biodome("VARS_TIMEOUT", 10), biodome("VARS_RETRIES", 3))
+    });
+    assert_eq!((timeout, retries), (5, 2));
+    assert_eq!(env::var("VARS_TIMEOUT").unwrap(), "30");
+    assert!(env::var("VARS_RETRIES").is_err());
+
+    let panicked = std::panic::catch_unwind(|| {
+        with_vars(&[("VARS_TIMEOUT", "1")], || panic!("test failed"));
+    });
+    assert!(panicked.is_err());
+    assert_eq!(env::var("VARS_TIMEOUT").unwrap(), "30");
+    env::remove_var("VARS_TIMEOUT");
+}
+
+#[test]
+fn test_override_var() {
+    env::set_var("OVERRIDE_TIMEOUT", "30");
+    let threads: Vec<_> = (0..4)
+        .map(|i| {
+            std::thread::spawn(move || {
+                override_var("OVERRIDE_TIMEOUT", &i.to_string());
+                assert_eq!(biodome("OVERRIDE_TIMEOUT", 10), i);
+                assert_eq!(
+                    biodome_raw("OVERRIDE_TIMEOUT")
+                        .unwrap()
+                        .unwrap()
+                        .provenance
+                        .as_deref(),
+                    Some("thread-local override")
+                );
+                remove_override("OVERRIDE_TIMEOUT");
+                biodome("OVERRIDE_TIMEOUT", 10)
+            })
+        })
+        .collect();
+    for thread in threads {
+        assert_eq!(thread.join().unwrap(), 30);
+    }
+    override_var("OVERRIDE_RETRIES", "2");
+    assert!(biodome::any_set("OVERRIDE_RETR"));
+    clear_overrides();
+    assert_eq!(biodome("OVERRIDE_RETRIES", 3), 3);
+    assert_eq!(env::var("OVERRIDE_TIMEOUT").unwrap(), "30");
+    env::remove_var("OVERRIDE_TIMEOUT");
+}
@@ -27,6 +27,7 @@ fn test_vec() {
 }
 
 #[test]
+#[allow(clippy::bool_assert_comparison)]
 fn test_bool() {
     let varname = new_varname();
     let x = biodome(&varname, true);
@@ -38,3 +39,228 @@ fn test_bool() {
     let x = biodome(&varname, true);
     assert_eq!(x, true);
 }
+
+#[test]
+fn test_enum() {
+    use biodome::FromEnvEnum;
+    use biodome::TryFromEnv;
+
+    #[derive(Debug, PartialEq, FromEnvEnum)]
+    enum Mode {
+        Dev,
+        Staging,
+        Prod,
+    }
+
+    let varname = new_varname();
+    assert_eq!(biodome(&varname, Mode::Dev), Mode::Dev);
+    env::set_var(&varname, " STAGING ");
+    assert_eq!(biodome(&varname, Mode::Dev), Mode::Staging);
+    env::set_var(&varname, r#"["prod", "Dev"]"#);
+    let modes: Vec<Mode> = biodome(&varname, vec![]);
+    assert_eq!(modes, vec![Mode::Prod, Mode::Dev]);
+    assert_eq!(
+        Mode::try_from_env("qa"),
+        Err("expected one of: dev, staging, prod".to_string())
+    );
+    assert_eq!(
+        Mode::try_from_env("stagng"),
+        Err("did you mean 'staging'?".to_string())
+    );
+    assert_eq!(Mode::to_env(&Mode::Prod), Some("prod".to_string()));
+}
+
+#[test]
+fn test_settings_derive() {
+    use biodome::Settings;
+    use biodome::{load_settings, Secret};
+
+    #[derive(Settings)]
+    struct Server {
+        /// Port the HTTP server listens on.
+        #[biodome(default = 8080)]
+        port: u16,
+        /// Filter for log output,
+        /// in `EnvFilter` syntax.
+        #[biodome(name = "LOG", default = "info")]
+        log_filter: String,
+        token: Secret<String>,
+        r#type: String,
+    }
+
+    env::set_var("DERIVED_PORT", "9000");
+    env::set_var("DERIVED_TOKEN", "Zq4!mT9#rW2$");
+    let server: Server = load_settings("DERIVED_").unwrap();
+    assert_eq!(server.port, 9000);
+    assert_eq!(server.log_filter, "info");
+    assert!(server.token.verify("Zq4!mT9#rW2$"));
+    assert_eq!(server.r#type, "");
+
+    let docs = biodome::docs::markdown();
+    assert!(docs.contains("| `DERIVED_PORT` | `u16` | `8080` | Port the HTTP server listens on. |"));
+    assert!(docs.contains(
+        "| `DERIVED_LOG` | `String` | `info` | Filter for log output, in `EnvFilter` syntax. |"
+    ));
+    assert!(docs.contains("| `DERIVED_TOKEN` | `Secret<String>` | *hidden* |  |"));
+    assert!(docs.contains("| `DERIVED_TYPE` | `String` | `` |  |"));
+
+    let schema = Server::json_schema();
+    assert_eq!(schema["title"], "Server");
+    assert_eq!(
+        schema["properties"]["PORT"],
+        biodome::serde_json::json!({
+            "type": "integer",
+            "minimum": 0,
+            "maximum": 65535,
+            "default": 8080,
+            "description": "Port the HTTP server listens on.",
+        })
+    );
+    assert_eq!(schema["properties"]["LOG"]["default"], "info");
+    assert_eq!(schema["properties"]["TOKEN"]["writeOnly"], true);
+}
+
+#[test]
+fn test_settings_flatten() {
+    use biodome::{load_settings, validate_all, Settings};
+
+    #[derive(Settings)]
+    struct RetryConfig {
+        #[biodome(default = 3)]
+        attempts: u8,
+    }
+
+    #[derive(Settings)]
+    struct TlsConfig {
+        /// Path to the certificate.
+        cert: String,
+    }
+
+    #[derive(Settings)]
+    struct Service {
+        #[biodome(default = 8080)]
+        port: u16,
+        #[biodome(flatten)]
+        retry: RetryConfig,
+        #[biodome(flatten, prefix = "TLS_")]
+        tls: TlsConfig,
+    }
+
+    env::set_var("FLAT_ATTEMPTS", "5");
+    env::set_var("FLAT_TLS_CERT", "/etc/tls/cert.pem");
+    let service: Service = load_settings("FLAT_").unwrap();
+    assert_eq!(service.port, 8080);
+    assert_eq!(service.retry.attempts, 5);
+    assert_eq!(service.tls.cert, "/etc/tls/cert.pem");
+
+    env::set_var("FLAT_ATTEMPTS", "lots");
+    let errors = validate_all::<Service>("FLAT_").err().unwrap();
+    assert_eq!(
+        errors,
+        vec![biodome::BiodomeError::Parse {
+            key: "FLAT_ATTEMPTS".to_string(),
+            reason: "\"parse error\"".to_string(),
+        }]
+    );
+
+    let schema = Service::json_schema();
+    assert_eq!(schema["title"], "Service");
+    assert_eq!(schema["properties"]["ATTEMPTS"]["default"], 3);
+    assert_eq!(
+        schema["properties"]["TLS_CERT"]["description"],
+        "Path to the certificate."
+    );
+}
+
+#[test]
+fn test_settings_default_from() {
+    use biodome::{load_settings, Settings};
+
+    #[derive(Settings)]
+    struct Timeouts {
+        #[biodome(default = 10)]
+        base_timeout: u64,
+        #[biodome(default_from = "base_timeout * 2")]
+        read_timeout: u64,
+        #[biodome(default_from = "format!(\"{}s\", read_timeout)")]
+        label: String,
+    }
+
+    let timeouts: Timeouts = load_settings("DEFAULT_FROM_").unwrap();
+    assert_eq!(timeouts.base_timeout, 10);
+    assert_eq!(timeouts.read_timeout, 20);
+
+    env::set_var("DEFAULT_FROM_BASE_TIMEOUT", "30");
+    let timeouts: Timeouts = load_settings("DEFAULT_FROM_").unwrap();
+    assert_eq!(timeouts.read_timeout, 60);
+    assert_eq!(timeouts.label, "60s");
+
+    env::set_var("DEFAULT_FROM_READ_TIMEOUT", "5");
+    let timeouts: Timeouts = load_settings("DEFAULT_FROM_").unwrap();
+    assert_eq!(timeouts.read_timeout, 5);
+    assert_eq!(timeouts.label, "5s");
+
+    let schema = Timeouts::json_schema();
+    assert_eq!(schema["properties"]["READ_TIMEOUT"]["default"], 20);
+}
+
+#[test]
+fn test_settings_tagged() {
+    use biodome::{load_settings, validate_all, BiodomeError, Settings};
+
+    #[derive(Settings)]
+    struct S3Config {
+        bucket: String,
+        #[biodome(default = "us-east-1")]
+        region: String,
+    }
+
+    #[derive(Settings)]
+    struct LocalConfig {
+        #[biodome(default = "/var/lib/app")]
+        root: String,
+    }
+
+    #[derive(Settings)]
+    #[biodome(tag = "BACKEND")]
+    enum Storage {
+        S3(S3Config),
+        #[biodome(default)]
+        Local(LocalConfig),
+        Memory,
+    }
+
+    let storage: Storage = load_settings("TAGGED_").unwrap();
+    assert!(matches!(storage, Storage::Local(local) if local.root == "/var/lib/app"));
+
+    env::set_var("TAGGED_BACKEND", "S3");
+    env::set_var("TAGGED_S3_BUCKET", "assets");
+    env::set_var("TAGGED_LOCAL_ROOT", "/ignored");
+    let Storage::S3(s3) = load_settings("TAGGED_").unwrap() else {
+        panic!("expected s3");
+    };
+    assert_eq!(s3.bucket, "assets");
+    assert_eq!(s3.region, "us-east-1");
+
+    env::set_var("TAGGED_BACKEND", "memory");
+    assert!(matches!(load_settings("TAGGED_"), Ok(Storage::Memory)));
+
+    env::set_var("TAGGED_BACKEND", "gcs");
+    let errors = validate_all::<Storage>("TAGGED_").err().unwrap();
+    assert!(matches!(
+        &errors[..],
+        [BiodomeError::NotOneOf { key, .. }] if key == "TAGGED_BACKEND"
+    ));
+
+    let schema = Storage::json_schema();
+    assert_eq!(
+        schema["properties"]["BACKEND"]["enum"],
+        biodome::serde_json::json!(["s3", "local", "memory"])
+    );
+    assert_eq!(schema["properties"]["BACKEND"]["default"], "local");
+    assert_eq!(schema["properties"]["S3_REGION"]["default"], "us-east-1");
+    assert_eq!(
+        schema["properties"]["LOCAL_ROOT"]["default"],
+        "/var/lib/app"
+    );
+}
@@ -0,0 +1,43 @@
+use biodome::{
+    biodome, biodome_validated, reset_observer, set_observer, BiodomeError, BiodomeObserver,
+};
+use std::sync::{Arc, Mutex};
+
+#[derive(Clone, Default)]
+struct Audit(Arc<Mutex<Vec<String>>>);
+
+impl BiodomeObserver for Audit {
+    fn on_read(&self, key: &str) {
+        self.0.lock().unwrap().push(format!("read {}", key));
+    }
+
+    fn on_default_used(&self, key: &str) {
+        self.0.lock().unwrap().push(format!("default {}", key));
+    }
+
+    fn on_parse_error(&self, key: &str, error: &BiodomeError) {
+        assert!(matches!(error, BiodomeError::Parse { .. }));
+        self.0.lock().unwrap().push(format!("error {}", key));
+    }
+}
+
+#[test]
+fn test_observer() {
+    let audit = Audit::default();
+    set_observer(audit.clone());
+    std::env::set_var("OBSERVED_PORT", "9000");
+    std::env::set_var("OBSERVED_WORKERS", "lots");
+    biodome("OBSERVED_PORT", 8080);
+    biodome("OBSERVED_TIMEOUT", 30);
+    let _ = biodome_validated("OBSERVED_WORKERS", 4, |_: &i32| true);
+    reset_observer();
+    biodome("OBSERVED_PORT", 8080);
+    assert_eq!(
+        *audit.0.lock().unwrap(),
+        vec![
+            "read OBSERVED_PORT",
+            "default OBSERVED_TIMEOUT",
+            "error OBSERVED_WORKERS",
+        ]
+    );
+}
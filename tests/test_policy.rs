@@ -0,0 +1,42 @@
+use std::collections::HashMap;
+use std::io::ErrorKind;
+
+use biodome::{allow_sources, biodome, BiodomeError, Layers, StdEnv};
+
+#[test]
+fn test_source_policy() {
+    let dir = std::env::temp_dir().join(format!("biodome-policy-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(".env");
+    std::fs::write(&path, "POLICY_WORKERS=8\n").unwrap();
+
+    allow_sources("prod", &["env"]);
+    biodome::set_profile("prod");
+    let err = Layers::new()
+        .with(StdEnv)
+        .try_with(HashMap::<String, String>::new())
+        .err()
+        .unwrap();
+    assert_eq!(
+        err,
+        BiodomeError::Forbidden {
+            kind: "memory".to_string(),
+            profile: "prod".to_string(),
+        }
+    );
+    let err = biodome::dotenv::load(&path).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::PermissionDenied);
+    assert_eq!(biodome("POLICY_WORKERS", 4), 4);
+    let refused = std::panic::catch_unwind(|| biodome::set_source(biodome::ConfigDir::new(&dir)));
+    assert!(refused.is_err());
+    biodome::set_source(Layers::new().with(StdEnv));
+
+    biodome::set_profile("dev");
+    biodome::dotenv::load(&path).unwrap();
+    assert_eq!(biodome("POLICY_WORKERS", 4), 8);
+
+    biodome::reset_source();
+    biodome::reset_profile();
+    biodome::reset_source_policy();
+    std::fs::remove_dir_all(&dir).unwrap();
+}
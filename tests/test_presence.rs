@@ -0,0 +1,24 @@
+use biodome::{any_set, biodome, is_set, set_empty_values, EmptyValues};
+use std::env;
+
+#[test]
+fn test_presence() {
+    env::set_var("PRESENCE_DSN", "https://sentry.example/1");
+    env::set_var("PRESENCE_TOKEN_FILE", "/run/secrets/token");
+    env::set_var("PRESENCE_EMPTY", "");
+    env::set_var("PRESENCE_OTHER_EMPTY", "");
+    assert!(is_set("PRESENCE_DSN"));
+    assert!(is_set("PRESENCE_TOKEN"));
+    assert!(!is_set("PRESENCE_MISSING"));
+    assert!(is_set("PRESENCE_EMPTY"));
+    assert!(any_set("PRESENCE_OTHER_"));
+    assert!(!any_set("NO_SUCH_PREFIX_"));
+    assert_eq!(biodome("PRESENCE_EMPTY", "x".to_string()), "");
+
+    set_empty_values(EmptyValues::Unset);
+    assert!(!is_set("PRESENCE_EMPTY"));
+    assert!(!any_set("PRESENCE_OTHER_"));
+    assert!(any_set("PRESENCE_"));
+    assert_eq!(biodome("PRESENCE_EMPTY", "x".to_string()), "x");
+    set_empty_values(EmptyValues::Value);
+}
@@ -0,0 +1,39 @@
+use biodome::biodome;
+
+#[test]
+fn test_profile() {
+    std::env::set_var("TIMEOUT", "10;staging:20;prod:30");
+    std::env::set_var("REPLICAS", "dev:1;prod:3");
+    std::env::set_var("DSN", "Server=db;Database=app");
+    std::env::set_var("SENTINELS", "host1:26379;host2:26379");
+    std::env::set_var("HDRS", "a;X-Foo:1");
+    assert_eq!(biodome::profile(), None);
+    assert_eq!(biodome("REPLICAS", ""), "dev:1;prod:3");
+
+    biodome::set_profiles(&["dev", "staging", "prod"]);
+    std::env::set_var("BIODOME_PROFILE", "staging");
+    assert_eq!(biodome("TIMEOUT", 5), 20);
+    assert_eq!(biodome("REPLICAS", ""), "dev:1;prod:3");
+
+    biodome::set_profile("prod");
+    assert_eq!(biodome("TIMEOUT", 5), 30);
+    assert_eq!(biodome("REPLICAS", 2), 3);
+    assert_eq!(biodome("DSN", ""), "Server=db;Database=app");
+    assert_eq!(biodome("SENTINELS", ""), "host1:26379;host2:26379");
+    assert_eq!(biodome("HDRS", ""), "a;X-Foo:1");
+    assert_eq!(
+        biodome::biodome_one_of("REPLICAS", "1", &["1", "3"]).unwrap(),
+        "3"
+    );
+
+    let digest = biodome::config_digest(&["TIMEOUT"]);
+    biodome::set_profile("qa");
+    assert_eq!(biodome("TIMEOUT", 5), 10);
+    assert_ne!(biodome::config_digest(&["TIMEOUT"]), digest);
+
+    biodome::reset_profile();
+    assert_eq!(biodome::profile(), Some("staging".to_string()));
+    std::env::remove_var("BIODOME_PROFILE");
+    biodome::set_profiles(&[]);
+    assert_eq!(biodome("TIMEOUT", ""), "10;staging:20;prod:30");
+}
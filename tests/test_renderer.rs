@@ -0,0 +1,57 @@
+use biodome::{biodome, biodome_one_of, BiodomeError, ErrorRenderer};
+use std::env;
+
+// The renderer is global, so this is the only test in this binary.
+
+struct Terse;
+
+impl ErrorRenderer for Terse {
+    fn render(&self, error: &BiodomeError) -> String {
+        match error {
+            BiodomeError::Parse { key, .. } => format!("{}: bad value", key),
+            BiodomeError::Missing { key } => format!("{}: not set", key),
+            BiodomeError::Invalid { key, .. } => format!("{}: refused", key),
+            BiodomeError::NotOneOf { key, value, .. } => {
+                format!("{}: '{}' not allowed", key, value)
+            }
+            BiodomeError::Collision { key, .. } => format!("{}: read twice", key),
+            BiodomeError::Forbidden { kind, .. } => format!("{}: not allowed", kind),
+        }
+    }
+}
+
+struct Panicky;
+
+impl ErrorRenderer for Panicky {
+    fn render(&self, _error: &BiodomeError) -> String {
+        panic!("renderer failed")
+    }
+}
+
+#[test]
+fn test_renderer() {
+    let varname = "RENDERER_FORMAT";
+    env::set_var(varname, "xml");
+    biodome::set_renderer(Terse);
+    let err = biodome_one_of(varname, "json", &["json"]).unwrap_err();
+    assert_eq!(err.to_string(), format!("{}: 'xml' not allowed", varname));
+    assert!(matches!(err, BiodomeError::NotOneOf { .. }));
+
+    let result = std::panic::catch_unwind(|| biodome(varname, 1));
+    biodome::reset_renderer();
+    let msg = *result.unwrap_err().downcast::<String>().unwrap();
+    assert_eq!(msg, format!("{}: bad value", varname));
+
+    // A renderer that panics leaves the next messages working.
+    biodome::set_renderer(Panicky);
+    let err = biodome_one_of(varname, "json", &["json"]).unwrap_err();
+    assert!(std::panic::catch_unwind(|| err.to_string()).is_err());
+    biodome::reset_renderer();
+    assert_eq!(
+        err.to_string(),
+        format!(
+            "Invalid value for env var {}: got 'xml', expected one of: json",
+            varname
+        )
+    );
+}
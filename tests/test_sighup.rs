@@ -0,0 +1,52 @@
+#![cfg(all(unix, feature = "sighup"))]
+
+use biodome::reload::{reload_on_sighup, ReloadEvent, Reloadable};
+use biodome::{Scope, Settings};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::Duration;
+
+struct Pool {
+    size: u32,
+}
+
+impl Settings for Pool {
+    fn load(scope: &Scope) -> Self {
+        Pool {
+            size: scope.biodome("SIZE", 8u32),
+        }
+    }
+}
+
+#[test]
+fn test_reload_on_sighup() {
+    let path = std::env::temp_dir().join(format!("biodome-sighup-{}.env", std::process::id()));
+    std::fs::write(&path, "SIGHUP_SIZE=16\n").unwrap();
+    biodome::dotenv::load(&path).unwrap();
+
+    let pool = Arc::new(Reloadable::<Pool>::new("SIGHUP_").unwrap());
+    assert_eq!(pool.load().size, 16);
+    let (tx, rx) = mpsc::channel();
+    let tx = Mutex::new(tx);
+    pool.on_event(move |event| tx.lock().unwrap().send(event.clone()).unwrap());
+    reload_on_sighup(&pool).unwrap();
+    let hangup = || {
+        signal_hook::low_level::raise(signal_hook::consts::SIGHUP).unwrap();
+        rx.recv_timeout(Duration::from_secs(5)).unwrap()
+    };
+
+    std::fs::write(&path, "SIGHUP_SIZE=32\n").unwrap();
+    assert_eq!(hangup(), ReloadEvent::Applied);
+    assert_eq!(pool.load().size, 32);
+
+    // Bad values are not swapped in.
+    std::fs::write(&path, "SIGHUP_SIZE=lots\n").unwrap();
+    assert!(matches!(hangup(), ReloadEvent::Rejected(_)));
+    assert_eq!(pool.load().size, 32);
+
+    // Neither is anything, when the file can't be read.
+    std::fs::remove_file(&path).unwrap();
+    assert!(matches!(hangup(), ReloadEvent::Rejected(_)));
+    assert_eq!(pool.load().size, 32);
+
+    biodome::reset_source();
+}
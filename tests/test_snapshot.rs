@@ -0,0 +1,56 @@
+use biodome::snapshot::boot_from_last_known_good;
+use biodome::{Scope, Secret, Settings};
+use std::env;
+
+struct Server {
+    port: u16,
+    workers: u8,
+    token: Secret<String>,
+}
+
+impl Settings for Server {
+    fn load(scope: &Scope) -> Self {
+        Server {
+            port: scope.biodome("PORT", 8080),
+            workers: scope.biodome("WORKERS", 4),
+            token: scope.biodome("TOKEN", Secret::new(String::new())),
+        }
+    }
+}
+
+#[test]
+fn test_last_known_good() {
+    let path = env::temp_dir().join(format!("biodome-snapshot-{}.env", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+
+    env::set_var("LKG_WORKERS", "300");
+    assert_eq!(
+        boot_from_last_known_good::<Server, _>("LKG_", &path)
+            .err()
+            .unwrap()
+            .len(),
+        1
+    );
+
+    env::set_var("LKG_PORT", "9000");
+    env::set_var("LKG_WORKERS", "8");
+    env::set_var("LKG_TOKEN", "hunter2");
+    let server: Server = boot_from_last_known_good("LKG_", &path).unwrap();
+    assert_eq!(server.port, 9000);
+    let text = std::fs::read_to_string(&path).unwrap();
+    assert!(text.contains("LKG_PORT=\"9000\"\n"));
+    assert!(text.contains("LKG_WORKERS=\"8\"\n"));
+    assert!(!text.contains("hunter2"));
+
+    // A bad deploy: the snapshot stands in for the bad value, and the
+    // secret still comes from the environment.
+    env::set_var("LKG_PORT", "ninety");
+    env::set_var("LKG_TOKEN", "hunter3");
+    let server: Server = boot_from_last_known_good("LKG_", &path).unwrap();
+    assert_eq!(server.port, 9000);
+    assert_eq!(server.workers, 8);
+    assert_eq!(server.token.expose(), "hunter3");
+
+    biodome::reset_source();
+    std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,38 @@
+use biodome::{biodome, EnvSource};
+use std::collections::HashMap;
+
+struct Prefixed(HashMap<String, String>);
+
+impl EnvSource for Prefixed {
+    fn get(&self, key: &str) -> Option<String> {
+        self.0.get(&format!("APP_{}", key)).cloned()
+    }
+}
+
+#[test]
+fn test_set_source() {
+    let vars = HashMap::from([
+        ("APP_WORKERS".to_string(), "8".to_string()),
+        ("APP_HOME".to_string(), "/srv/app".to_string()),
+        ("APP_DATA_DIR".to_string(), "~/data".to_string()),
+    ]);
+    biodome::set_source(Prefixed(vars));
+    assert_eq!(biodome("WORKERS", 4), 8);
+    assert_eq!(biodome("TIMEOUT", 30), 30);
+    assert_eq!(
+        biodome("DATA_DIR", std::path::PathBuf::new()),
+        std::path::PathBuf::from("/srv/app/data")
+    );
+    biodome::reset_source();
+    std::env::set_var("WORKERS", "2");
+    assert_eq!(biodome("WORKERS", 4), 2);
+
+    // The process environment wins over a .env file.
+    let path = std::env::temp_dir().join(format!("biodome-{}.env", std::process::id()));
+    std::fs::write(&path, "export WORKERS=16\nGREETING='hello # world'\n").unwrap();
+    biodome::dotenv::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(biodome("WORKERS", 4), 2);
+    assert_eq!(biodome("GREETING", "hi"), "hello # world");
+    biodome::reset_source();
+}
@@ -0,0 +1,50 @@
+#![cfg(feature = "watch")]
+
+use biodome::reload::{reload_on_change, ReloadEvent, Reloadable};
+use biodome::{ConfigDir, Scope, Settings};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::Duration;
+
+struct Pool {
+    size: u32,
+}
+
+impl Settings for Pool {
+    fn load(scope: &Scope) -> Self {
+        Pool {
+            size: scope.biodome("SIZE", 8u32),
+        }
+    }
+}
+
+#[test]
+fn test_reload_on_change() {
+    let dir = std::env::temp_dir().join(format!("biodome-watch-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("WATCH_SIZE"), "16\n").unwrap();
+    biodome::set_source(ConfigDir::new(&dir));
+
+    let pool = Arc::new(Reloadable::<Pool>::new("WATCH_").unwrap());
+    assert_eq!(pool.load().size, 16);
+    let (tx, rx) = mpsc::channel();
+    let tx = Mutex::new(tx);
+    pool.on_event(move |event| tx.lock().unwrap().send(event.clone()).unwrap());
+    let watcher = reload_on_change(&pool, Duration::from_millis(50)).unwrap();
+
+    std::fs::write(dir.join("WATCH_SIZE"), "32\n").unwrap();
+    let event = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert_eq!(event, ReloadEvent::Applied);
+    assert_eq!(pool.load().size, 32);
+
+    // Bad values are not swapped in.
+    // A single write can be seen as more than one change, so skip
+    // any reloads left over from the one above.
+    std::fs::write(dir.join("WATCH_SIZE"), "lots\n").unwrap();
+    while rx.recv_timeout(Duration::from_secs(5)).unwrap() == ReloadEvent::Applied {}
+    assert_eq!(pool.load().size, 32);
+
+    drop(watcher);
+    biodome::reset_source();
+    assert!(reload_on_change(&pool, Duration::from_millis(50)).is_err());
+    std::fs::remove_dir_all(&dir).unwrap();
+}